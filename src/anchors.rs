@@ -0,0 +1,77 @@
+use colored::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::cache_dir;
+
+/// Where the last `--anchors` run left its index → path table
+fn anchors_path() -> PathBuf {
+    cache_dir().join("anchors.txt")
+}
+
+/// `--anchors`: a `1.2.3` style index for every directory drawn, numbered in
+/// display order below each parent. Saved at the end of the run so
+/// `struct goto 1.2.3` can resolve an index from the tree on screen.
+#[derive(Default)]
+pub struct Anchors {
+    /// Anchor of each numbered directory (the start dir is the empty anchor)
+    by_dir: HashMap<PathBuf, String>,
+    /// Children numbered so far under each directory
+    next: HashMap<PathBuf, usize>,
+    /// Every anchor handed out, in order, with its absolute path
+    assigned: Vec<(String, PathBuf)>,
+}
+
+impl Anchors {
+    /// Number the directory `path` as the next child of its parent
+    pub fn assign(&mut self, path: &Path) -> String {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let n = self.next.entry(parent.clone()).or_insert(0);
+        *n += 1;
+        let anchor = match self.by_dir.get(&parent) {
+            Some(base) if !base.is_empty() => format!("{}.{}", base, n),
+            _ => n.to_string(),
+        };
+        self.by_dir.insert(path.clone(), anchor.clone());
+        self.assigned.push((anchor.clone(), path));
+        anchor
+    }
+
+    /// Write the table for `struct goto`, replacing the previous run's
+    pub fn save(&self) -> std::io::Result<()> {
+        let file = anchors_path();
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lines: Vec<String> =
+            self.assigned.iter().map(|(anchor, path)| format!("{}\t{}", anchor, path.display())).collect();
+        fs::write(file, lines.join("\n") + "\n")
+    }
+}
+
+/// `struct goto ANCHOR`: print the absolute path the last `--anchors` run
+/// showed under ANCHOR. Returns false (after an error on stderr) when unknown.
+pub fn goto_anchor(anchor: &str) -> bool {
+    let wanted = anchor.trim().trim_end_matches('.');
+    let Ok(content) = fs::read_to_string(anchors_path()) else {
+        eprintln!("error: no anchors saved yet — run struct with --anchors first");
+        return false;
+    };
+    let found = content
+        .lines()
+        .filter_map(|l| l.split_once('\t'))
+        .find(|(a, _)| *a == wanted)
+        .map(|(_, path)| path.to_string());
+    match found {
+        Some(path) => {
+            println!("{}", path);
+            true
+        }
+        None => {
+            eprintln!("error: no directory {} in the last --anchors tree", wanted.yellow());
+            false
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use colored::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::load_config_settings_with_prefix;
+use crate::entry::Entry;
+use crate::utils::truncate_to_width;
+
+/// Sent as `version` in every request; bumped only for changes an existing
+/// annotator would misread
+pub const PROTOCOL_VERSION: u64 = 1;
+
+/// An annotator still running after this is killed and its output dropped
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest annotation drawn, in characters, and how many per entry
+const MAX_WIDTH: usize = 40;
+const MAX_PER_ENTRY: usize = 3;
+
+/// `annotator.NAME = "command"` lines of the config file, in file order
+pub fn configured_annotators() -> Vec<(String, String)> {
+    load_config_settings_with_prefix("annotator.")
+        .into_iter()
+        .map(|(key, command)| (key.trim_start_matches("annotator.").to_string(), command))
+        .collect()
+}
+
+/// --annotate: short labels from external commands, drawn after entries.
+///
+/// Each annotator runs once per tree through the shell, gets every listed
+/// entry as one JSON document on stdin and answers with one on stdout:
+///
+/// ```text
+/// → {"version": 1, "root": "/abs/root", "entries": [{"path": "src/main.rs", "type": "file", "size": 1234}, ...]}
+/// ← {"annotations": {"src/main.rs": "✓ built", "src": ["PROJ-12", "owner: ana"]}}
+/// ```
+///
+/// Paths are relative to the root and `/`-separated (`type` is `file`,
+/// `dir` or `symlink`; `size` is left out for dirs). Entries without an
+/// annotation are simply left out of the answer, and unknown keys are
+/// ignored on both sides so either end can grow.
+pub struct ExternalAnnotations {
+    root: PathBuf,
+    by_path: HashMap<String, Vec<String>>,
+}
+
+/// Path relative to `root`, `/`-separated, as annotators see it
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Run `command` with `input` on stdin; its stdout, or why there is none
+fn run(command: &str, cwd: &Path, input: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut child = shell(command)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("cannot run: {}", e))?;
+
+    // Feed and drain on threads, so a large tree can't deadlock on full pipes
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || {
+        if let Some(ref mut stdin) = stdin {
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let mut stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || {
+        let mut out = Vec::new();
+        if let Some(ref mut stdout) = stdout {
+            let _ = stdout.read_to_end(&mut out);
+        }
+        out
+    });
+
+    let deadline = Instant::now() + TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("no answer within {}s", TIMEOUT.as_secs()));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+    let _ = writer.join();
+    let out = reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(format!("exited with {}", status));
+    }
+    Ok(out)
+}
+
+/// One annotation as drawn: control characters dropped, cut to `MAX_WIDTH`
+fn clean(label: &str) -> Option<String> {
+    let label: String = label.chars().filter(|c| !c.is_control()).collect();
+    let label = label.trim();
+    (!label.is_empty()).then(|| truncate_to_width(label, MAX_WIDTH))
+}
+
+impl ExternalAnnotations {
+    /// Ask each `(name, command)` about `entries` (the tree drawn from `root`).
+    /// An annotator that fails is reported on stderr and skipped.
+    pub fn collect(annotators: &[(String, String)], root: &Path, entries: &[Entry]) -> Self {
+        let listed: Vec<Value> = entries
+            .iter()
+            .map(|e| {
+                let kind = if e.is_symlink() {
+                    "symlink"
+                } else if e.is_dir() {
+                    "dir"
+                } else {
+                    "file"
+                };
+                let mut item = json!({ "path": relative(root, &e.path), "type": kind });
+                if kind == "file" {
+                    item["size"] = json!(e.size());
+                }
+                item
+            })
+            .collect();
+        let abs_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let request = json!({ "version": PROTOCOL_VERSION, "root": abs_root.display().to_string(), "entries": listed });
+        let input = request.to_string().into_bytes();
+
+        let mut by_path: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, command) in annotators {
+            let answer = run(command, &abs_root, input.clone()).and_then(|out| {
+                serde_json::from_slice::<Value>(&out).map_err(|e| format!("answer is not JSON: {}", e))
+            });
+            let answer = match answer {
+                Ok(answer) => answer,
+                Err(e) => {
+                    eprintln!("warning: annotator {}: {}", name, e);
+                    continue;
+                }
+            };
+            let Some(annotations) = answer.get("annotations").and_then(Value::as_object) else {
+                eprintln!("warning: annotator {}: answer has no \"annotations\" object", name);
+                continue;
+            };
+            for (path, value) in annotations {
+                let labels: Vec<&str> = match value {
+                    Value::String(label) => vec![label.as_str()],
+                    Value::Array(labels) => labels.iter().filter_map(Value::as_str).collect(),
+                    _ => continue,
+                };
+                let path = path.trim_matches('/').to_string();
+                by_path.entry(path).or_default().extend(labels.into_iter().filter_map(clean));
+            }
+        }
+        ExternalAnnotations { root: root.to_path_buf(), by_path }
+    }
+
+    /// Tags for an entry of the tree (at most `MAX_PER_ENTRY`)
+    pub fn tags(&self, path: &Path) -> Vec<ColoredString> {
+        self.by_path
+            .get(&relative(&self.root, path))
+            .map(|labels| labels.iter().take(MAX_PER_ENTRY).map(|l| l.magenta()).collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,91 @@
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// What an archive or the live tree holds at one path: a file's size, or None
+/// for a symlink (only its presence is compared)
+pub type Listing = BTreeMap<String, Option<u64>>;
+
+/// Whether `name` is an archive `read_archive` can list
+pub fn is_archive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [".zip", ".jar", ".tar", ".tar.gz", ".tgz"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Files and symlinks of a zip, jar, tar or tar.gz, keyed by their path inside it
+pub fn read_archive(path: &Path) -> Result<Listing, String> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let file = fs::File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    let corrupt = |e: &dyn std::fmt::Display| format!("cannot read {}: {}", path.display(), e);
+
+    let mut listing = Listing::new();
+    if name.ends_with(".zip") || name.ends_with(".jar") {
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| corrupt(&e))?;
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).map_err(|e| corrupt(&e))?;
+            if entry.is_dir() {
+                continue;
+            }
+            let size = (!entry.is_symlink()).then(|| entry.size());
+            listing.insert(entry.name().to_string(), size);
+        }
+        return Ok(listing);
+    }
+
+    let reader: Box<dyn Read> = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Box::new(GzDecoder::new(file))
+    } else if name.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        return Err(format!(
+            "can't tell the archive format of {} (use .zip, .jar, .tar, .tar.gz or .tgz)",
+            path.display()
+        ));
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(|e| corrupt(&e))? {
+        let entry = entry.map_err(|e| corrupt(&e))?;
+        let kind = entry.header().entry_type();
+        let size = if kind.is_file() {
+            Some(entry.header().size().unwrap_or(0))
+        } else if kind.is_symlink() {
+            None
+        } else {
+            continue;
+        };
+        let name = entry.path().map_err(|e| corrupt(&e))?.to_string_lossy().to_string();
+        listing.insert(name, size);
+    }
+    Ok(listing)
+}
+
+/// An archive's listing as a directory tree, for `--archives`
+#[derive(Default)]
+pub struct ArchiveDir {
+    pub dirs: BTreeMap<String, ArchiveDir>,
+    /// File name → size (None for a symlink)
+    pub files: BTreeMap<String, Option<u64>>,
+}
+
+impl ArchiveDir {
+    pub fn from_listing(listing: &Listing) -> ArchiveDir {
+        let mut root = ArchiveDir::default();
+        for (path, size) in listing {
+            let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+            let Some(file) = parts.pop() else { continue };
+            let dir = parts.into_iter().fold(&mut root, |dir, part| dir.dirs.entry(part.to_string()).or_default());
+            dir.files.insert(file.to_string(), *size);
+        }
+        root
+    }
+
+    /// Files anywhere below, and their total size
+    pub fn totals(&self) -> (usize, u64) {
+        self.dirs.values().map(ArchiveDir::totals).fold(
+            (self.files.len(), self.files.values().flatten().sum()),
+            |(files, bytes), (f, b)| (files + f, bytes + b),
+        )
+    }
+}
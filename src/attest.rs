@@ -0,0 +1,100 @@
+use colored::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::entry::Entry;
+use crate::utils::{format_count, shell_quote};
+
+/// Bumped whenever the canonical form below changes, so old footers never
+/// silently match new ones
+const FORMAT: &str = "struct-attest v1";
+
+/// --attest: one hash over everything the tree listed and how it was asked
+/// for, so two people can compare directory states by comparing one line
+pub struct Attestation {
+    pub digest: String,
+    pub files: usize,
+    pub dirs: usize,
+    pub params: Vec<String>,
+}
+
+/// Hex sha256 of a file's contents
+pub fn sha256_file(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf).ok()? {
+            0 => break,
+            n => hasher.update(&buf[..n]),
+        }
+    }
+    Some(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Attestation {
+    /// Hash the listed entries of the tree drawn from `root` with `params`.
+    ///
+    /// The canonical form is the format line, the parameters, then one line
+    /// per entry sorted by path (relative to the root, `/`-separated):
+    /// `d PATH` for a directory, `f PATH SIZE SHA256` for a file, `l PATH
+    /// TARGET` for a symlink, tab-separated. The root's own location is left
+    /// out, so two checkouts in different places compare equal.
+    pub fn new(root: &Path, entries: &[Entry], params: Vec<String>) -> Attestation {
+        let mut lines: Vec<String> = Vec::with_capacity(entries.len());
+        let (mut files, mut dirs) = (0, 0);
+        for entry in entries {
+            let rel = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+            let rel = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            if entry.is_symlink() {
+                let target = fs::read_link(&entry.path).map(|t| t.display().to_string()).unwrap_or_default();
+                lines.push(format!("l\t{}\t{}", rel, target));
+                files += 1;
+            } else if entry.is_dir() {
+                lines.push(format!("d\t{}", rel));
+                dirs += 1;
+            } else {
+                let digest = sha256_file(&entry.path).unwrap_or_else(|| "unreadable".to_string());
+                lines.push(format!("f\t{}\t{}\t{}", rel, entry.size(), digest));
+                files += 1;
+            }
+        }
+        lines.sort_by(|a, b| a.split('\t').nth(1).cmp(&b.split('\t').nth(1)));
+
+        let mut hasher = Sha256::new();
+        hasher.update(FORMAT.as_bytes());
+        hasher.update(format!("\nparams\t{}\n", params.join("\t")).as_bytes());
+        for line in &lines {
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+        }
+        Attestation { digest: hex(&hasher.finalize()), files, dirs, params }
+    }
+
+    /// `attest sha256:… · 12 files, 3 dirs · params: -L 2 -i '*.log'`
+    pub fn print_footer(&self) {
+        let params = if self.params.is_empty() {
+            "none".to_string()
+        } else {
+            self.params.iter().map(|p| shell_quote(p)).collect::<Vec<_>>().join(" ")
+        };
+        println!(
+            "\n{} {} {}",
+            "attest".bright_black(),
+            format!("sha256:{}", self.digest).bold(),
+            format!(
+                "· {} files, {} dirs · params: {}",
+                format_count(self.files),
+                format_count(self.dirs),
+                params
+            )
+            .bright_black()
+        );
+    }
+}
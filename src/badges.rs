@@ -0,0 +1,164 @@
+use colored::*;
+use git2::{Repository, StatusOptions};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::utils::{get_dir_size, keep_offline};
+
+/// Default size above which a directory gets the `oversized` badge
+pub const DEFAULT_BADGE_SIZE_MB: u64 = 100;
+
+/// File names that usually hold credentials
+const SECRET_NAMES: &[&str] = &[
+    ".env", ".npmrc", ".pypirc", ".netrc", ".htpasswd", "credentials", "credentials.json",
+    "secrets.json", "secrets.yml", "secrets.yaml", "id_rsa", "id_dsa", "id_ecdsa", "id_ed25519",
+];
+
+/// Extensions of key / certificate stores
+const SECRET_EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx", "keystore", "jks", "ppk"];
+
+/// Whether a file name looks like it holds secrets (`.env.local`, `server.key`, `id_rsa`, ...)
+fn is_secret_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    if SECRET_NAMES.contains(&lower.as_str()) || lower.starts_with(".env.") {
+        // .env.example / .env.sample are templates, not secrets
+        return !lower.ends_with(".example") && !lower.ends_with(".sample");
+    }
+    match lower.rsplit_once('.') {
+        Some((stem, ext)) => !stem.is_empty() && SECRET_EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
+
+/// Directory health indicators for `--badges`:
+///
+///   ●  uncommitted changes (modified, staged or untracked files inside)
+///   ▲  larger than the badge threshold
+///   ⚠  contains files that look like secrets (.env, *.pem, id_rsa, ...)
+///   ∅  empty
+pub struct Badges {
+    /// Every dirty path in the repo, or None outside a git repo
+    dirty: Option<HashSet<PathBuf>>,
+    large_bytes: u64,
+}
+
+impl Badges {
+    pub fn new(path: &Path, large_mb: u64) -> Self {
+        Badges { dirty: dirty_paths(path), large_bytes: large_mb * 1024 * 1024 }
+    }
+
+    /// Badges for a directory, in display order (empty when it has none)
+    pub fn for_dir(&self, path: &Path, ignores: &IgnoreRules) -> Vec<ColoredString> {
+        let mut badges: Vec<ColoredString> = Vec::new();
+
+        if let Some(ref dirty) = self.dirty {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            if dirty.iter().any(|p| p.starts_with(&canonical)) {
+                badges.push("●".yellow());
+            }
+        }
+
+        let is_empty = fs::read_dir(path).map(|mut e| e.next().is_none()).unwrap_or(false);
+        if is_empty {
+            badges.push("∅".bright_black());
+        } else {
+            if get_dir_size(path) > self.large_bytes {
+                badges.push("▲".red());
+            }
+            if contains_secrets(path, ignores) {
+                badges.push("⚠".magenta().bold());
+            }
+        }
+
+        badges
+    }
+}
+
+/// Files that make a directory look like a (sub)project, so --meta-badges marks
+/// it even when it has none of the meta files yet
+const PROJECT_MARKERS: &[&str] = &[
+    ".git", "Cargo.toml", "package.json", "go.mod", "pyproject.toml", "setup.py", "pom.xml",
+    "build.gradle", "build.gradle.kts", "Gemfile", "composer.json", "mix.exs", "CMakeLists.txt",
+];
+
+/// Single-file CI configs at a project root
+const CI_FILES: &[&str] = &[
+    ".gitlab-ci.yml", ".travis.yml", "Jenkinsfile", "azure-pipelines.yml", "bitbucket-pipelines.yml",
+    ".drone.yml", "appveyor.yml",
+];
+
+/// Directories whose contents are CI config (`.github` only counts with workflows inside)
+const CI_DIRS: &[&str] = &[".github/workflows", ".circleci", ".buildkite", ".woodpecker"];
+
+/// Project meta files for `--meta-badges`, in slot order:
+///
+///   R  README        L  LICENSE (or LICENCE, COPYING)
+///   C  CONTRIBUTING  ⚙  CI config (.github/workflows, .gitlab-ci.yml, ...)
+///
+/// A project dir gets all four slots, with `·` for the ones it lacks, so the
+/// gaps line up down the tree. Dirs that are neither projects nor hold any
+/// meta file get nothing.
+pub fn meta_badges(path: &Path) -> Vec<ColoredString> {
+    let Ok(entries) = fs::read_dir(path) else { return Vec::new() };
+    let names: Vec<String> = entries.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().to_string()).collect();
+    let has_prefix = |prefixes: &[&str]| {
+        names.iter().any(|n| {
+            let lower = n.to_lowercase();
+            prefixes.iter().any(|p| lower.starts_with(p))
+        })
+    };
+
+    let slots = [
+        ("R", has_prefix(&["readme"])),
+        ("L", has_prefix(&["license", "licence", "copying"])),
+        ("C", has_prefix(&["contributing"])),
+        (
+            "⚙",
+            names.iter().any(|n| CI_FILES.contains(&n.as_str()))
+                || CI_DIRS.iter().any(|d| path.join(d).is_dir()),
+        ),
+    ];
+    let is_project = names.iter().any(|n| PROJECT_MARKERS.contains(&n.as_str()));
+    if !is_project && !slots.iter().any(|&(_, present)| present) {
+        return Vec::new();
+    }
+    slots
+        .iter()
+        .map(|&(symbol, present)| if present { symbol.green() } else { "·".bright_black() })
+        .collect()
+}
+
+/// Whether any file below `path` looks like a secret (ignored dirs are not searched)
+fn contains_secrets(path: &Path, ignores: &IgnoreRules) -> bool {
+    WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || !(ignores.ignores_dir(&e.file_name().to_string_lossy()) || keep_offline(e.path()))
+        })
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_type().is_file() && is_secret_name(&e.file_name().to_string_lossy()))
+}
+
+/// Absolute paths of all modified, staged and untracked (not ignored) files
+fn dirty_paths(path: &Path) -> Option<HashSet<PathBuf>> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true);
+    opts.recurse_untracked_dirs(true);
+
+    let statuses = repo.statuses(Some(&mut opts)).ok()?;
+    let dirty = statuses
+        .iter()
+        .filter(|e| !e.status().is_ignored())
+        .filter_map(|e| e.path().map(|p| workdir.join(p)))
+        .collect();
+    Some(dirty)
+}
@@ -0,0 +1,130 @@
+use colored::*;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::get_config_path;
+use crate::utils::{expand_tilde, is_plain_name};
+
+/// One `name = path | flags` per line; the `| flags` part is optional.
+/// Lives next to ignores.txt, so `struct config export` carries it along.
+fn bookmarks_path() -> PathBuf {
+    get_config_path().with_file_name("bookmarks.txt")
+}
+
+/// A named directory for `struct @name`, with the flags it runs with
+pub struct Bookmark {
+    pub name: String,
+    pub path: PathBuf,
+    /// Default flags as written (split like STRUCT_FLAGS when used)
+    pub flags: String,
+}
+
+pub fn load_bookmarks() -> Vec<Bookmark> {
+    let content = fs::read_to_string(bookmarks_path()).unwrap_or_default();
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let (name, rest) = l.split_once('=')?;
+            let (path, flags) = rest.split_once(" | ").unwrap_or((rest, ""));
+            Some(Bookmark {
+                name: name.trim().to_string(),
+                path: expand_tilde(path.trim()),
+                flags: flags.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn find_bookmark(name: &str) -> Option<Bookmark> {
+    load_bookmarks().into_iter().find(|b| b.name == name)
+}
+
+fn save_bookmarks(bookmarks: &[Bookmark]) -> std::io::Result<()> {
+    let file = bookmarks_path();
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lines: Vec<String> = bookmarks
+        .iter()
+        .map(|b| {
+            if b.flags.is_empty() {
+                format!("{} = {}", b.name, b.path.display())
+            } else {
+                format!("{} = {} | {}", b.name, b.path.display(), b.flags)
+            }
+        })
+        .collect();
+    fs::write(file, lines.join("\n") + "\n")
+}
+
+/// `struct bookmark add NAME [PATH] [--flags "..."]`: save PATH (absolute)
+/// under NAME, replacing an existing bookmark of that name
+pub fn add_bookmark(name: &str, path: &Path, flags: &str) -> Result<(), String> {
+    let name = name.trim_start_matches('@');
+    if !is_plain_name(name) {
+        return Err(format!("invalid bookmark name '{}' (use letters, digits, '-', '_' and '.')", name));
+    }
+    let path = path.canonicalize().map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", path.display()));
+    }
+
+    let mut bookmarks = load_bookmarks();
+    let replaced = bookmarks.iter().any(|b| b.name == name);
+    bookmarks.retain(|b| b.name != name);
+    bookmarks.push(Bookmark { name: name.to_string(), path: path.clone(), flags: flags.trim().to_string() });
+    save_bookmarks(&bookmarks).map_err(|e| format!("failed to save bookmarks: {}", e))?;
+
+    let verb = if replaced { "updated" } else { "added" };
+    println!("{} {} → {}", format!("@{}", name).green(), verb, path.display().to_string().cyan());
+    if !flags.trim().is_empty() {
+        println!("{} {}", "flags:".bright_black(), flags.trim());
+    }
+    Ok(())
+}
+
+/// `struct bookmark rm NAME`; false (after a message) when there's no such bookmark
+pub fn remove_bookmark(name: &str) -> bool {
+    let name = name.trim_start_matches('@');
+    let mut bookmarks = load_bookmarks();
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.name != name);
+    if bookmarks.len() == before {
+        eprintln!("error: no bookmark named {}", name.yellow());
+        return false;
+    }
+    if let Err(e) = save_bookmarks(&bookmarks) {
+        eprintln!("error: failed to save bookmarks: {}", e);
+        return false;
+    }
+    println!("{} removed", format!("@{}", name).green());
+    true
+}
+
+/// `struct bookmark list`
+pub fn list_bookmarks(json_out: bool) {
+    let bookmarks = load_bookmarks();
+    if json_out {
+        let items: Vec<serde_json::Value> = bookmarks
+            .iter()
+            .map(|b| json!({ "name": b.name, "path": b.path.display().to_string(), "flags": b.flags }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&items).unwrap_or_default());
+        return;
+    }
+    if bookmarks.is_empty() {
+        println!("no bookmarks yet");
+        println!("add one with: struct bookmark add NAME [PATH]");
+        return;
+    }
+    let width = bookmarks.iter().map(|b| b.name.chars().count() + 1).max().unwrap_or(0);
+    for b in &bookmarks {
+        let path = b.path.display().to_string();
+        let path = if b.path.is_dir() { path.cyan() } else { format!("{} (missing)", path).red() };
+        println!("  {:<width$}  {}  {}", format!("@{}", b.name).green(), path, b.flags.bright_black(), width = width);
+    }
+    println!("\nbookmarks file: {}", bookmarks_path().display().to_string().bright_black());
+}
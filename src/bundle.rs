@@ -0,0 +1,289 @@
+use colored::*;
+use dialoguer::Select;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::{get_config_path, print_json_outcome, record_history};
+
+/// Name of the manifest entry at the top of every bundle
+const MANIFEST: &str = "struct-bundle.json";
+
+/// Bumped when the bundle layout changes in a way older versions can't read
+const BUNDLE_VERSION: u64 = 1;
+
+/// Machine-local state — the undo log and the invocation log — is neither
+/// exported nor overwritten
+const SKIPPED: &[&str] = &["history", "invocations.jsonl"];
+
+/// What to do with a bundled file that differs from the one already on disk
+#[derive(Clone, Copy, PartialEq)]
+pub enum Conflicts {
+    Ask,
+    Overwrite,
+    Keep,
+}
+
+/// The directory holding everything struct keeps: ignores.txt (patterns,
+/// settings and `color.*` themes), roots.txt, backups/ and whatever later
+/// features store next to them
+fn state_dir() -> PathBuf {
+    get_config_path().parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Relative paths of the files that make up the state, sorted
+fn state_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || !SKIPPED.contains(&e.file_name().to_string_lossy().as_ref()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect();
+    files.sort();
+    files
+}
+
+fn report_error(action: &str, json: bool, message: String) {
+    if json {
+        print_json_outcome(action, None, "error", Some(message));
+    } else {
+        eprintln!("error: {}", message);
+    }
+}
+
+/// `struct config export FILE`: pack the state directory into a tar bundle
+/// with a small manifest, for moving a setup to another machine
+pub fn export_bundle(out: &Path, json: bool) {
+    let dir = state_dir();
+    let files = state_files(&dir);
+    if files.is_empty() {
+        if json {
+            print_json_outcome("export", None, "no_config", None);
+        } else {
+            println!("nothing to export — {} holds no files", dir.display());
+        }
+        return;
+    }
+
+    let result = (|| -> std::io::Result<()> {
+        let mut builder = tar::Builder::new(fs::File::create(out)?);
+        let manifest = json!({
+            "format": "struct-bundle",
+            "version": BUNDLE_VERSION,
+            "struct_version": env!("CARGO_PKG_VERSION"),
+            "files": files.iter().map(|f| f.to_string_lossy().to_string()).collect::<Vec<_>>(),
+        });
+        let body = serde_json::to_vec_pretty(&manifest).unwrap_or_default();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(body.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, MANIFEST, body.as_slice())?;
+        for file in &files {
+            builder.append_path_with_name(dir.join(file), file)?;
+        }
+        builder.into_inner()?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        report_error("export", json, format!("cannot write {}: {}", out.display(), e));
+        return;
+    }
+    if json {
+        let out = json!({
+            "action": "export",
+            "status": "exported",
+            "bundle": out.display().to_string(),
+            "files": files.len(),
+        });
+        println!("{}", out);
+    } else {
+        println!("{} {} files from {}", "exported".green(), files.len(), dir.display().to_string().bright_black());
+        for file in &files {
+            println!("  {}", file.display());
+        }
+        println!("bundle: {}", out.display().to_string().bright_black());
+    }
+}
+
+/// A bundle member path that stays inside the state dir (no `..`, no root)
+fn safe_relative(path: &Path) -> Option<PathBuf> {
+    let clean: PathBuf = path
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => Some(part),
+            Component::CurDir => Some(std::ffi::OsStr::new("")),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect();
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+/// Read every file out of a bundle, checking the manifest first
+fn read_bundle(path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    let mut archive = tar::Archive::new(file);
+    let entries = archive.entries().map_err(|e| format!("{} is not a tar file: {}", path.display(), e))?;
+
+    let mut manifest: Option<Value> = None;
+    let mut files = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("corrupt bundle: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path().map_err(|e| format!("corrupt bundle: {}", e))?.to_path_buf();
+        let mut body = Vec::new();
+        entry.read_to_end(&mut body).map_err(|e| format!("corrupt bundle: {}", e))?;
+        if name == Path::new(MANIFEST) {
+            manifest = serde_json::from_slice(&body).ok();
+            continue;
+        }
+        let rel = safe_relative(&name).ok_or_else(|| format!("refusing unsafe path in bundle: {}", name.display()))?;
+        if SKIPPED.iter().any(|d| rel.starts_with(d)) {
+            continue;
+        }
+        files.push((rel, body));
+    }
+
+    let Some(manifest) = manifest.filter(|m| m["format"] == "struct-bundle") else {
+        return Err(format!("{} is not a struct bundle (no {})", path.display(), MANIFEST));
+    };
+    let version = manifest["version"].as_u64().unwrap_or(0);
+    if version > BUNDLE_VERSION {
+        return Err(format!(
+            "bundle format {} is newer than this struct understands ({}) — upgrade struct first",
+            version, BUNDLE_VERSION
+        ));
+    }
+    Ok(files)
+}
+
+/// `struct config import FILE`: unpack a bundle into the state directory.
+///
+/// Files that don't exist yet are written and identical ones are left alone.
+/// For files that differ, `conflicts` decides: ask per file (needs a
+/// terminal), overwrite them all, or keep every local copy. Nothing is written
+/// until every conflict is settled, and a changed ignores.txt is recorded in
+/// the history so `struct config undo` can take it back.
+pub fn import_bundle(path: &Path, conflicts: Conflicts, json: bool) {
+    let files = match read_bundle(path) {
+        Ok(files) => files,
+        Err(e) => return report_error("import", json, e),
+    };
+    let dir = state_dir();
+
+    let mut mode = conflicts;
+    let mut plan: Vec<(PathBuf, Vec<u8>, &'static str)> = Vec::new();
+    let mut unchanged = 0;
+    for (rel, body) in files {
+        let target = dir.join(&rel);
+        let status = match fs::read(&target) {
+            Err(_) => "new",
+            Ok(current) if current == body => {
+                unchanged += 1;
+                continue;
+            }
+            Ok(_) => match mode {
+                Conflicts::Overwrite => "overwritten",
+                Conflicts::Keep => "kept",
+                Conflicts::Ask if json || !std::io::stdin().is_terminal() => {
+                    let message = format!(
+                        "{} differs from the bundle — pass --overwrite or --keep to import without prompts",
+                        target.display()
+                    );
+                    if json {
+                        print_json_outcome("import", None, "aborted", Some(message));
+                    } else {
+                        eprintln!("aborted — nothing imported: {}", message);
+                    }
+                    return;
+                }
+                Conflicts::Ask => {
+                    let choice = Select::new()
+                        .with_prompt(format!("{} already exists and differs", rel.display()))
+                        .items(&["overwrite", "keep mine", "overwrite all", "keep all"])
+                        .default(1)
+                        .interact()
+                        .unwrap_or(1);
+                    match choice {
+                        0 => "overwritten",
+                        2 => {
+                            mode = Conflicts::Overwrite;
+                            "overwritten"
+                        }
+                        3 => {
+                            mode = Conflicts::Keep;
+                            "kept"
+                        }
+                        _ => "kept",
+                    }
+                }
+            },
+        };
+        plan.push((rel, body, status));
+    }
+
+    let config_rel = get_config_path().strip_prefix(&dir).map(Path::to_path_buf).unwrap_or_default();
+    let result = (|| -> std::io::Result<()> {
+        if plan.iter().any(|(rel, _, status)| *rel == config_rel && *status != "kept") {
+            record_history()?;
+        }
+        for (rel, body, status) in &plan {
+            if *status == "kept" {
+                continue;
+            }
+            let target = dir.join(rel);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, body)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        return report_error("import", json, format!("import failed: {}", e));
+    }
+
+    let count = |wanted: &str| plan.iter().filter(|(_, _, s)| *s == wanted).count();
+    if json {
+        let rows: Vec<Value> = plan
+            .iter()
+            .map(|(rel, _, status)| json!({ "file": rel.to_string_lossy(), "status": status }))
+            .collect();
+        let out = json!({
+            "action": "import",
+            "status": "imported",
+            "bundle": path.display().to_string(),
+            "files": rows,
+            "unchanged": unchanged,
+        });
+        println!("{}", out);
+        return;
+    }
+    for (rel, _, status) in &plan {
+        let label = match *status {
+            "new" => status.green(),
+            "overwritten" => status.yellow(),
+            _ => status.bright_black(),
+        };
+        println!("  {:<12} {}", label, rel.display());
+    }
+    println!(
+        "{} {} new, {} overwritten, {} kept, {} unchanged → {}",
+        "imported".green(),
+        count("new"),
+        count("overwritten"),
+        count("kept"),
+        unchanged,
+        dir.display().to_string().bright_black()
+    );
+}
@@ -0,0 +1,119 @@
+use colored::*;
+use serde_json::json;
+use std::path::Path;
+
+use crate::archive::{read_archive, Listing};
+use crate::copy::{plan_copy, GitIgnores, Kind};
+use crate::ignores::IgnoreRules;
+use crate::utils::{format_count, format_size};
+
+/// Drop the folder every entry sits under (`app-1.2/src/...` → `src/...`),
+/// as `struct pack` and most release archives add one
+fn strip_common_top(listing: Listing) -> Listing {
+    let tops: Vec<&str> = listing.keys().map(|k| k.trim_start_matches("./").split('/').next().unwrap_or("")).collect();
+    let shared = tops.first().copied().filter(|top| {
+        tops.iter().all(|t| t == top) && listing.keys().all(|k| k.trim_start_matches("./").contains('/'))
+    });
+    match shared.map(|t| format!("{}/", t)) {
+        Some(prefix) => listing
+            .into_iter()
+            .map(|(k, v)| {
+                let k = k.trim_start_matches("./");
+                (k.strip_prefix(prefix.as_str()).unwrap_or(k).to_string(), v)
+            })
+            .collect(),
+        None => listing.into_iter().map(|(k, v)| (k.trim_start_matches("./").to_string(), v)).collect(),
+    }
+}
+
+/// `struct compare ARCHIVE [PATH]`: check a release archive against the live
+/// tree without extracting it. PATH is read with the same rules as `struct
+/// pack`, so a fresh pack compares clean. Returns None on error, else whether
+/// the two match.
+pub fn compare_archive(archive: &Path, path: &Path, ignores: &IgnoreRules, gitignore: bool, json_out: bool) -> Option<bool> {
+    let packed = match read_archive(archive) {
+        Ok(listing) => strip_common_top(listing),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return None;
+        }
+    };
+    if !path.is_dir() {
+        eprintln!("error: {} is not a directory", path.display());
+        return None;
+    }
+
+    // The archive may sit inside the tree it was made from
+    let archive_abs = archive.canonicalize().ok();
+    let git = if gitignore { GitIgnores::new(path) } else { None };
+    let (items, _) = plan_copy(path, archive_abs.as_deref(), ignores, git.as_ref());
+    let live: Listing = items
+        .iter()
+        .filter(|i| i.kind != Kind::Dir)
+        .map(|i| {
+            let rel: Vec<String> = i.rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+            (rel.join("/"), (i.kind == Kind::File).then_some(i.size))
+        })
+        .collect();
+
+    let missing: Vec<&String> = live.keys().filter(|k| !packed.contains_key(*k)).collect();
+    let extra: Vec<&String> = packed.keys().filter(|k| !live.contains_key(*k)).collect();
+    let differ: Vec<(&String, u64, u64)> = live
+        .iter()
+        .filter_map(|(k, size)| match (size, packed.get(k)) {
+            (Some(here), Some(Some(there))) if here != there => Some((k, *here, *there)),
+            _ => None,
+        })
+        .collect();
+    let same = missing.is_empty() && extra.is_empty() && differ.is_empty();
+
+    if json_out {
+        let out = json!({
+            "archive": archive.display().to_string(),
+            "path": path.display().to_string(),
+            "match": same,
+            "missing": missing,
+            "extra": extra,
+            "size_mismatch": differ
+                .iter()
+                .map(|(k, here, there)| json!({ "path": k, "live": here, "archive": there }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", out);
+        return Some(same);
+    }
+
+    println!(
+        "{} {} {}",
+        path.display().to_string().cyan(),
+        "vs".bright_black(),
+        archive.display().to_string().cyan()
+    );
+    for k in &missing {
+        println!("  {} {}", "missing".red(), k);
+    }
+    for k in &extra {
+        println!("  {}   {}", "extra".yellow(), k);
+    }
+    for (k, here, there) in &differ {
+        println!(
+            "  {}    {} {}",
+            "size".magenta(),
+            k,
+            format!("({} here, {} in archive)", format_size(*here), format_size(*there)).bright_black()
+        );
+    }
+    let summary = format!(
+        "{} files compared · {} missing from the archive, {} extra, {} size mismatches",
+        format_count(live.len().max(packed.len())),
+        format_count(missing.len()),
+        format_count(extra.len()),
+        format_count(differ.len())
+    );
+    if same {
+        println!("  {} {}", "match".green(), summary.bright_black());
+    } else {
+        println!("\n{}", summary.bright_black());
+    }
+    Some(same)
+}
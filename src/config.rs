@@ -1,24 +1,326 @@
 use colored::*;
+use dialoguer::{Confirm, MultiSelect};
+use serde_json::json;
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
 
-/// Get the path to the config file
+use crate::ignores::{pattern_to_regex, should_ignore_dir};
+use crate::utils::{expand_tilde, format_count};
+
+/// Get the path to the config file: `config.toml` once it exists (see
+/// `struct config migrate`), the flat `ignores.txt` otherwise
 pub fn get_config_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home).join(".config").join("struct").join("ignores.txt")
+    let dir = PathBuf::from(home).join(".config").join("struct");
+    let toml = dir.join("config.toml");
+    if toml.exists() {
+        toml
+    } else {
+        dir.join("ignores.txt")
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "toml")
+}
+
+/// Tables of config.toml and the flat key prefix their keys get, so
+/// `[colors] dir = "blue"` reads as `color.dir = "blue"`. Keys of
+/// `[ignore]`, `[display]` and the top level keep their own names.
+const TOML_SECTIONS: &[(&str, &str)] =
+    &[("colors", "color."), ("sort", "sort."), ("annotators", "annotator."), ("defaults", "defaults.")];
+
+/// A TOML value the way the flat file writes it: `"text"`, `3`, `["a", "b"]`
+fn flat_value(value: &toml_edit::Value) -> String {
+    match value {
+        toml_edit::Value::String(s) => format!("\"{}\"", s.value()),
+        toml_edit::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(flat_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        other => other.clone().decorated("", "").to_string(),
+    }
+}
+
+/// `LINE is not valid TOML...: reason` parts of a parse error, on one line
+fn toml_error(err: &toml_edit::TomlError, content: &str) -> (usize, String) {
+    let line = err.span().map_or(1, |span| content[..span.start.min(content.len())].matches('\n').count() + 1);
+    let reason: Vec<&str> = err.message().lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    (line, reason.join(", "))
+}
+
+/// Warn about a broken config.toml once per run, not on every lookup
+fn warn_bad_toml(err: &toml_edit::TomlError, content: &str) {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    WARNED.call_once(|| {
+        let (line, reason) = toml_error(err, content);
+        eprintln!("warning: {}:{} is not valid TOML, ignoring the config: {}", get_config_path().display(), line, reason);
+    });
+}
+
+/// config.toml as the lines of a flat ignores.txt: the `[ignore] patterns`
+/// one per line, then every other setting as `key = value`
+fn flatten_toml(content: &str) -> String {
+    let doc = match content.parse::<toml_edit::DocumentMut>() {
+        Ok(doc) => doc,
+        Err(e) => {
+            warn_bad_toml(&e, content);
+            return String::new();
+        }
+    };
+    let mut lines: Vec<String> = toml_patterns(&doc).into_iter().collect();
+    for (name, item) in doc.iter() {
+        match item.as_table_like() {
+            Some(table) => {
+                let prefix = TOML_SECTIONS.iter().find(|(s, _)| *s == name).map(|(_, p)| *p).unwrap_or("");
+                for (key, item) in table.iter() {
+                    let Some(value) = item.as_value() else { continue };
+                    if name != "ignore" || key != "patterns" {
+                        lines.push(format!("{}{} = {}", prefix, key, flat_value(value)));
+                    }
+                }
+            }
+            None => {
+                if let Some(value) = item.as_value() {
+                    lines.push(format!("{} = {}", name, flat_value(value)));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// The `[ignore] patterns` array of config.toml (or of a project's
+/// `.struct.toml`, where `[tool.struct.ignore]` works too)
+fn toml_patterns(doc: &toml_edit::DocumentMut) -> Vec<String> {
+    doc.get("ignore")
+        .or_else(|| doc.get("tool").and_then(|t| t.get("struct")).and_then(|t| t.get("ignore")))
+        .and_then(|t| t.get("patterns"))
+        .and_then(|p| p.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// The config file's contents in the flat format, whichever file it is
+fn config_text() -> String {
+    let config_path = get_config_path();
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    if is_toml(&config_path) {
+        flatten_toml(&content)
+    } else {
+        content
+    }
+}
+
+/// Get the path to the named search roots file
+pub fn get_roots_path() -> PathBuf {
+    get_config_path().with_file_name("roots.txt")
+}
+
+/// Load named search roots: one `name = path, path, ...` per line
+pub fn load_search_roots() -> Vec<(String, Vec<PathBuf>)> {
+    let content = fs::read_to_string(get_roots_path()).unwrap_or_default();
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let (name, paths) = l.split_once('=')?;
+            let paths: Vec<PathBuf> = paths
+                .split(',')
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .map(expand_tilde)
+                .collect();
+            Some((name.trim().to_string(), paths))
+        })
+        .collect()
 }
 
 /// Load patterns from config file
 pub fn load_config_patterns() -> Vec<String> {
+    load_numbered_config_patterns().into_iter().map(|(_, pattern)| pattern).collect()
+}
+
+/// Patterns from the config file with their 1-based line numbers
+pub fn load_numbered_config_patterns() -> Vec<(usize, String)> {
     let config_path = get_config_path();
-    if let Ok(content) = fs::read_to_string(&config_path) {
-        content.lines()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty() && !s.starts_with('#'))
-            .collect()
-    } else {
-        Vec::new()
+    let Ok(content) = fs::read_to_string(&config_path) else { return Vec::new() };
+    if !is_toml(&config_path) {
+        return content.lines()
+            .enumerate()
+            .map(|(i, s)| (i + 1, s.trim().to_string()))
+            .filter(|(_, s)| !s.is_empty() && !s.starts_with('#') && parse_setting_line(s).is_none())
+            .collect();
     }
+
+    // In config.toml: the line each quoted pattern sits on, in array order
+    let patterns = match content.parse::<toml_edit::DocumentMut>() {
+        Ok(doc) => toml_patterns(&doc),
+        Err(e) => {
+            warn_bad_toml(&e, &content);
+            return Vec::new();
+        }
+    };
+    number_toml_patterns(&content, patterns)
+}
+
+/// The line each quoted pattern of a TOML `patterns` array sits on, in array order
+fn number_toml_patterns(content: &str, patterns: Vec<String>) -> Vec<(usize, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut from = lines.iter().position(|l| l.trim_start().starts_with("patterns")).unwrap_or(0);
+    patterns
+        .into_iter()
+        .map(|pattern| {
+            let quoted = [format!("\"{}\"", pattern), format!("'{}'", pattern)];
+            if let Some(i) = (from..lines.len()).find(|&i| quoted.iter().any(|q| lines[i].contains(q.as_str()))) {
+                from = i;
+            }
+            (from + 1, pattern)
+        })
+        .collect()
+}
+
+/// Patterns of the project ignore files in `dir`, each with its `file:line`.
+/// `.structignore` has one pattern per line, like ignores.txt; `.struct.toml`
+/// an `[ignore] patterns` array, like config.toml.
+pub fn load_project_patterns(dir: &Path) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let plain = dir.join(".structignore");
+    if let Ok(content) = fs::read_to_string(&plain) {
+        for (i, line) in content.lines().enumerate().map(|(i, l)| (i, l.trim())) {
+            if !line.is_empty() && !line.starts_with('#') {
+                found.push((line.to_string(), format!("{}:{}", plain.display(), i + 1)));
+            }
+        }
+    }
+    let toml = dir.join(".struct.toml");
+    if let Ok(content) = fs::read_to_string(&toml) {
+        match content.parse::<toml_edit::DocumentMut>() {
+            Ok(doc) => {
+                let numbered = number_toml_patterns(&content, toml_patterns(&doc));
+                found.extend(numbered.into_iter().map(|(line, p)| (p, format!("{}:{}", toml.display(), line))));
+            }
+            Err(e) => {
+                let (line, reason) = toml_error(&e, &content);
+                eprintln!("warning: {}:{} is not valid TOML, skipping it: {}", toml.display(), line, reason);
+            }
+        }
+    }
+    found
+}
+
+/// Keys of `key = value` setting lines that may sit among the patterns in
+/// the config file (plus any `color.*` key, see theme.rs, `sort.*`, see importance.rs,
+/// `annotator.*`, see annotate.rs, and `defaults.*`, see main.rs)
+const SETTING_KEYS: &[&str] = &[
+    "unignore", "size_precision", "size_min_unit", "protect", "guard_size_mb", "symlink_style", "time_format", "hidden",
+    "history",
+];
+
+/// Split a `key = value` setting line; None for pattern lines
+fn parse_setting_line(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    let prefixed = ["color.", "sort.", "annotator.", "defaults."].iter().any(|p| key.starts_with(p));
+    (SETTING_KEYS.contains(&key) || prefixed).then(|| (key, value.trim()))
+}
+
+/// Value of a setting from the config file (the last one wins)
+pub fn load_config_setting(key: &str) -> Option<String> {
+    let content = config_text();
+    content
+        .lines()
+        .filter_map(|l| parse_setting_line(l.trim()))
+        .filter(|(k, _)| *k == key)
+        .map(|(_, v)| v.trim_matches('"').to_string())
+        .next_back()
+}
+
+/// Every `key = value` setting whose key starts with `prefix`, in file order
+pub fn load_config_settings_with_prefix(prefix: &str) -> Vec<(String, String)> {
+    let content = config_text();
+    content
+        .lines()
+        .filter_map(|l| parse_setting_line(l.trim()))
+        .filter(|(k, _)| k.starts_with(prefix))
+        .map(|(k, v)| (k.to_string(), v.trim_matches('"').to_string()))
+        .collect()
+}
+
+/// Parse a list setting like `unignore = ["bin", "env"]` (brackets and quotes optional)
+fn parse_list_line(line: &str, wanted: &str) -> Option<Vec<String>> {
+    let (key, value) = parse_setting_line(line)?;
+    if key != wanted {
+        return None;
+    }
+    let value = value.trim_start_matches('[').trim_end_matches(']');
+    Some(
+        value
+            .split(',')
+            .map(|n| n.trim().trim_matches('"').trim_matches('\'').to_string())
+            .filter(|n| !n.is_empty())
+            .collect(),
+    )
+}
+
+/// Every entry of the list setting `key`, across all its lines
+pub fn load_config_list(key: &str) -> Vec<String> {
+    let content = config_text();
+    content.lines().filter_map(|l| parse_list_line(l.trim(), key)).flatten().collect()
+}
+
+/// Built-in ignore entries the config takes off the list (`unignore = [...]`
+/// lines in the config file), e.g. for projects where `bin/` is real source
+pub fn load_builtin_unignores() -> Vec<String> {
+    load_config_list("unignore")
+}
+
+/// How many previous config states `struct config undo` can step back through
+const MAX_HISTORY: usize = 20;
+
+/// Directory holding snapshots of the config taken before each change
+fn get_history_dir() -> PathBuf {
+    get_config_path().with_file_name("history")
+}
+
+/// Numbered history snapshots, oldest first
+fn list_history_entries() -> Vec<(u64, PathBuf)> {
+    let mut entries: Vec<(u64, PathBuf)> = match fs::read_dir(get_history_dir()) {
+        Ok(rd) => rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let path = e.path();
+                let n = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+                Some((n, path))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort_by_key(|(n, _)| *n);
+    entries
+}
+
+/// Snapshot the current config file so the next change can be undone
+pub fn record_history() -> std::io::Result<()> {
+    let history_dir = get_history_dir();
+    fs::create_dir_all(&history_dir)?;
+
+    // A missing config file is recorded as an empty one — same effective state
+    let current = fs::read_to_string(get_config_path()).unwrap_or_default();
+    let entries = list_history_entries();
+    let next = entries.last().map(|(n, _)| n + 1).unwrap_or(1);
+    fs::write(history_dir.join(format!("{:06}.txt", next)), current)?;
+
+    // Drop the oldest snapshots beyond the limit
+    let excess = (entries.len() + 1).saturating_sub(MAX_HISTORY);
+    for (_, path) in entries.iter().take(excess) {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
 }
 
 /// Save patterns to config file
@@ -27,19 +329,224 @@ fn save_config_patterns(patterns: &[String]) -> std::io::Result<()> {
     if let Some(parent) = config_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(&config_path, patterns.join("\n"))
+    record_history()?;
+
+    // Keep settings, comments and blank lines; add/remove only touch the patterns
+    let current = fs::read_to_string(&config_path).unwrap_or_default();
+    if is_toml(&config_path) {
+        let mut doc = current
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.message().trim().to_string()))?;
+        doc["ignore"]["patterns"] = toml_edit::value(pattern_array(patterns));
+        return fs::write(&config_path, doc.to_string());
+    }
+    // Patterns still wanted stay on their line, the rest go; new ones are appended
+    let mut pending: Vec<&String> = patterns.iter().collect();
+    let mut lines: Vec<String> = Vec::new();
+    for line in current.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || parse_setting_line(trimmed).is_some() {
+            lines.push(line.to_string());
+        } else if let Some(i) = pending.iter().position(|p| p.as_str() == trimmed) {
+            pending.remove(i);
+            lines.push(line.to_string());
+        }
+    }
+    lines.extend(pending.into_iter().cloned());
+    fs::write(&config_path, lines.join("\n"))
+}
+
+/// Patterns as a TOML array, one per line
+fn pattern_array(patterns: &[String]) -> toml_edit::Array {
+    let mut array: toml_edit::Array = patterns.iter().map(String::as_str).collect();
+    for item in array.iter_mut() {
+        item.decor_mut().set_prefix("\n    ");
+    }
+    if !patterns.is_empty() {
+        array.set_trailing_comma(true);
+        array.set_trailing("\n");
+    }
+    array
+}
+
+/// Where a flat ignores.txt setting goes in config.toml: (table, key), with
+/// no table for top-level keys
+fn toml_place(key: &str) -> (Option<&'static str>, String) {
+    for (table, prefix) in TOML_SECTIONS {
+        if let Some(rest) = key.strip_prefix(prefix) {
+            return (Some(table), rest.to_string());
+        }
+    }
+    let table = match key {
+        "unignore" | "protect" | "hidden" => Some("ignore"),
+        "size_precision" | "size_min_unit" | "symlink_style" | "time_format" => Some("display"),
+        _ => None,
+    };
+    (table, key.to_string())
+}
+
+/// A flat setting value as TOML: numbers and booleans stay bare, the rest are strings
+fn toml_value(raw: &str) -> toml_edit::Value {
+    let raw = raw.trim();
+    if let Ok(n) = raw.parse::<i64>() {
+        return n.into();
+    }
+    match raw {
+        "true" => true.into(),
+        "false" => false.into(),
+        _ => raw.trim_matches('"').into(),
+    }
+}
+
+/// A config.toml with everything a flat ignores.txt sets, in sections
+fn toml_from_flat(content: &str) -> toml_edit::DocumentMut {
+    let mut doc = toml_edit::DocumentMut::new();
+    let patterns: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && parse_setting_line(l).is_none())
+        .map(String::from)
+        .collect();
+    doc["ignore"] = toml_edit::table();
+    doc["ignore"]["patterns"] = toml_edit::value(pattern_array(&patterns));
+
+    for line in content.lines().map(str::trim) {
+        let Some((key, raw)) = parse_setting_line(line) else { continue };
+        let value: toml_edit::Value = if key == "unignore" || key == "protect" {
+            // List settings may span several lines; gather them all
+            let mut items = doc.get("ignore").and_then(|t| t.get(key)).and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            for item in parse_list_line(line, key).unwrap_or_default() {
+                items.push(item);
+            }
+            items.into()
+        } else {
+            toml_value(raw)
+        };
+        match toml_place(key) {
+            (Some(table), key) => {
+                if doc.get(table).is_none() {
+                    doc[table] = toml_edit::table();
+                }
+                doc[table][key.as_str()] = toml_edit::value(value);
+            }
+            (None, key) => doc[key.as_str()] = toml_edit::value(value),
+        }
+    }
+    doc
+}
+
+/// Starting point for `struct config migrate` without an ignores.txt
+const TOML_TEMPLATE: &str = r#"# struct config — see `struct --help`
+
+# Applied to every tree run; flags on the command line win
+[defaults]
+# depth = 3
+# show_size = true
+# sort = "size"
+# flags = "--align-sizes -a"
+
+[ignore]
+patterns = []
+# unignore = ["bin"]
+# hidden = "show"
+
+[display]
+# size_precision = 1
+# symlink_style = "arrow"
+# time_format = "%Y-%m-%d %H:%M"
+
+# [colors]
+# dir = "blue bold"
+"#;
+
+/// `struct config migrate`: write config.toml from ignores.txt (or a
+/// commented starter file when there is none). The old file is kept as
+/// `ignores.txt.migrated`; from then on add/remove/list edit config.toml.
+pub fn migrate_config(json: bool) {
+    let config_path = get_config_path();
+    if is_toml(&config_path) {
+        if json {
+            print_json_outcome("migrate", None, "exists", None);
+        } else {
+            println!("already using {}", config_path.display().to_string().cyan());
+        }
+        return;
+    }
+    let toml_path = config_path.with_file_name("config.toml");
+    let result = (|| -> std::io::Result<Option<PathBuf>> {
+        if let Some(parent) = toml_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let Ok(flat) = fs::read_to_string(&config_path) else {
+            fs::write(&toml_path, TOML_TEMPLATE)?;
+            return Ok(None);
+        };
+        let doc = toml_from_flat(&flat);
+        fs::write(&toml_path, format!("# struct config, migrated from ignores.txt\n{}", doc))?;
+        let kept = config_path.with_file_name("ignores.txt.migrated");
+        fs::rename(&config_path, &kept)?;
+        Ok(Some(kept))
+    })();
+
+    match result {
+        Err(e) => {
+            if json {
+                print_json_outcome("migrate", None, "error", Some(e.to_string()));
+            } else {
+                eprintln!("failed to migrate config: {}", e);
+            }
+        }
+        Ok(kept) => {
+            if json {
+                print_json_outcome("migrate", None, "migrated", None);
+                return;
+            }
+            println!("{} {}", "wrote".green(), toml_path.display().to_string().cyan());
+            if let Some(kept) = kept {
+                println!("old file kept as {}", kept.display().to_string().bright_black());
+            }
+        }
+    }
+}
+
+/// Print the outcome of a config-modifying command as a single JSON object
+pub fn print_json_outcome(action: &str, pattern: Option<&str>, status: &str, error: Option<String>) {
+    let mut out = json!({
+        "action": action,
+        "status": status,
+        "config_file": get_config_path().display().to_string(),
+    });
+    if let Some(p) = pattern {
+        out["pattern"] = json!(p);
+    }
+    if let Some(e) = error {
+        out["error"] = json!(e);
+    }
+    println!("{}", out);
 }
 
 /// Add a pattern to the config file
-pub fn add_config_pattern(pattern: String) {
+pub fn add_config_pattern(pattern: String, json: bool) {
     let mut patterns = load_config_patterns();
     if patterns.contains(&pattern) {
-        println!("{} already in config", pattern.yellow());
+        if json {
+            print_json_outcome("add", Some(&pattern), "exists", None);
+        } else {
+            println!("{} already in config", pattern.yellow());
+        }
         return;
     }
     patterns.push(pattern.clone());
     if let Err(e) = save_config_patterns(&patterns) {
-        eprintln!("failed to save config: {}", e);
+        if json {
+            print_json_outcome("add", Some(&pattern), "error", Some(e.to_string()));
+        } else {
+            eprintln!("failed to save config: {}", e);
+        }
+        return;
+    }
+    if json {
+        print_json_outcome("add", Some(&pattern), "added", None);
         return;
     }
     println!("{} added to config", pattern.green());
@@ -47,26 +554,114 @@ pub fn add_config_pattern(pattern: String) {
 }
 
 /// Remove a pattern from the config file
-pub fn remove_config_pattern(pattern: String) {
+pub fn remove_config_pattern(pattern: String, json: bool) {
     let mut patterns = load_config_patterns();
     let before_len = patterns.len();
     patterns.retain(|p| p != &pattern);
     
     if patterns.len() == before_len {
-        println!("{} not found in config", pattern.yellow());
+        if json {
+            print_json_outcome("remove", Some(&pattern), "not_found", None);
+        } else {
+            println!("{} not found in config", pattern.yellow());
+        }
         return;
     }
     
     if let Err(e) = save_config_patterns(&patterns) {
-        eprintln!("failed to save config: {}", e);
+        if json {
+            print_json_outcome("remove", Some(&pattern), "error", Some(e.to_string()));
+        } else {
+            eprintln!("failed to save config: {}", e);
+        }
+        return;
+    }
+    if json {
+        print_json_outcome("remove", Some(&pattern), "removed", None);
+    } else {
+        println!("{} removed from config", pattern.red());
+    }
+}
+
+/// Interactively pick several patterns to remove (`struct remove` with no argument)
+pub fn remove_config_patterns_interactive(json: bool) {
+    let patterns = load_config_patterns();
+    if patterns.is_empty() {
+        if json {
+            print_json_outcome("remove", None, "no_config", None);
+        } else {
+            println!("no custom patterns configured");
+        }
+        return;
+    }
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!("error: no pattern given and stdin is not a terminal — use: struct remove \"pattern\"");
+        return;
+    }
+
+    let selected = match MultiSelect::new()
+        .with_prompt("select patterns to remove (space to toggle, enter to confirm)")
+        .items(&patterns)
+        .interact_opt()
+    {
+        Ok(Some(selected)) => selected,
+        Ok(None) => {
+            println!("cancelled");
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return;
+        }
+    };
+
+    if selected.is_empty() {
+        println!("nothing selected");
         return;
     }
-    println!("{} removed from config", pattern.red());
+
+    let remaining: Vec<String> = patterns
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !selected.contains(i))
+        .map(|(_, p)| p.clone())
+        .collect();
+
+    if let Err(e) = save_config_patterns(&remaining) {
+        if json {
+            print_json_outcome("remove", None, "error", Some(e.to_string()));
+        } else {
+            eprintln!("failed to save config: {}", e);
+        }
+        return;
+    }
+
+    for i in selected {
+        if json {
+            print_json_outcome("remove", Some(&patterns[i]), "removed", None);
+        } else {
+            println!("{} removed from config", patterns[i].red());
+        }
+    }
 }
 
 /// List all patterns in the config file
-pub fn list_config_patterns() {
+pub fn list_config_patterns(json: bool) {
     let patterns = load_config_patterns();
+    let unignored = load_builtin_unignores();
+    if json {
+        let out = json!({
+            "config_file": get_config_path().display().to_string(),
+            "patterns": patterns,
+            "unignore": unignored,
+        });
+        println!("{}", out);
+        return;
+    }
+    if !unignored.is_empty() {
+        println!("{} {}", "built-in ignores turned off:".bright_black(), unignored.join(", ").cyan());
+    }
     if patterns.is_empty() {
         println!("no custom patterns configured");
         println!("add some with: struct add \"pattern\"");
@@ -80,16 +675,305 @@ pub fn list_config_patterns() {
     println!("\nconfig file: {}", get_config_path().display().to_string().bright_black());
 }
 
+/// Directory holding timestamped backups written by `struct clear`
+fn get_backup_dir() -> PathBuf {
+    get_config_path().with_file_name("backups")
+}
+
+/// Copy the config file to `backups/ignores-<unix time>.txt` (or
+/// `config-<unix time>.toml`)
+fn backup_config_file() -> std::io::Result<PathBuf> {
+    let backup_dir = get_backup_dir();
+    fs::create_dir_all(&backup_dir)?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let config_path = get_config_path();
+    let name = if is_toml(&config_path) { format!("config-{}.toml", stamp) } else { format!("ignores-{}.txt", stamp) };
+    let backup_path = backup_dir.join(name);
+    fs::copy(&config_path, &backup_path)?;
+    Ok(backup_path)
+}
+
 /// Clear all patterns from the config file
-pub fn clear_config_patterns() {
+///
+/// Needs `--yes` or an interactive confirmation; the old file is backed up
+/// first so it can be brought back with `struct config restore`.
+pub fn clear_config_patterns(yes: bool, json: bool) {
     let config_path = get_config_path();
-    if config_path.exists() {
-        if let Err(e) = fs::remove_file(&config_path) {
-            eprintln!("failed to clear config: {}", e);
+    if !config_path.exists() {
+        if json {
+            print_json_outcome("clear", None, "no_config", None);
+        } else {
+            println!("no config file to clear");
+        }
+        return;
+    }
+
+    if !yes {
+        let confirmed = std::io::stdin().is_terminal()
+            && Confirm::new()
+                .with_prompt(format!("clear all {} custom patterns?", load_config_patterns().len()))
+                .default(false)
+                .interact()
+                .unwrap_or(false);
+        if !confirmed {
+            if json {
+                print_json_outcome("clear", None, "aborted", Some("confirmation required (pass --yes)".to_string()));
+            } else {
+                eprintln!("aborted — nothing cleared (pass --yes to skip confirmation)");
+            }
             return;
         }
+    }
+
+    let backup_path = match backup_config_file() {
+        Ok(p) => p,
+        Err(e) => {
+            if json {
+                print_json_outcome("clear", None, "error", Some(format!("backup failed: {}", e)));
+            } else {
+                eprintln!("failed to back up config, not clearing: {}", e);
+            }
+            return;
+        }
+    };
+
+    // config.toml holds more than patterns, so only its pattern list is emptied
+    let cleared = if is_toml(&config_path) {
+        save_config_patterns(&[])
+    } else {
+        record_history().and_then(|_| fs::remove_file(&config_path))
+    };
+    if let Err(e) = cleared {
+        if json {
+            print_json_outcome("clear", None, "error", Some(e.to_string()));
+        } else {
+            eprintln!("failed to clear config: {}", e);
+        }
+        return;
+    }
+    if json {
+        print_json_outcome("clear", None, "cleared", None);
+    } else {
         println!("{}", "cleared all custom patterns".green());
+        println!("backup: {}", backup_path.display().to_string().bright_black());
+        println!("restore with: struct config restore");
+    }
+}
+
+/// Restore the config from a backup written by `struct clear`.
+/// With no name, the most recent backup is used.
+pub fn restore_config_backup(name: Option<String>, json: bool) {
+    let backup_dir = get_backup_dir();
+    let backup_path = match name {
+        Some(n) => {
+            let p = PathBuf::from(&n);
+            if p.is_absolute() { p } else { backup_dir.join(n) }
+        }
+        None => {
+            let mut backups: Vec<PathBuf> = fs::read_dir(&backup_dir)
+                .map(|rd| rd.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+                .unwrap_or_default();
+            // Only backups of the file in use: ignores-<unix time>.txt or
+            // config-<unix time>.toml, where numeric order is chronological
+            let (prefix, ext) = if is_toml(&get_config_path()) { ("config-", "toml") } else { ("ignores-", "txt") };
+            backups.retain(|p| p.extension().is_some_and(|e| e == ext));
+            backups.sort_by_key(|p| {
+                p.file_stem()
+                    .and_then(|s| s.to_str())
+                    .and_then(|s| s.trim_start_matches(prefix).parse::<u64>().ok())
+                    .unwrap_or(0)
+            });
+            match backups.pop() {
+                Some(p) => p,
+                None => {
+                    if json {
+                        print_json_outcome("restore", None, "no_backup", None);
+                    } else {
+                        println!("no backups found in {}", backup_dir.display());
+                    }
+                    return;
+                }
+            }
+        }
+    };
+
+    let result = fs::read_to_string(&backup_path).and_then(|content| {
+        let config_path = get_config_path();
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        record_history()?;
+        fs::write(&config_path, content)
+    });
+
+    if let Err(e) = result {
+        if json {
+            print_json_outcome("restore", None, "error", Some(format!("{}: {}", backup_path.display(), e)));
+        } else {
+            eprintln!("failed to restore {}: {}", backup_path.display(), e);
+        }
+        return;
+    }
+
+    if json {
+        print_json_outcome("restore", None, "restored", None);
     } else {
-        println!("no config file to clear");
+        println!("{} {}", "restored config from".green(), backup_path.display().to_string().bright_black());
+    }
+}
+
+/// Revert the last add/remove/clear by restoring the most recent history snapshot
+pub fn undo_config_change(json: bool) {
+    let Some((_, snapshot)) = list_history_entries().pop() else {
+        if json {
+            print_json_outcome("undo", None, "no_history", None);
+        } else {
+            println!("nothing to undo");
+        }
+        return;
+    };
+
+    let result = fs::read_to_string(&snapshot).and_then(|content| {
+        let config_path = get_config_path();
+        if content.trim().is_empty() {
+            // The config didn't exist (or was empty) before the change
+            if config_path.exists() {
+                fs::remove_file(&config_path)?;
+            }
+        } else {
+            fs::write(&config_path, content)?;
+        }
+        fs::remove_file(&snapshot)
+    });
+
+    if let Err(e) = result {
+        if json {
+            print_json_outcome("undo", None, "error", Some(e.to_string()));
+        } else {
+            eprintln!("failed to undo: {}", e);
+        }
+        return;
     }
-}
\ No newline at end of file
+
+    if json {
+        print_json_outcome("undo", None, "undone", None);
+    } else {
+        println!("{}", "reverted last config change".green());
+        let remaining = load_config_patterns();
+        if !remaining.is_empty() {
+            println!("{}", "custom ignore patterns:".bright_black());
+            for pattern in remaining {
+                println!("  {}", pattern.cyan());
+            }
+        }
+    }
+}
+
+/// Traverse `path` and report how many entries each config pattern hides.
+///
+/// A directory hit counts the directory plus everything beneath it, since
+/// the tree view never descends into it. Built-in ignored dirs are skipped
+/// the same way the tree skips them, so their contents never count.
+pub fn config_pattern_stats(path: &Path, json: bool) {
+    let patterns = load_config_patterns();
+    let compiled: Vec<(String, Option<regex::Regex>)> = patterns
+        .iter()
+        .map(|p| (p.clone(), pattern_to_regex(p)))
+        .collect();
+    // (direct matches, entries hidden)
+    let mut hits: Vec<(usize, usize)> = vec![(0, 0); compiled.len()];
+
+    let mut walker = WalkDir::new(path).follow_links(false).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        if entry.depth() == 0 {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let is_dir = entry.file_type().is_dir();
+
+        let matched: Vec<usize> = compiled
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, re))| re.as_ref().map(|re| re.is_match(&name)).unwrap_or(false))
+            .map(|(i, _)| i)
+            .collect();
+
+        if !matched.is_empty() {
+            let hidden = if is_dir {
+                1 + WalkDir::new(entry.path())
+                    .follow_links(false)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.depth() > 0)
+                    .count()
+            } else {
+                1
+            };
+            for i in matched {
+                hits[i].0 += 1;
+                hits[i].1 += hidden;
+            }
+            if is_dir {
+                walker.skip_current_dir();
+            }
+        } else if is_dir && should_ignore_dir(&name) {
+            walker.skip_current_dir();
+        }
+    }
+
+    if json {
+        let rows: Vec<serde_json::Value> = compiled
+            .iter()
+            .zip(&hits)
+            .map(|((pattern, re), (matches, hidden))| {
+                json!({
+                    "pattern": pattern,
+                    "valid": re.is_some(),
+                    "matches": matches,
+                    "hidden": hidden,
+                })
+            })
+            .collect();
+        let out = json!({
+            "config_file": get_config_path().display().to_string(),
+            "path": path.display().to_string(),
+            "patterns": rows,
+        });
+        println!("{}", out);
+        return;
+    }
+
+    if patterns.is_empty() {
+        println!("no custom patterns configured");
+        return;
+    }
+
+    println!("{} {}", "pattern hits under".bright_black(), path.display().to_string().cyan());
+    let width = patterns.iter().map(|p| p.chars().count()).max().unwrap_or(0);
+    let mut unused = 0;
+    for ((pattern, re), (matches, hidden)) in compiled.iter().zip(&hits) {
+        let padded = format!("{:<width$}", pattern, width = width);
+        if re.is_none() {
+            println!("  {}  {}", padded.red(), "invalid pattern".red());
+        } else if *matches == 0 {
+            unused += 1;
+            println!("  {}  {}", padded.yellow(), "matched nothing".yellow());
+        } else {
+            println!(
+                "  {}  {}",
+                padded.cyan(),
+                format!("{} matches · {} entries hidden", format_count(matches), format_count(hidden)).bright_black()
+            );
+        }
+    }
+    if unused > 0 {
+        println!(
+            "\n{}",
+            format!("{} pattern(s) matched nothing — remove with: struct remove", unused).yellow()
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use colored::*;
+use git2::Repository;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::theme::paint_dir;
+use crate::utils::{format_count, format_size};
+
+pub struct CopyOptions {
+    /// Print the tree of what would be copied and touch nothing
+    pub dry_run: bool,
+    /// Also leave out what the repository's .gitignore files ignore
+    pub gitignore: bool,
+}
+
+/// `.gitignore` lookups for the repo SRC lives in
+pub struct GitIgnores {
+    repo: Repository,
+    workdir: PathBuf,
+}
+
+impl GitIgnores {
+    pub fn new(path: &Path) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        Some(GitIgnores { repo, workdir })
+    }
+
+    fn ignores(&self, path: &Path) -> bool {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        match canonical.strip_prefix(&self.workdir) {
+            Ok(rel) if !rel.as_os_str().is_empty() => self.repo.is_path_ignored(rel).unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+/// One kept entry, relative to SRC
+pub struct Item {
+    pub rel: PathBuf,
+    pub kind: Kind,
+    pub size: u64,
+}
+
+#[derive(PartialEq)]
+pub enum Kind {
+    Dir,
+    File,
+    Symlink,
+}
+
+/// Walk SRC and split it into what gets copied and how many entries the
+/// rules leave out (an ignored dir counts once — nothing below it is read)
+pub fn plan_copy(
+    src: &Path,
+    exclude: Option<&Path>,
+    ignores: &IgnoreRules,
+    git: Option<&GitIgnores>,
+) -> (Vec<Item>, usize) {
+    let mut skipped = 0;
+    let mut keep = |e: &walkdir::DirEntry| {
+        if e.depth() == 0 {
+            return true;
+        }
+        let name = e.file_name().to_string_lossy();
+        let ignored = if e.file_type().is_dir() { ignores.ignores_dir(&name) } else { ignores.ignores_file(&name) }
+            || ignores.is_project_ignored(e.path())
+            || git.is_some_and(|g| g.ignores(e.path()))
+            || exclude.is_some_and(|x| e.path().canonicalize().is_ok_and(|p| p == x));
+        if ignored {
+            skipped += 1;
+        }
+        !ignored
+    };
+
+    let mut items = Vec::new();
+    for entry in WalkDir::new(src).follow_links(false).into_iter().filter_entry(&mut keep).filter_map(|e| e.ok()) {
+        if entry.depth() == 0 {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(src).unwrap_or(entry.path()).to_path_buf();
+        let file_type = entry.file_type();
+        let (kind, size) = if file_type.is_symlink() {
+            (Kind::Symlink, 0)
+        } else if file_type.is_dir() {
+            (Kind::Dir, 0)
+        } else {
+            (Kind::File, entry.metadata().map(|m| m.len()).unwrap_or(0))
+        };
+        items.push(Item { rel, kind, size });
+    }
+    (items, skipped)
+}
+
+/// Absolute form of a path that may not exist yet (its parent must)
+pub fn absolute(path: &Path) -> PathBuf {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => parent.canonicalize().map(|p| p.join(path.file_name().unwrap_or_default())),
+        None => std::env::current_dir().map(|d| d.join(path)),
+    }
+    .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Draw the kept entries as a tree (WalkDir already yields parents before children)
+fn print_plan(dest: &Path, items: &[Item]) {
+    let mut children: BTreeMap<PathBuf, Vec<&Item>> = BTreeMap::new();
+    for item in items {
+        let parent = item.rel.parent().map(Path::to_path_buf).unwrap_or_default();
+        children.entry(parent).or_default().push(item);
+    }
+    for list in children.values_mut() {
+        list.sort_by_cached_key(|i| (i.kind != Kind::Dir, i.rel.to_string_lossy().to_lowercase()));
+    }
+
+    fn draw(dir: &Path, prefix: &str, children: &BTreeMap<PathBuf, Vec<&Item>>) {
+        let Some(list) = children.get(dir) else { return };
+        for (i, item) in list.iter().enumerate() {
+            let last = i == list.len() - 1;
+            let name = item.rel.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let label = match item.kind {
+                Kind::Dir => paint_dir(&format!("{}/", name)),
+                Kind::Symlink => name.cyan(),
+                Kind::File => name.normal(),
+            };
+            println!("{}{}{}", prefix, if last { "└── " } else { "├── " }, label);
+            if item.kind == Kind::Dir {
+                draw(&item.rel, &format!("{}{}", prefix, if last { "    " } else { "│   " }), children);
+            }
+        }
+    }
+
+    println!("{} {}", dest.display().to_string().cyan(), "(dry run)".bright_black());
+    draw(Path::new(""), "", &children);
+}
+
+/// Copy one kept entry; symlinks are recreated as links, never followed
+fn copy_item(src: &Path, dest: &Path, item: &Item) -> std::io::Result<()> {
+    let from = src.join(&item.rel);
+    let to = dest.join(&item.rel);
+    match item.kind {
+        Kind::Dir => fs::create_dir_all(&to),
+        Kind::File => fs::copy(&from, &to).map(|_| ()),
+        Kind::Symlink => {
+            let target = fs::read_link(&from)?;
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(&target, &to)
+            }
+            #[cfg(windows)]
+            {
+                if from.is_dir() {
+                    std::os::windows::fs::symlink_dir(&target, &to)
+                } else {
+                    std::os::windows::fs::symlink_file(&target, &to)
+                }
+            }
+        }
+    }
+}
+
+/// `struct copy SRC DEST`: copy a tree without what the ignore rules hide —
+/// built-in ignores, config and `-i` patterns, and (unless turned off) the
+/// repo's .gitignore. DEST must not exist yet or be an empty directory.
+/// Returns false after printing an error.
+pub fn copy_tree(src: &Path, dest: &Path, ignores: &IgnoreRules, opts: &CopyOptions) -> bool {
+    if !src.is_dir() {
+        eprintln!("error: {} is not a directory", src.display());
+        return false;
+    }
+    if fs::read_dir(dest).is_ok_and(|mut d| d.next().is_some()) || dest.is_file() {
+        eprintln!("error: {} already exists and is not empty", dest.display());
+        return false;
+    }
+
+    // DEST inside SRC (`struct copy . clean/`) must not be copied into itself
+    let src_abs = src.canonicalize().unwrap_or_else(|_| src.to_path_buf());
+    let dest_abs = absolute(dest);
+    if src_abs == dest_abs {
+        eprintln!("error: SRC and DEST are the same directory");
+        return false;
+    }
+    let exclude = dest_abs.starts_with(&src_abs).then_some(dest_abs.as_path());
+
+    let git = if opts.gitignore { GitIgnores::new(src) } else { None };
+    let (items, skipped) = plan_copy(src, exclude, ignores, git.as_ref());
+    let files = items.iter().filter(|i| i.kind != Kind::Dir).count();
+    let bytes: u64 = items.iter().map(|i| i.size).sum();
+    let summary = format!(
+        "{} files, {} dirs, {} · {} ignored entries left out",
+        format_count(files),
+        format_count(items.len() - files),
+        format_size(bytes),
+        format_count(skipped)
+    );
+
+    if opts.dry_run {
+        print_plan(dest, &items);
+        println!("\n{}", summary.bright_black());
+        return true;
+    }
+
+    if let Err(e) = fs::create_dir_all(dest) {
+        eprintln!("error: cannot create {}: {}", dest.display(), e);
+        return false;
+    }
+    let mut failed = 0;
+    for item in &items {
+        if let Err(e) = copy_item(src, dest, item) {
+            eprintln!("warning: {}: {}", src.join(&item.rel).display(), e);
+            failed += 1;
+        }
+    }
+    println!("{} {} → {}", "copied".green(), src.display(), dest.display());
+    println!("{}", summary.bright_black());
+    if failed > 0 {
+        eprintln!("{}", format!("{} entries could not be copied", format_count(failed)).yellow());
+        return false;
+    }
+    true
+}
@@ -0,0 +1,141 @@
+use chrono::Local;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+
+use crate::config::{get_config_path, get_roots_path};
+
+/// The flag, taken out of argv before anything else parses it
+pub const DEBUG_BUNDLE_FLAG: &str = "--debug-bundle";
+/// Lines of stderr kept for the bundle
+const LOG_LINES: usize = 200;
+/// Environment that changes what struct does or prints
+const ENV_VARS: &[&str] = &[
+    "STRUCT_FLAGS", "LANG", "LC_ALL", "LC_MESSAGES", "LC_NUMERIC", "LS_COLORS", "NO_COLOR", "CLICOLOR",
+    "CLICOLOR_FORCE", "COLUMNS", "TERM",
+];
+
+/// `--debug-bundle`: run the same command again as a child with a backtrace
+/// on, pass its output through, and keep the last lines of its stderr. If
+/// it panics, exits with 2 or more, or prints an `error:`, write args,
+/// config, platform and that stderr to a temp file and print its path.
+/// Exits with the child's status.
+///
+/// stdin and stdout are shared with the child, but stderr is not a
+/// terminal for it, so interactive prompts fall back to their non-tty path.
+pub fn run_with_debug_bundle(args: &[String]) -> ! {
+    let child_args: Vec<&String> = args.iter().skip(1).filter(|a| *a != DEBUG_BUNDLE_FLAG).collect();
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from(&args[0]));
+    // argv already has STRUCT_FLAGS spliced in; don't let the child add them twice
+    let spawned = Command::new(exe)
+        .args(&child_args)
+        .env_remove("STRUCT_FLAGS")
+        .env("RUST_BACKTRACE", "1")
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("error: cannot start struct for --debug-bundle: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut tail: VecDeque<String> = VecDeque::new();
+    let mut saw_error = false;
+    if let Some(stderr) = child.stderr.take() {
+        let mut out = std::io::stderr();
+        let ansi = Regex::new(r"\x1b\[[0-9;]*m").expect("valid regex");
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let _ = writeln!(out, "{}", line);
+            let line = ansi.replace_all(&line, "").to_string();
+            saw_error |= line.starts_with("error") || line.contains("panicked at");
+            if tail.len() == LOG_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    }
+    let status = match child.wait() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: lost the struct process: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let failed = status.code().is_none_or(|c| c >= 2) || saw_error;
+    if failed {
+        let report = bundle_text(&child_args, &status, &tail);
+        let file = std::env::temp_dir().join(format!(
+            "struct-debug-{}-{}.txt",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        ));
+        match fs::write(&file, report) {
+            Ok(()) => eprintln!("debug bundle: {} — attach it to the issue report", file.display()),
+            Err(e) => eprintln!("error: cannot write the debug bundle to {}: {}", file.display(), e),
+        }
+    }
+    std::process::exit(exit_code(&status));
+}
+
+/// The child's exit code; 128 + signal when it was killed (unix shells do the same)
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Everything an issue report needs, as plain text sections
+fn bundle_text(args: &[&String], status: &ExitStatus, tail: &VecDeque<String>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "struct debug bundle");
+    let _ = writeln!(out, "version:  {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "time:     {}", Local::now().format("%Y-%m-%d %H:%M:%S %z"));
+    let _ = writeln!(out, "status:   {}", status);
+    let quoted: Vec<String> = args.iter().map(|a| format!("{:?}", a)).collect();
+    let _ = writeln!(out, "args:     struct {}", quoted.join(" "));
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_else(|e| e.to_string());
+    let _ = writeln!(out, "cwd:      {}", cwd);
+    let _ = writeln!(
+        out,
+        "platform: {} {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::FAMILY
+    );
+    let _ = writeln!(out, "stdout:   {}", if std::io::stdout().is_terminal() { "terminal" } else { "not a terminal" });
+
+    let _ = writeln!(out, "\n── environment ──");
+    for var in ENV_VARS {
+        if let Ok(value) = std::env::var(var) {
+            let _ = writeln!(out, "{}={}", var, value);
+        }
+    }
+
+    for (title, path) in [("config", get_config_path()), ("roots", get_roots_path())] {
+        let _ = writeln!(out, "\n── {} ({}) ──", title, path.display());
+        match fs::read_to_string(&path) {
+            Ok(text) => out.push_str(&text),
+            Err(e) => {
+                let _ = writeln!(out, "(unreadable: {})", e);
+            }
+        }
+    }
+
+    let _ = writeln!(out, "\n── stderr (last {} lines) ──", LOG_LINES);
+    for line in tail {
+        let _ = writeln!(out, "{}", line);
+    }
+    out
+}
@@ -0,0 +1,92 @@
+use colored::*;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::{cache_dir, dir_sizes, format_size};
+
+/// Directory holding one size table per root, written by `--delta` runs;
+/// machine-local, so it lives with the other caches
+fn sizes_dir() -> PathBuf {
+    cache_dir().join("delta")
+}
+
+/// Table file for a root: its absolute path with `/` turned into `%`
+fn sizes_file(root: &Path) -> PathBuf {
+    let name: String = root
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c == ':' { '%' } else { c })
+        .collect();
+    sizes_dir().join(format!("{}.txt", name))
+}
+
+/// `--delta`: each directory's size now against the last `--delta` run on the
+/// same root, so a tree after a build shows what grew (`+120M`) or shrank
+pub struct SizeDelta {
+    /// The start path as given, which every tree path begins with
+    start: PathBuf,
+    /// Canonical start path, naming the table
+    root: PathBuf,
+    /// Recursive size of every dir under the start path, from one walk
+    now: HashMap<PathBuf, u64>,
+    /// Sizes from the last run, by path relative to the root
+    previous: BTreeMap<PathBuf, u64>,
+    /// Sizes of the dirs drawn this run, saved for the next one
+    seen: RefCell<BTreeMap<PathBuf, u64>>,
+}
+
+impl SizeDelta {
+    pub fn new(start: &Path) -> Self {
+        let root = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+        let previous = fs::read_to_string(sizes_file(&root))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| {
+                let (size, rel) = l.split_once('\t')?;
+                Some((PathBuf::from(rel), size.parse().ok()?))
+            })
+            .collect();
+        SizeDelta {
+            start: start.to_path_buf(),
+            root,
+            now: dir_sizes(start),
+            previous,
+            seen: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether there is no earlier run to compare against
+    pub fn is_first_run(&self) -> bool {
+        self.previous.is_empty()
+    }
+
+    /// Growth tag for a directory in the tree: `+120M` in red, `-3.2M` in
+    /// green, `new` for a dir the last run didn't see; None when unchanged
+    pub fn tag(&self, path: &Path) -> Option<ColoredString> {
+        let size = *self.now.get(path)?;
+        let rel = path.strip_prefix(&self.start).unwrap_or(path).to_path_buf();
+        self.seen.borrow_mut().insert(rel.clone(), size);
+        if self.is_first_run() {
+            return None;
+        }
+        match self.previous.get(&rel) {
+            None => Some("new".cyan()),
+            Some(&before) if size > before => Some(format!("+{}", format_size(size - before)).red()),
+            Some(&before) if size < before => Some(format!("-{}", format_size(before - size)).green()),
+            Some(_) => None,
+        }
+    }
+
+    /// Store this run's sizes as the baseline for the next; dirs not drawn
+    /// this time (a shallower depth, say) keep their old sizes, deleted ones are dropped
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut table = self.previous.clone();
+        table.retain(|rel, _| self.root.join(rel).is_dir());
+        table.extend(self.seen.borrow().iter().map(|(p, s)| (p.clone(), *s)));
+        fs::create_dir_all(sizes_dir())?;
+        let lines: Vec<String> = table.iter().map(|(rel, size)| format!("{}\t{}", size, rel.display())).collect();
+        fs::write(sizes_file(&self.root), lines.join("\n") + "\n")
+    }
+}
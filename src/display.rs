@@ -1,13 +1,42 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
 use colored::*;
-use git2::{Repository, StatusOptions};
+use git2::{
+    AttrCheckFlags, AttrValue, Delta, DiffFindOptions, DiffOptions, Patch, Repository, RepositoryState, RevparseMode,
+    StatusOptions,
+};
 use regex::Regex;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
-use crate::ignores::{should_ignore_dir, should_ignore_file, matches_custom_pattern};
-use crate::utils::{format_size, get_dir_size, is_executable};
+use crate::anchors::Anchors;
+use crate::annotate::ExternalAnnotations;
+use crate::archive::{is_archive, read_archive, ArchiveDir};
+use crate::badges::{meta_badges, Badges};
+use crate::config::load_config_setting;
+use crate::delta::SizeDelta;
+use crate::entry::{read_entries, Entry};
+use crate::filter::Filter;
+use crate::forge::{hyperlink, ForgeLinks};
+use crate::i18n::{tr, trf};
+use crate::ignores::{matches_custom_pattern, trace_decision, tracing, IgnoreRules};
+use crate::importance::Importance;
+use crate::lastcommit::{commit_tags, LastCommits};
+use crate::theme::{paint_dir, paint_external_symlink, paint_file, paint_symlink};
+use crate::tree::{visibility, NodeKind, TreeNode, Visibility};
+use crate::utils::{
+    count_lines, disk_space, file_attributes, format_count, format_permissions, format_size, get_dir_size, group_name,
+    is_cloud_placeholder, keep_offline, owner_name, relative_time, shallow_dir_size, skip_virtual, subtree_totals, spoken_size, tally_dir, terminal_width, truncate_to_width,
+    unreadable_note,
+};
+
+/// Ignored dirs that take longer than this to count get a `slow-dir` warning
+const SLOW_DIR_WARN: Duration = Duration::from_millis(1000);
 
 #[derive(Debug, Clone)]
 pub enum GitMode {
@@ -16,17 +45,302 @@ pub enum GitMode {
     Staged,       // --gs: files staged for commit
     Changed,      // --gc: modified files (not staged)
     History,      // --gh: show last commit per directory
+    Conflicts,    // --conflicts: unmerged paths during a merge/rebase
+    Diff,         // --gd A..B: files that differ between two commits
+}
+
+/// How a file differs between the two sides of `--gd A..B`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeChange {
+    Added,
+    Modified,
+    Deleted,
+    /// Moved here from this path (relative to the repo root)
+    Renamed(PathBuf),
+}
+
+/// `--gd`: what happened to each changed file, and its lines added/removed
+pub type RangeDiff = (HashMap<PathBuf, RangeChange>, HashMap<PathBuf, (usize, usize)>);
+
+/// What a directory's size percentage is relative to (--percent)
+#[derive(Debug, Clone)]
+pub enum PercentMode {
+    OfParent,      // --percent: share of the containing directory
+    OfRoot(u64),   // --percent=root: share of the whole tree (root size in bytes)
+}
+
+/// strftime pattern for `-t` when the config sets no `time_format`
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How `-t/--time` shows modification times
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeStyle {
+    Date(String), // -t: strftime pattern from `time_format` in the config file
+    Relative,     // -t=relative: "2h ago"
+}
+
+impl TimeStyle {
+    /// The style for a `--time` value; a `time_format` chrono can't parse
+    /// is reported and replaced by the default
+    pub fn from_flag(value: &str) -> Self {
+        if value == "relative" {
+            return TimeStyle::Relative;
+        }
+        let format = match load_config_setting("time_format") {
+            Some(f) if StrftimeItems::new(&f).any(|item| item == Item::Error) => {
+                eprintln!("warning: config time_format = \"{}\": not a valid strftime pattern", f);
+                DEFAULT_TIME_FORMAT.to_string()
+            }
+            Some(f) => f,
+            None => DEFAULT_TIME_FORMAT.to_string(),
+        };
+        TimeStyle::Date(format)
+    }
+
+    /// An entry's modification time in this style (None when unreadable)
+    fn format(&self, path: &Path) -> Option<String> {
+        let modified = fs::symlink_metadata(path).and_then(|m| m.modified()).ok()?;
+        Some(match self {
+            TimeStyle::Date(format) => DateTime::<Local>::from(modified).format(format).to_string(),
+            TimeStyle::Relative => relative_time(modified),
+        })
+    }
+}
+
+/// Entry order within a directory (`--sort`)
+pub enum SortKey {
+    Name,               // directories first, then alphabetical (default)
+    Smart(Importance),  // README and manifests first, locks and generated files last
+    Size,               // largest first; directories by their recursive size
+    Mtime,              // most recently modified first
+    Extension,          // directories first, then by extension, then name
+    Count,              // directories holding the most files first
+}
+
+impl SortKey {
+    pub fn from_flag(value: Option<&str>) -> Self {
+        match value {
+            Some("smart") => SortKey::Smart(Importance::load()),
+            Some("size") => SortKey::Size,
+            Some("mtime") => SortKey::Mtime,
+            Some("extension") => SortKey::Extension,
+            Some("count") => SortKey::Count,
+            _ => SortKey::Name,
+        }
+    }
+}
+
+/// Recursive sizes and file counts of directories, computed on first use.
+/// Asking for a directory walks it once and keeps the totals of everything
+/// below it too, so sorting and annotating a whole tree costs one walk.
+#[derive(Default)]
+pub struct DirSizes {
+    totals: RefCell<HashMap<PathBuf, (u64, usize, usize)>>,
+}
+
+impl DirSizes {
+    /// (bytes, files) under `path`
+    pub fn get(&self, path: &Path) -> (u64, usize) {
+        let (bytes, files, _) = self.totals(path);
+        (bytes, files)
+    }
+
+    /// Entries under `path` that couldn't be read, so its size is a lower bound
+    pub fn unreadable(&self, path: &Path) -> usize {
+        self.totals(path).2
+    }
+
+    fn totals(&self, path: &Path) -> (u64, usize, usize) {
+        if let Some(&total) = self.totals.borrow().get(path) {
+            return total;
+        }
+        let totals = subtree_totals(path);
+        let total = totals.get(path).copied().unwrap_or_default();
+        self.totals.borrow_mut().extend(totals);
+        total
+    }
+}
+
+/// --lines: lines of text per directory, counted on first use. Like the
+/// tree, a directory's total leaves out what the ignore rules hide; binary
+/// files count as nothing, placeholders and virtual filesystems aren't read.
+/// Asking for a directory walks it once and keeps the totals below it too.
+#[derive(Default)]
+pub struct LineCounts {
+    totals: RefCell<HashMap<PathBuf, usize>>,
+}
+
+impl LineCounts {
+    pub fn get(&self, path: &Path, ignores: &IgnoreRules) -> usize {
+        if let Some(&total) = self.totals.borrow().get(path) {
+            return total;
+        }
+        let mut totals: HashMap<PathBuf, usize> = HashMap::new();
+        let visible = |e: &walkdir::DirEntry| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0
+                || if e.file_type().is_dir() {
+                    !ignores.ignores_dir(&name)
+                        && !ignores.is_project_ignored(e.path())
+                        && !keep_offline(e.path())
+                        && skip_virtual(e.path()).is_none()
+                } else {
+                    !ignores.ignores_file(&name) && !ignores.is_project_ignored(e.path())
+                }
+        };
+        for entry in WalkDir::new(path).follow_links(false).into_iter().filter_entry(visible).filter_map(|e| e.ok()) {
+            if entry.file_type().is_dir() {
+                totals.entry(entry.path().to_path_buf()).or_default();
+                continue;
+            }
+            if !entry.file_type().is_file() || keep_offline(entry.path()) {
+                continue;
+            }
+            let Some(lines) = count_lines(entry.path()) else { continue };
+            for dir in entry.path().ancestors().skip(1) {
+                *totals.entry(dir.to_path_buf()).or_default() += lines;
+                if dir == path {
+                    break;
+                }
+            }
+        }
+        let total = totals.get(path).copied().unwrap_or_default();
+        self.totals.borrow_mut().extend(totals);
+        total
+    }
+}
+
+/// How symlinks are labeled (`symlink_style` in the config file)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymlinkStyle {
+    Name,     // name
+    Arrow,    // name -> target, as stored in the link (default)
+    Absolute, // name -> /resolved/absolute/target
+    Relative, // name -> target relative to the tree's root
+}
+
+impl SymlinkStyle {
+    pub fn from_config() -> Self {
+        match load_config_setting("symlink_style").as_deref() {
+            None | Some("arrow") => SymlinkStyle::Arrow,
+            Some("name") => SymlinkStyle::Name,
+            Some("absolute") => SymlinkStyle::Absolute,
+            Some("relative") => SymlinkStyle::Relative,
+            Some(other) => {
+                eprintln!("warning: config symlink_style = \"{}\": use name, arrow, absolute or relative", other);
+                SymlinkStyle::Arrow
+            }
+        }
+    }
 }
 
 pub struct StructConfig {
     pub depth: usize,
-    pub custom_ignores: Vec<Regex>,
+    /// The tree's root, canonical (symlinks pointing out of it are colored apart)
+    pub root: PathBuf,
+    pub symlink_style: SymlinkStyle,
+    pub ignores: IgnoreRules,
     pub max_size_bytes: Option<u64>,
     pub git_files: Option<HashSet<PathBuf>>,
     pub git_mode: Option<GitMode>,
     pub show_size: bool,
-    pub skip_defaults: bool,
-    pub skip_specific: Option<String>,
+    /// Modification times next to entries (-t/--time)
+    pub time: Option<TimeStyle>,
+    pub align_sizes: bool,
+    /// --shallow-size: dirs show the size of their own files only, not recursive
+    pub shallow_size: bool,
+    pub percent: Option<PercentMode>,
+    pub budget: Option<Duration>,
+    pub show_df: bool,
+    pub forge: Option<ForgeLinks>,
+    pub quiet: bool,
+    pub badges: Option<Badges>,
+    /// --meta-badges: README/LICENSE/CONTRIBUTING/CI slots on project dirs
+    pub meta_badges: bool,
+    pub group_by_ext: bool,
+    /// --where: files must match; directories stay when something below does
+    pub filter: Option<Filter>,
+    /// --only: files must match one of these patterns
+    pub only: Option<Vec<Regex>>,
+    /// --where/--only verdicts for directories already searched
+    pub filter_dirs: RefCell<HashMap<PathBuf, bool>>,
+    /// --sort: entry order within each directory
+    pub sort: SortKey,
+    /// --reverse: flip the --sort order
+    pub reverse: bool,
+    /// Recursive dir sizes, filled lazily (--sort size/count, --percent, -s)
+    pub dir_sizes: DirSizes,
+    /// --max-lines: cap on tree lines, the rest elided per directory
+    pub max_lines: Option<usize>,
+    /// --gc/--gs: lines added/removed per file
+    pub diffstat: Option<HashMap<PathBuf, (usize, usize)>>,
+    /// --conflicts: how each unmerged file conflicts
+    pub conflicts: Option<HashMap<PathBuf, &'static str>>,
+    /// --gd: how each file changed between the two commits
+    pub range_changes: Option<HashMap<PathBuf, RangeChange>>,
+    /// --gh: the last commit of each file and directory
+    pub last_commits: Option<LastCommits>,
+    /// --gu: wholly untracked dirs, collapsed unless --expand-untracked
+    pub untracked_dirs: Option<HashSet<PathBuf>>,
+    pub show_git_overhead: bool,
+    /// Files in the git index, to flag tracked code inside built-in ignored dirs
+    pub tracked_files: Option<HashSet<PathBuf>>,
+    /// --show-ignored: draw ignored entries struck through instead of hiding them
+    pub show_ignored: bool,
+    /// --timeout: stop walking at this instant and show what was gathered
+    pub deadline: Option<Instant>,
+    /// Set once the deadline cut the output short
+    pub timed_out: Cell<bool>,
+    /// Files .gitattributes marks as generated, drawn dimmed
+    pub generated: Option<GeneratedFiles>,
+    /// --hide-generated: leave generated files out instead of dimming them
+    pub hide_generated: bool,
+    /// --anchors: directory indexes handed out so far, saved for `struct goto`
+    pub anchors: Option<RefCell<Anchors>>,
+    /// --archives: zip/jar/tar contents drawn as subtrees of the archive
+    pub archives: bool,
+    /// --lines: line counts next to files and directories
+    pub lines: Option<LineCounts>,
+    /// --annotate: labels from the configured annotator commands
+    pub external: Option<ExternalAnnotations>,
+    /// --delta: directory sizes against the last --delta run on this root
+    pub delta: Option<SizeDelta>,
+    /// -p/--perms: a permissions column before the tree art
+    pub perms: bool,
+    /// --owner: user and group (unix) or attribute letters (windows) columns
+    pub owner: bool,
+    /// --a11y: "level N:" lines in plain words instead of box-drawing connectors
+    pub a11y: bool,
+}
+
+/// `.gitattributes` lookups for `linguist-generated` / `export-ignore`
+pub struct GeneratedFiles {
+    repo: Repository,
+    workdir: PathBuf,
+}
+
+impl GeneratedFiles {
+    /// None outside a git work tree
+    pub fn new(path: &Path) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        Some(GeneratedFiles { repo, workdir })
+    }
+
+    /// Whether `path` (a file, or a directory matched by name) is marked
+    /// `linguist-generated` or `export-ignore`
+    pub fn contains(&self, path: &Path) -> bool {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let Ok(rel) = canonical.strip_prefix(&self.workdir) else { return false };
+        if rel.as_os_str().is_empty() {
+            return false;
+        }
+        ["linguist-generated", "export-ignore"].iter().any(|attr| {
+            let value = self.repo.get_attr(rel, attr, AttrCheckFlags::default()).ok().flatten();
+            // `attr` and `attr=true` both count (linguist writes the latter)
+            matches!(AttrValue::from_string(value), AttrValue::True | AttrValue::String("true"))
+        })
+    }
 }
 
 /// Get git-tracked files (in index)
@@ -37,7 +351,7 @@ pub fn get_git_tracked_files(path: &Path) -> Option<HashSet<PathBuf>> {
         if let Ok(workdir) = repo.workdir().ok_or("No workdir") {
             if let Ok(index) = repo.index() {
                 for entry in index.iter() {
-                    if let Some(path_str) = std::str::from_utf8(&entry.path).ok() {
+                    if let Ok(path_str) = std::str::from_utf8(&entry.path) {
                         let full_path = workdir.join(path_str);
                         tracked.insert(full_path);
                     }
@@ -81,6 +395,25 @@ pub fn get_git_untracked_files(path: &Path) -> Option<HashSet<PathBuf>> {
     }
 }
 
+/// Directories whose contents are entirely untracked — what `git status`
+/// lists as a single `newdir/` entry
+pub fn get_git_untracked_dirs(path: &Path) -> Option<HashSet<PathBuf>> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.to_path_buf();
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true);
+    opts.recurse_untracked_dirs(false);
+
+    let statuses = repo.statuses(Some(&mut opts)).ok()?;
+    let dirs = statuses
+        .iter()
+        .filter(|e| e.status().is_wt_new())
+        .filter_map(|e| e.path().filter(|p| p.ends_with('/')).map(|p| workdir.join(p)))
+        .collect();
+    Some(dirs)
+}
+
 /// Get git-ignored files (matches .gitignore patterns)
 /// Get git-staged files (in staging area)
 pub fn get_git_staged_files(path: &Path) -> Option<HashSet<PathBuf>> {
@@ -138,6 +471,400 @@ pub fn get_git_changed_files(path: &Path) -> Option<HashSet<PathBuf>> {
     }
 }
 
+/// Get conflicted (unmerged) files, each with how both sides touched it
+pub fn get_git_conflicts(path: &Path) -> Option<HashMap<PathBuf, &'static str>> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.to_path_buf();
+    let index = repo.index().ok()?;
+
+    let mut conflicts = HashMap::new();
+    for conflict in index.conflicts().ok()?.filter_map(|c| c.ok()) {
+        let kind = match (conflict.ancestor.is_some(), conflict.our.is_some(), conflict.their.is_some()) {
+            (false, true, true) => "both added",
+            (true, true, true) => "both modified",
+            (_, false, true) => "deleted by us",
+            (_, true, false) => "deleted by them",
+            (_, false, false) => "both deleted",
+        };
+        let entry = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref());
+        if let Some(path_str) = entry.and_then(|e| std::str::from_utf8(&e.path).ok()) {
+            conflicts.insert(workdir.join(path_str), kind);
+        }
+    }
+    Some(conflicts)
+}
+
+/// Where a repository's disk space goes (--git-overhead)
+pub struct GitOverhead {
+    /// Everything under .git
+    pub total: u64,
+    /// Loose objects (objects/ minus packs)
+    pub loose: u64,
+    /// Packfiles and their indexes
+    pub packs: u64,
+    /// Git LFS object cache
+    pub lfs: u64,
+    /// The working tree without .git
+    pub worktree: u64,
+}
+
+/// Measure .git and the working tree of the repo containing `path`
+pub fn git_overhead(path: &Path) -> Option<GitOverhead> {
+    let repo = Repository::discover(path).ok()?;
+    let git_dir = repo.path();
+    let total = get_dir_size(git_dir);
+    let packs = get_dir_size(&git_dir.join("objects").join("pack"));
+    let loose = get_dir_size(&git_dir.join("objects")).saturating_sub(packs);
+    let lfs = get_dir_size(&git_dir.join("lfs"));
+
+    let worktree = match repo.workdir() {
+        Some(workdir) => {
+            let size = get_dir_size(workdir);
+            // A regular checkout keeps .git inside the working tree
+            if git_dir.starts_with(workdir) { size.saturating_sub(total) } else { size }
+        }
+        None => 0,
+    };
+    Some(GitOverhead { total, loose, packs, lfs, worktree })
+}
+
+/// In-progress operation shown in the header (`MERGING`, `REBASING`, ...), if any
+pub fn git_operation_state(repo: &Repository) -> Option<&'static str> {
+    match repo.state() {
+        RepositoryState::Clean => None,
+        RepositoryState::Merge => Some("MERGING"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("REVERTING"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("CHERRY-PICKING"),
+        RepositoryState::Bisect => Some("BISECTING"),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => Some("REBASING"),
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("AM"),
+    }
+}
+
+/// Lines added/removed per file: staged changes (HEAD → index) or unstaged
+/// ones (index → working tree). Binary files have no line counts.
+pub fn get_git_diffstat(path: &Path, staged: bool) -> Option<HashMap<PathBuf, (usize, usize)>> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.to_path_buf();
+
+    let mut opts = DiffOptions::new();
+    let diff = if staged {
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts)).ok()?
+    } else {
+        repo.diff_index_to_workdir(None, Some(&mut opts)).ok()?
+    };
+
+    let mut stats = HashMap::new();
+    for idx in 0..diff.deltas().len() {
+        let Ok(Some(patch)) = Patch::from_diff(&diff, idx) else { continue };
+        let Some(file) = patch.delta().new_file().path().or(patch.delta().old_file().path()) else {
+            continue;
+        };
+        if let Ok((_, added, removed)) = patch.line_stats() {
+            stats.insert(workdir.join(file), (added, removed));
+        }
+    }
+    Some(stats)
+}
+
+/// Files that differ between two commits, as `git diff` takes them:
+/// `A..B`, `A...B` (from their merge base), `A..` or `A` (to HEAD).
+/// Renames are detected; paths are absolute, in the working tree.
+pub fn get_git_range_diff(path: &Path, range: &str) -> Result<RangeDiff, String> {
+    let repo = Repository::discover(path).map_err(|_| tr("error: not in a git repository").to_string())?;
+    let workdir = repo.workdir().ok_or("a bare repository has no working tree")?.to_path_buf();
+    let fail = |e: git2::Error| format!("--gd {}: {}", range, e.message());
+    let spec = repo.revparse(range).map_err(fail)?;
+    let from = match spec.from() {
+        Some(from) => from.clone(),
+        None => repo.revparse_single("HEAD").map_err(fail)?,
+    };
+    let to = match spec.to() {
+        Some(to) => to.clone(),
+        None => repo.revparse_single("HEAD").map_err(fail)?,
+    };
+    let from = if spec.mode().contains(RevparseMode::MERGE_BASE) {
+        let base = repo.merge_base(from.id(), to.id()).map_err(fail)?;
+        repo.find_object(base, None).map_err(fail)?
+    } else {
+        from
+    };
+    let from_tree = from.peel_to_tree().map_err(fail)?;
+    let to_tree = to.peel_to_tree().map_err(fail)?;
+
+    let mut diff = repo
+        .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), Some(&mut DiffOptions::new()))
+        .map_err(fail)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true))).map_err(fail)?;
+
+    let (mut changes, mut stats) = (HashMap::new(), HashMap::new());
+    for (idx, delta) in diff.deltas().enumerate() {
+        let (old, new) = (delta.old_file().path(), delta.new_file().path());
+        let (file, change) = match delta.status() {
+            Delta::Added | Delta::Copied => (new, RangeChange::Added),
+            Delta::Deleted => (old, RangeChange::Deleted),
+            Delta::Renamed => (new, RangeChange::Renamed(old.map(Path::to_path_buf).unwrap_or_default())),
+            _ => (new.or(old), RangeChange::Modified),
+        };
+        let Some(file) = file else { continue };
+        let full = workdir.join(file);
+        if let Ok(Some(patch)) = Patch::from_diff(&diff, idx) {
+            if let Ok((_, added, removed)) = patch.line_stats() {
+                stats.insert(full.clone(), (added, removed));
+            }
+        }
+        changes.insert(full, change);
+    }
+    Ok((changes, stats))
+}
+
+/// --gd: changed files that aren't in the working tree (deleted, or only on
+/// one side of the range), as entries of the nearest directory that is —
+/// named `old/a.rs` when all of old/ is gone
+fn missing_changes(dir: &Path, changes: &HashMap<PathBuf, RangeChange>) -> Vec<Entry> {
+    let Ok(dir) = dir.canonicalize() else { return Vec::new() };
+    let mut missing = Vec::new();
+    for path in changes.keys().filter(|p| fs::symlink_metadata(p).is_err()) {
+        if path.ancestors().skip(1).find(|a| a.exists()) != Some(dir.as_path()) {
+            continue;
+        }
+        if let Ok(rel) = path.strip_prefix(&dir) {
+            missing.push(Entry::missing(path.clone(), rel.display().to_string()));
+        }
+    }
+    missing
+}
+
+/// Column where aligned names end and the size gutter begins (--align-sizes)
+const SIZE_GUTTER_COLUMN: usize = 60;
+/// Width of the right-aligned size gutter
+const SIZE_GUTTER_WIDTH: usize = 8;
+
+/// Everything printed after an entry's name; all optional
+#[derive(Default)]
+struct Annotations {
+    size: Option<String>,
+    /// Modification time (-t), after the size
+    time: Option<String>,
+    note: Option<String>,
+    /// Forge URL the name links to (--forge-links)
+    link: Option<String>,
+    /// Colored tags after the name: directory badges (--badges), diffstats
+    tags: Vec<ColoredString>,
+    /// --anchors index drawn before a directory's name
+    anchor: Option<String>,
+    /// What the entry is, in words, for --a11y ("executable file", "symbolic
+    /// link to ..."); "directory" or "file" from the name when unset
+    kind: Option<String>,
+    /// -p/--owner columns drawn before the tree art; blank when unset
+    columns: Option<String>,
+}
+
+/// Width of the -p column (`drwxr-xr-x`) and of one --owner name, as in `tree -pug`
+const PERMS_WIDTH: usize = 10;
+const OWNER_WIDTH: usize = 8;
+
+/// -p/--owner columns for one path, padded so the tree art lines up below:
+/// `drwxr-xr-x  ana      staff   `. `?` marks metadata that couldn't be read.
+/// None when neither flag is given.
+pub fn meta_columns(path: &Path, config: &StructConfig) -> Option<String> {
+    if !config.perms && !config.owner {
+        return None;
+    }
+    let metadata = fs::symlink_metadata(path).ok();
+    let mut columns = Vec::new();
+    if config.perms {
+        let perms = metadata.as_ref().map_or_else(|| "?".to_string(), format_permissions);
+        columns.push(format!("{:<width$}", perms, width = PERMS_WIDTH));
+    }
+    if config.owner {
+        let owner = match metadata {
+            Some(ref m) => match file_attributes(m) {
+                Some(attrs) => attrs,
+                None => format!("{:<width$} {}", owner_name(m), group_name(m), width = OWNER_WIDTH),
+            },
+            None => "?".to_string(),
+        };
+        columns.push(format!("{:<width$}", owner, width = 2 * OWNER_WIDTH + 1));
+    }
+    Some(format!("{}  ", columns.join("  ")))
+}
+
+/// Blank lead for lines without their own -p/--owner columns (markers,
+/// archive members), so their tree art stays in line
+fn blank_columns(config: &StructConfig) -> String {
+    let width = [(config.perms, PERMS_WIDTH), (config.owner, 2 * OWNER_WIDTH + 1)]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, w)| w + 2)
+        .sum();
+    " ".repeat(width)
+}
+
+/// Print a single tree line with an optional size and trailing note.
+///
+/// Default layout:  `name (size, note)`
+/// --align-sizes:   `name ........ size (note)` with the size right-aligned
+/// in a fixed gutter so long listings can be scanned vertically.
+fn print_entry(
+    prefix: &str,
+    connector: &str,
+    name: &ColoredString,
+    extra: Annotations,
+    config: &StructConfig,
+) {
+    if config.a11y {
+        return print_spoken(prefix, name, extra);
+    }
+    let Annotations { size, time, note, link, tags, anchor, columns, .. } = extra;
+    let lead = columns.unwrap_or_else(|| blank_columns(config));
+
+    // Width of the visible name, measured before any hyperlink escapes are added
+    let mut name_width = name.chars().count();
+    let mut display_name = match link {
+        Some(url) => hyperlink(&url, &name.to_string()),
+        None => name.to_string(),
+    };
+    if let Some(anchor) = anchor {
+        name_width += anchor.chars().count() + 1;
+        display_name = format!("{} {}", anchor.yellow(), display_name);
+    }
+    let tags_width: usize = tags.iter().map(|t| 1 + t.chars().count()).sum();
+    let tag_str: String = tags.iter().map(|t| format!(" {}", t)).collect();
+    let used = lead.chars().count() + prefix.chars().count() + connector.chars().count() + name_width;
+
+    // Plain annotation text and whether it sits in the --align-sizes gutter
+    let annotation = match (size, time, note) {
+        (None, None, None) => None,
+        (size, time, note) if config.align_sizes => Some((
+            format!(
+                "{:>width$}{}{}",
+                size.unwrap_or_default(),
+                time.map(|t| format!("  {}", t)).unwrap_or_default(),
+                note.map(|n| format!(" ({})", n)).unwrap_or_default(),
+                width = SIZE_GUTTER_WIDTH
+            ),
+            true,
+        )),
+        (size, time, note) => {
+            let parts: Vec<String> = [size, time, note].into_iter().flatten().collect();
+            Some((format!(" ({})", parts.join(", ")), false))
+        }
+    };
+    // In a narrow terminal the gutter moves left rather than wrapping the line
+    let gutter_pad = |used: usize, text: &str| {
+        let pad = SIZE_GUTTER_COLUMN.saturating_sub(used);
+        let room = terminal_width().map_or(pad, |w| w.saturating_sub(used + text.chars().count()));
+        pad.min(room).max(1)
+    };
+    let line_width = used
+        + tags_width
+        + match annotation {
+            Some((ref text, true)) => gutter_pad(used + tags_width, text) + text.chars().count(),
+            Some((ref text, false)) => text.chars().count(),
+            None => 0,
+        };
+
+    // Too wide for the terminal: keep the name on the tree line and move the
+    // tags and annotation to a continuation line under it, cut to fit, so a
+    // wrapped line never breaks the connectors below
+    if let Some(width) = terminal_width().filter(|&w| line_width > w && (tags_width > 0 || annotation.is_some())) {
+        println!("{}{}{}{}", lead, prefix, connector, display_name);
+        let stem: String = connector
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i == 0 && matches!(c, '├' | '│') { '│' } else { ' ' })
+            .collect();
+        let continuation = format!("{}{}{}  ", blank_columns(config), prefix, stem);
+        let mut room = width.saturating_sub(continuation.chars().count());
+        let mut line = continuation;
+        for tag in &tags {
+            let tag_width = tag.chars().count() + 1;
+            if tag_width > room {
+                break;
+            }
+            line.push_str(&format!("{} ", tag));
+            room -= tag_width;
+        }
+        if let Some((text, _)) = annotation {
+            let text = truncate_to_width(text.trim_start(), room);
+            line.push_str(&text.bright_black().to_string());
+        }
+        println!("{}", line.trim_end());
+        return;
+    }
+
+    match annotation {
+        None => println!("{}{}{}{}{}", lead, prefix, connector, display_name, tag_str),
+        Some((text, true)) => println!(
+            "{}{}{}{}{}{}{}",
+            lead,
+            prefix,
+            connector,
+            display_name,
+            tag_str,
+            " ".repeat(gutter_pad(used + tags_width, &text)),
+            text.bright_black()
+        ),
+        Some((text, false)) => {
+            println!("{}{}{}{}{}{}", lead, prefix, connector, display_name, tag_str, text.bright_black())
+        }
+    }
+}
+
+/// Tree depth of an entry drawn under `prefix` (each level indents four columns)
+fn level_of(prefix: &str) -> usize {
+    prefix.chars().count() / 4 + 1
+}
+
+/// --a11y: one plain-language line per entry, with the depth spelled out
+/// instead of drawn with box characters:
+///
+///   level 2: main.rs, file, 3.2 kilobytes
+fn print_spoken(prefix: &str, name: &ColoredString, extra: Annotations) {
+    let Annotations { size, time, note, tags, anchor, kind, columns, .. } = extra;
+    let is_dir = name.ends_with('/');
+    let kind = kind.unwrap_or_else(|| tr(if is_dir { "directory" } else { "file" }).to_string());
+
+    let mut parts = vec![name.trim_end_matches('/').to_string(), kind];
+    parts.extend(anchor.map(|a| trf("anchor {}", &[&a])));
+    parts.extend(columns.map(|c| c.split_whitespace().collect::<Vec<_>>().join(" ")));
+    parts.extend(size.map(|s| spoken_size(&s)));
+    parts.extend(time.map(|t| trf("modified {}", &[&t])));
+    parts.extend(note);
+    parts.extend(tags.iter().map(|t| t.to_string()));
+    println!("{}", trf("level {}: {}", &[&level_of(prefix), &parts.join(", ")]));
+}
+
+/// A line that isn't an entry — elision and timeout markers, --group-by
+/// headers — drawn as a tree branch, or as a plain level line with --a11y
+fn print_marker(prefix: &str, connector: &str, text: ColoredString, config: &StructConfig) {
+    if config.a11y {
+        println!("{}", trf("level {}: {}", &[&level_of(prefix), &text.trim_start_matches("… ")]));
+    } else {
+        println!("{}{}{}{}", blank_columns(config), prefix, connector, text);
+    }
+}
+
+/// `note` with `incomplete, N unreadable` added when a size missed entries
+fn with_unreadable(note: String, unreadable: usize) -> String {
+    match unreadable_note(unreadable) {
+        Some(missed) => format!("{}, {}", note, missed),
+        None => note,
+    }
+}
+
+/// Format `part` as a percentage of `whole` (one decimal place)
+fn format_percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        return "0.0%".to_string();
+    }
+    format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+}
+
 /// Display directory tree
 pub fn display_tree(
     path: &Path,
@@ -150,195 +877,974 @@ pub fn display_tree(
         return;
     }
 
-    // Show git branch info at root level
-    if current_depth == 0 {
-        if let Ok(repo) = Repository::discover(path) {
-            if let Ok(head) = repo.head() {
-                if let Some(branch) = head.shorthand() {
-                    print!("{}", format!("(git:{}) ", branch).bright_black());
+    // Header line at root level: git branch info (left out with -q), then
+    // whatever was asked for with --df, --git-overhead and --delta
+    let requested = config.show_df || config.show_git_overhead || config.delta.is_some();
+    if current_depth == 0 && (!config.quiet || requested) {
+        match Repository::discover(path) {
+            Ok(repo) if !config.quiet => {
+                if let Ok(head) = repo.head() {
+                    if let Some(branch) = head.shorthand() {
+                        if config.a11y {
+                            print!("{}", trf("git branch {}. ", &[&branch]));
+                        } else {
+                            print!("{}", format!("(git:{}) ", branch).bright_black());
+                        }
+                    }
+                }
+                if let Some(state) = git_operation_state(&repo) {
+                    print!("{}", format!("[{}] ", state).red().bold());
                 }
             }
+            _ => {}
+        }
+        if config.show_df {
+            match disk_space(path) {
+                Some((total, used, available)) => {
+                    let pct = (used * 100).checked_div(total).unwrap_or(0);
+                    print!(
+                        "{}",
+                        format!(
+                            "[disk: {} used of {} ({}%), {} free] ",
+                            format_size(used),
+                            format_size(total),
+                            pct,
+                            format_size(available)
+                        )
+                        .bright_black()
+                    );
+                }
+                None => print!("{}", "[disk: unavailable] ".bright_black()),
+            }
+        }
+        if config.show_git_overhead {
+            if let Some(o) = git_overhead(path) {
+                print!(
+                    "{}",
+                    format!(
+                        "[.git: {} (packs {}, loose {}, lfs {}) vs worktree {}] ",
+                        format_size(o.total),
+                        format_size(o.packs),
+                        format_size(o.loose),
+                        format_size(o.lfs),
+                        format_size(o.worktree)
+                    )
+                    .bright_black()
+                );
+            }
+        }
+        if let Some(ref delta) = config.delta {
+            if delta.is_first_run() {
+                delta.tag(path);
+                print!("{}", "[delta: first run, sizes recorded] ".bright_black());
+            } else {
+                let change = delta.tag(path).map(|t| t.to_string()).unwrap_or_else(|| "±0".to_string());
+                print!("{}{}{}", "[delta: ".bright_black(), change, " since last run] ".bright_black());
+            }
         }
-        println!("");
+        println!();
+    }
+
+    display_dir(path, config, current_depth, prefix, config.max_lines);
+}
+
+/// Score --sort smart gives generated files at the least, whatever the table says
+const GENERATED_SCORE: i32 = 90;
+
+/// Order a directory's entries per --sort (directories first, then
+/// alphabetically, by default), flipped with --reverse. Symlinks to dirs
+/// count as files.
+fn sort_entries(entries: &mut [Entry], config: &StructConfig) {
+    let name = |e: &Entry| e.name.to_lowercase();
+    match config.sort {
+        SortKey::Name => entries.sort_by_cached_key(|e| (!e.is_dir(), name(e))),
+        SortKey::Smart(ref importance) => entries.sort_by_cached_key(|e| {
+            let mut score = importance.score(&e.name, e.is_dir());
+            if config.generated.as_ref().is_some_and(|g| g.contains(&e.path)) {
+                score = score.max(GENERATED_SCORE);
+            }
+            (score, !e.is_dir(), name(e))
+        }),
+        SortKey::Size => entries.sort_by_cached_key(|e| {
+            let size = if e.is_dir() { config.dir_sizes.get(&e.path).0 } else { e.size() };
+            (Reverse(size), name(e))
+        }),
+        SortKey::Mtime => entries.sort_by_cached_key(|e| {
+            let modified = fs::symlink_metadata(&e.path).and_then(|m| m.modified()).ok();
+            (Reverse(modified), name(e))
+        }),
+        SortKey::Extension => entries.sort_by_cached_key(|e| {
+            let ext = if e.is_dir() { None } else { Path::new(&e.name).extension().map(|x| x.to_ascii_lowercase()) };
+            (!e.is_dir(), ext, name(e))
+        }),
+        SortKey::Count => entries.sort_by_cached_key(|e| {
+            let files = if e.is_dir() { config.dir_sizes.get(&e.path).1 } else { 0 };
+            (Reverse(files), !e.is_dir(), name(e))
+        }),
+    }
+    if config.reverse {
+        entries.reverse();
+    }
+}
+
+/// Print a directory's contents, fitting them into `budget` lines when set
+/// (--max-lines). Returns the number of lines printed.
+fn display_dir(
+    path: &Path,
+    config: &StructConfig,
+    current_depth: usize,
+    prefix: &str,
+    budget: Option<usize>,
+) -> usize {
+    // Out of lines: the parent's `+k more` note already covers this directory
+    if current_depth >= config.depth || budget == Some(0) {
+        return 0;
+    }
+
+    let Some(mut entries) = read_entries(path) else { return 0 };
+    if let Some(ref changes) = config.range_changes {
+        entries.extend(missing_changes(path, changes));
     }
 
-    let mut entries: Vec<_> = match fs::read_dir(path) {
-        Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
-        Err(_) => return,
+    sort_entries(&mut entries, config);
+    entries.iter().for_each(|e| trace_entry(e, config));
+
+    let visible: Vec<&Entry> = entries.iter().filter(|e| !is_hidden(e, config)).collect();
+
+    // Base for --percent: what this level lists (so the shares add up), or
+    // the whole tree's size
+    let percent_base = match config.percent {
+        Some(PercentMode::OfParent) => Some(
+            visible
+                .iter()
+                .map(|e| if e.is_dir() { config.dir_sizes.get(&e.path).0 } else { e.size() })
+                .sum(),
+        ),
+        Some(PercentMode::OfRoot(root_size)) => Some(root_size),
+        None => None,
+    };
+    let (shown, elided) = match budget {
+        Some(lines) => select_within(visible, lines, config),
+        None => (visible, 0),
     };
 
-    // Sort: directories first, then alphabetically
-    entries.sort_by_key(|e| {
-        let path = e.path();
-        // Check if it's a symlink pointing to a directory
-        let is_dir = if path.is_symlink() {
-            // Don't follow symlinks to avoid infinite loops
-            false
-        } else {
-            path.is_dir()
-        };
-        let name = e.file_name().to_string_lossy().to_lowercase();
-        (!is_dir, name)
-    });
+    // Lines left over after this level's own lines go to the subdirectories
+    let own_lines = level_lines(&shown, elided, config);
+    let mut allot = Allot {
+        spare: budget.map(|b| b.saturating_sub(own_lines)),
+        dirs_left: shown.iter().filter(|e| e.is_dir()).count(),
+    };
 
-    let total = entries.len();
+    let mut used = if config.group_by_ext {
+        display_grouped(&shown, elided > 0, config, current_depth, prefix, percent_base, &mut allot)
+    } else {
+        let mut used = 0;
+        for (idx, entry) in shown.iter().enumerate() {
+            if out_of_time(config) {
+                return used + print_truncated(prefix, config);
+            }
+            let is_last = idx == shown.len() - 1 && elided == 0;
+            used += display_allotted(entry, is_last, config, current_depth, prefix, percent_base, &mut allot);
+        }
+        used
+    };
 
-    for (idx, entry) in entries.iter().enumerate() {
-        let is_last_entry = idx == total - 1;
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
-        
-        // Check if it's a symlink first - NEVER recurse into symlinks
-        let is_symlink = path.is_symlink();
-        let is_dir = if is_symlink {
-            false  // Treat symlinks as files to prevent recursion
-        } else {
-            path.is_dir()
-        };
+    if elided > 0 {
+        print_marker(prefix, "└── ", trf("… +{} more", &[&format_count(elided)]).bright_black(), config);
+        used += 1;
+    }
+    used
+}
 
-        // Check git mode FIRST - this overrides everything
-        if let Some(ref git_files) = config.git_files {
-            // Canonicalize the path for comparison (relative vs absolute issue)
-            let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-            
-            if is_dir {
-                // For directories, check if ANY tracked file is inside this directory
-                let has_tracked_files = git_files.iter().any(|f| f.starts_with(&canonical_path));
-                if !has_tracked_files {
-                    continue; // Skip this directory, no tracked files inside
+/// --long: one tab-separated line per entry — permissions, owner, size in
+/// bytes (`-` for dirs), mtime, path — with the tree's ignore and git filters.
+/// Built-in ignored dirs are listed but not descended into.
+pub fn display_long(root: &Path, dir: &Path, config: &StructConfig, current_depth: usize) {
+    if current_depth >= config.depth {
+        return;
+    }
+    let Some(mut entries) = read_entries(dir) else { return };
+    sort_entries(&mut entries, config);
+    entries.iter().for_each(|e| trace_entry(e, config));
+
+    for entry in entries.iter().filter(|e| !is_hidden(e, config)) {
+        if out_of_time(config) {
+            return;
+        }
+        let path = &entry.path;
+        let is_dir = entry.is_dir();
+        let Some(metadata) = entry.metadata() else { continue };
+
+        let rel = path.strip_prefix(root).unwrap_or(path).display().to_string();
+        let modified = metadata
+            .modified()
+            .map(|t| DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| "-".to_string());
+        let size = if is_dir { "-".to_string() } else { metadata.len().to_string() };
+        println!(
+            "{}\t{}\t{}\t{}\t{}{}",
+            format_permissions(metadata),
+            owner_name(metadata),
+            size,
+            modified,
+            rel,
+            if is_dir { "/" } else { "" }
+        );
+
+        let collapsed = config.git_files.is_none() && config.ignores.is_default_dir(&entry.name);
+        if is_dir && !collapsed {
+            display_long(root, path, config, current_depth + 1);
+        }
+    }
+}
+
+/// --timeout: whether the traversal has run out of time (and remember that it did)
+fn out_of_time(config: &StructConfig) -> bool {
+    match config.deadline {
+        Some(deadline) if Instant::now() >= deadline => {
+            config.timed_out.set(true);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Marker for a directory whose remaining entries were cut off by --timeout
+fn print_truncated(prefix: &str, config: &StructConfig) -> usize {
+    print_marker(prefix, "└── ", tr("(truncated)").yellow(), config);
+    1
+}
+
+/// Time allowed for counting one ignored dir: --budget, capped by what's left of --timeout
+fn count_budget(config: &StructConfig) -> Option<Duration> {
+    let left = config.deadline.map(|d| d.saturating_duration_since(Instant::now()));
+    match (config.budget, left) {
+        (Some(budget), Some(left)) => Some(budget.min(left)),
+        (budget, left) => budget.or(left),
+    }
+}
+
+/// Where a symlink ends up, absolute: fully resolved, or for a dangling
+/// link its target joined to the link's directory and cleaned up lexically
+fn resolve_link(path: &Path, target: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| {
+        let parent = path.parent().and_then(|p| p.canonicalize().ok()).unwrap_or_default();
+        let mut resolved = PathBuf::new();
+        for part in parent.join(target).components() {
+            match part {
+                Component::ParentDir => {
+                    resolved.pop();
                 }
+                Component::CurDir => {}
+                other => resolved.push(other),
+            }
+        }
+        resolved
+    })
+}
+
+/// `path` relative to `base` (both absolute), with `..` where it leaves `base`
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let common = path.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+    let mut rel = PathBuf::new();
+    for _ in base.components().skip(common) {
+        rel.push("..");
+    }
+    rel.extend(path.components().skip(common));
+    if rel.as_os_str().is_empty() { PathBuf::from(".") } else { rel }
+}
+
+/// A symlink's label in the configured `symlink_style`, colored apart when
+/// it points outside the tree (`color.symlink_external`)
+fn symlink_label(name: &str, path: &Path, config: &StructConfig) -> ColoredString {
+    let Ok(target) = fs::read_link(path) else { return paint_symlink(name) };
+    let resolved = resolve_link(path, &target);
+    let label = match config.symlink_style {
+        SymlinkStyle::Name => name.to_string(),
+        SymlinkStyle::Arrow => format!("{} -> {}", name, target.display()),
+        SymlinkStyle::Absolute => format!("{} -> {}", name, resolved.display()),
+        SymlinkStyle::Relative => format!("{} -> {}", name, relative_to(&resolved, &config.root).display()),
+    };
+    if resolved.starts_with(&config.root) {
+        paint_symlink(&label)
+    } else {
+        paint_external_symlink(&label)
+    }
+}
+
+/// Next --anchors index for a directory about to be drawn
+fn anchor_for(config: &StructConfig, path: &Path) -> Option<String> {
+    config.anchors.as_ref().map(|a| a.borrow_mut().assign(path))
+}
+
+/// --trace-ignores: log why an entry is collapsed, hidden or kept. A git
+/// mode replaces the ignore rules, so its file set is the only rule then.
+fn trace_entry(entry: &Entry, config: &StructConfig) {
+    if !tracing() {
+        return;
+    }
+    let (path, is_dir) = (&entry.path, entry.is_dir());
+    if config.hide_generated && config.generated.as_ref().is_some_and(|g| g.contains(path)) {
+        trace_decision(path, is_dir, "hidden", "generated (--hide-generated)");
+        return;
+    }
+    match (&config.git_files, &config.git_mode) {
+        (Some(git_files), Some(mode)) => {
+            let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            let listed = if is_dir {
+                git_files.iter().any(|f| f.starts_with(&canonical_path))
             } else {
-                // For files, check if this specific file is tracked
-                if !git_files.contains(&canonical_path) {
-                    continue; // Skip this untracked file
-                }
+                git_files.contains(&canonical_path)
+            };
+            if !listed {
+                let rule = format!("git mode {}: not in its file set", format!("{:?}", mode).to_lowercase());
+                trace_decision(path, is_dir, "hidden", &rule);
             }
-            // If we're in git mode and passed the check, skip all other filters
-        } else {
-            // Only apply normal ignore logic if NOT in git mode
-            // Check if we should skip this entry
+        }
+        _ => config.ignores.trace(path, &entry.name, is_dir),
+    }
+}
+
+/// Whether an entry is left out of the listing (not in the git set, or ignored).
+/// Built-in ignored dirs are not hidden — they're shown collapsed.
+fn is_hidden(entry: &Entry, config: &StructConfig) -> bool {
+    let (path, name) = (&entry.path, &entry.name);
+    let is_dir = entry.is_dir();
+
+    if config.hide_generated && config.generated.as_ref().is_some_and(|g| g.contains(path)) {
+        return true;
+    }
+    let hidden = match config.git_files {
+        Some(ref git_files) => {
+            let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
             if is_dir {
-                let should_skip = if config.skip_defaults {
-                    // -n defaults: don't ignore any defaults
-                    false
-                } else if let Some(ref specific) = config.skip_specific {
-                    // -n PATTERN: only ignore if it DOESN'T match the specific pattern
-                    &name != specific && should_ignore_dir(&name)
-                } else {
-                    // Normal mode: ignore defaults
-                    should_ignore_dir(&name)
-                };
+                !git_files.iter().any(|f| f.starts_with(&canonical_path))
+            } else {
+                !git_files.contains(&canonical_path)
+            }
+        }
+        // --show-ignored draws ignored entries instead of hiding them
+        None if config.show_ignored => false,
+        None => matches!(visibility(&config.ignores, path, name, is_dir), Visibility::Hidden(_)),
+    };
+    hidden || filtered_out(entry, config)
+}
 
-                if should_skip {
-                    // Count files in ignored directory
-                    let ignored_count = WalkDir::new(&path)
-                        .follow_links(false)
-                        .into_iter()
-                        .filter_map(|e| e.ok())
-                        .filter(|e| e.file_type().is_file())
-                        .count();
-
-                    let connector = if is_last_entry { "└── " } else { "├── " };
-                    let dir_name = format!("{}/", name).blue().bold();
-                    
-                    if config.show_size {
-                        let size = get_dir_size(&path);
-                        let size_str = format_size(size);
-                        let count_msg = format!(" ({}, {} files ignored)", size_str, ignored_count).bright_black();
-                        println!("{}{}{}{}", prefix, connector, dir_name, count_msg);
-                    } else {
-                        let count_msg = format!(" ({} files ignored)", ignored_count).bright_black();
-                        println!("{}{}{}{}", prefix, connector, dir_name, count_msg);
-                    }
-                    continue;
-                }
+/// Every entry the tree shows below `dir`, walked with the same depth,
+/// ignore rules and filters (for --attest). Built-in ignored dirs, virtual
+/// filesystems and cloud placeholders are listed but not descended into.
+pub fn listed_entries(dir: &Path, config: &StructConfig, current_depth: usize) -> Vec<Entry> {
+    if current_depth >= config.depth {
+        return Vec::new();
+    }
+    let Some(entries) = read_entries(dir) else { return Vec::new() };
+    let mut listed = Vec::new();
+    for entry in entries.into_iter().filter(|e| !is_hidden(e, config)) {
+        let descend = entry.is_dir()
+            && (config.git_files.is_some() || !config.ignores.is_default_dir(&entry.name))
+            && skip_virtual(&entry.path).is_none()
+            && !keep_offline(&entry.path);
+        let below = if descend { listed_entries(&entry.path, config, current_depth + 1) } else { Vec::new() };
+        listed.push(entry);
+        listed.extend(below);
+    }
+    listed
+}
+
+/// The tree as [`TreeNode`]s, walked with the same depth, git file set,
+/// ignore rules and filters as the drawn tree (for --output json and
+/// --interactive). Entries --show-ignored would strike through are left
+/// out; collapsed and oversized (-s) dirs carry their size but no children.
+pub fn tree_nodes(dir: &Path, config: &StructConfig, current_depth: usize) -> Vec<TreeNode> {
+    if current_depth >= config.depth {
+        return Vec::new();
+    }
+    let Some(mut entries) = read_entries(dir) else { return Vec::new() };
+    if let Some(ref changes) = config.range_changes {
+        entries.extend(missing_changes(dir, changes));
+    }
+    sort_entries(&mut entries, config);
+    entries.iter().for_each(|e| trace_entry(e, config));
+
+    let mut nodes = Vec::new();
+    for entry in entries.into_iter().filter(|e| !is_hidden(e, config)) {
+        let is_dir = entry.is_dir();
+        let shown = match config.git_files {
+            Some(_) => Visibility::Shown,
+            None => visibility(&config.ignores, &entry.path, &entry.name, is_dir),
+        };
+        let kind = if entry.is_symlink() {
+            NodeKind::Symlink { target: fs::read_link(&entry.path).ok() }
+        } else if is_dir {
+            NodeKind::Dir
+        } else {
+            NodeKind::File
+        };
+        let mut node = TreeNode { name: entry.name.clone(), path: entry.path.clone(), kind, size: None, ignored_files: None, children: None };
+        match shown {
+            Visibility::Hidden(_) => continue,
+            Visibility::Collapsed => {
+                let tally = tally_dir(&entry.path, count_budget(config));
+                node.size = Some(tally.bytes);
+                node.ignored_files = Some(tally.files);
+            }
+            _ if !is_dir => node.size = (node.kind == NodeKind::File && entry.metadata().is_some()).then(|| entry.size()),
+            _ if config.max_size_bytes.is_some_and(|max| config.dir_sizes.get(&entry.path).0 > max) => {
+                node.size = Some(config.dir_sizes.get(&entry.path).0);
+            }
+            _ if skip_virtual(&entry.path).is_some() || keep_offline(&entry.path) => {}
+            _ => {
+                node.children = (current_depth + 1 < config.depth).then(|| tree_nodes(&entry.path, config, current_depth + 1));
             }
+        }
+        nodes.push(node);
+    }
+    nodes
+}
 
-            // Check custom ignore patterns (unless we have a specific skip pattern)
-            if config.skip_specific.is_none() && matches_custom_pattern(&name, &config.custom_ignores) {
-                continue;
+/// --where and --only: a file that doesn't match, or a directory with no
+/// matching file anywhere below it. Built-in ignored dirs aren't searched
+/// and go too.
+fn filtered_out(entry: &Entry, config: &StructConfig) -> bool {
+    if config.filter.is_none() && config.only.is_none() {
+        return false;
+    }
+    if !entry.is_dir() {
+        let matches_where = config.filter.as_ref().is_none_or(|f| f.matches(entry));
+        let matches_only = config.only.as_ref().is_none_or(|p| matches_custom_pattern(&entry.name, p));
+        return !(matches_where && matches_only);
+    }
+    if config.ignores.is_default_dir(&entry.name) {
+        return true;
+    }
+    if let Some(&found) = config.filter_dirs.borrow().get(&entry.path) {
+        return !found;
+    }
+    let found = read_entries(&entry.path).is_some_and(|entries| entries.iter().any(|e| !is_hidden(e, config)));
+    config.filter_dirs.borrow_mut().insert(entry.path.clone(), found);
+    !found
+}
+
+/// --group-by ext header a file goes under; sorts extension-less files last
+fn ext_key(entry: &Entry) -> (bool, String) {
+    match entry.path.extension() {
+        Some(ext) => (false, format!(".{}", ext.to_string_lossy().to_lowercase())),
+        None => (true, "(no extension)".to_string()),
+    }
+}
+
+/// Lines a directory level takes itself: its entries, group headers and elision marker
+fn level_lines(shown: &[&Entry], elided: usize, config: &StructConfig) -> usize {
+    let headers = if config.group_by_ext {
+        let keys: HashSet<(bool, String)> =
+            shown.iter().filter(|e| !e.is_dir()).map(|e| ext_key(e)).collect();
+        keys.len()
+    } else {
+        0
+    };
+    shown.len() + headers + usize::from(elided > 0)
+}
+
+/// Pick the entries that fit in `lines` (--max-lines), most important first:
+/// directories, then files from largest to smallest, then collapsed ignored dirs.
+/// Returns the picks in their original order and how many were left out.
+fn select_within<'a>(
+    visible: Vec<&'a Entry>,
+    lines: usize,
+    config: &StructConfig,
+) -> (Vec<&'a Entry>, usize) {
+    if level_lines(&visible, 0, config) <= lines {
+        return (visible, 0);
+    }
+
+    let mut ranked: Vec<usize> = (0..visible.len()).collect();
+    ranked.sort_by_key(|&i| {
+        let entry = visible[i];
+        let class = if !entry.is_dir() {
+            1
+        } else if config.git_files.is_none() && config.ignores.is_default_dir(&entry.name) {
+            2
+        } else {
+            0
+        };
+        let size = if class == 1 { entry.size() } else { 0 };
+        (class, std::cmp::Reverse(size))
+    });
+
+    // Shrink until the picks, their group headers and the marker fit
+    let mut keep = lines.min(visible.len());
+    loop {
+        let mut picked = ranked[..keep].to_vec();
+        picked.sort_unstable();
+        let shown: Vec<&Entry> = picked.iter().map(|&i| visible[i]).collect();
+        if keep == 0 || level_lines(&shown, 1, config) <= lines {
+            let elided = visible.len() - shown.len();
+            return (shown, elided);
+        }
+        keep -= 1;
+    }
+}
+
+/// Spare lines under --max-lines, shared out to subdirectories as they're printed.
+/// Whatever one directory doesn't use is left for the ones after it.
+struct Allot {
+    spare: Option<usize>,
+    dirs_left: usize,
+}
+
+/// Print an entry, giving directories their share of the spare lines
+fn display_allotted(
+    entry: &Entry,
+    is_last: bool,
+    config: &StructConfig,
+    current_depth: usize,
+    prefix: &str,
+    percent_base: Option<u64>,
+    allot: &mut Allot,
+) -> usize {
+    if !entry.is_dir() {
+        return display_entry(entry, is_last, config, current_depth, prefix, percent_base, None);
+    }
+
+    let share = allot.spare.map(|s| s.div_ceil(allot.dirs_left.max(1)));
+    let used = display_entry(entry, is_last, config, current_depth, prefix, percent_base, share);
+    if let Some(ref mut spare) = allot.spare {
+        *spare = spare.saturating_sub(used.saturating_sub(1));
+    }
+    allot.dirs_left = allot.dirs_left.saturating_sub(1);
+    used
+}
+
+/// --group-by ext: directories first, then files under one header per extension
+fn display_grouped(
+    shown: &[&Entry],
+    elision_follows: bool,
+    config: &StructConfig,
+    current_depth: usize,
+    prefix: &str,
+    percent_base: Option<u64>,
+    allot: &mut Allot,
+) -> usize {
+    let dirs: Vec<&Entry> = shown.iter().copied().filter(|e| e.is_dir()).collect();
+
+    let mut groups: BTreeMap<(bool, String), Vec<&Entry>> = BTreeMap::new();
+    for entry in shown.iter().copied().filter(|e| !e.is_dir()) {
+        groups.entry(ext_key(entry)).or_default().push(entry);
+    }
+
+    let total = dirs.len() + groups.len();
+    let mut used = 0;
+    for (idx, entry) in dirs.iter().enumerate() {
+        if out_of_time(config) {
+            return used + print_truncated(prefix, config);
+        }
+        let is_last = idx == total - 1 && !elision_follows;
+        used += display_allotted(entry, is_last, config, current_depth, prefix, percent_base, allot);
+    }
+
+    for (idx, ((_, ext), files)) in groups.iter().enumerate() {
+        if out_of_time(config) {
+            return used + print_truncated(prefix, config);
+        }
+        let is_last_group = dirs.len() + idx == total - 1 && !elision_follows;
+        let connector = if is_last_group { "└── " } else { "├── " };
+        print_marker(prefix, connector, format!("{} ({})", ext, format_count(files.len())).bright_black(), config);
+        used += 1;
+
+        let group_prefix = if is_last_group {
+            format!("{}    ", prefix)
+        } else {
+            format!("{}│   ", prefix)
+        };
+        for (i, file) in files.iter().enumerate() {
+            let is_last = i == files.len() - 1;
+            used += display_entry(file, is_last, config, current_depth, &group_prefix, percent_base, None);
+        }
+    }
+    used
+}
+
+/// Print one entry of a directory listing (and recurse if it's a directory,
+/// within `budget` lines for its contents). Returns the number of lines printed.
+fn display_entry(
+    entry: &Entry,
+    is_last_entry: bool,
+    config: &StructConfig,
+    current_depth: usize,
+    prefix: &str,
+    percent_base: Option<u64>,
+    budget: Option<usize>,
+) -> usize {
+    let path = entry.path.clone();
+    let name = entry.name.clone();
+
+    // Symlinks are treated as files - NEVER recurse into them
+    let is_symlink = entry.is_symlink();
+    let is_dir = entry.is_dir();
+
+    // Check git mode FIRST - this overrides everything
+    if let Some(ref git_files) = config.git_files {
+        // Canonicalize the path for comparison (relative vs absolute issue)
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        
+        if is_dir {
+            // For directories, check if ANY tracked file is inside this directory
+            let has_tracked_files = git_files.iter().any(|f| f.starts_with(&canonical_path));
+            if !has_tracked_files {
+                return 0; // Skip this directory, no tracked files inside
             }
 
-            // Check file ignores
-            if !is_dir && should_ignore_file(&name) {
-                continue;
+            // Wholly untracked dir (--gu): one summary line, like `git status`
+            if config.untracked_dirs.as_ref().is_some_and(|dirs| dirs.contains(&canonical_path)) {
+                let count = git_files.iter().filter(|f| f.starts_with(&canonical_path)).count();
+                let label = if count == 1 { "{} new file" } else { "{} new files" };
+                let connector = if is_last_entry { "└── " } else { "├── " };
+                let extra = Annotations {
+                    note: Some(trf(label, &[&format_count(count)])),
+                    anchor: anchor_for(config, &path),
+                    columns: meta_columns(&path, config),
+                    ..Default::default()
+                };
+                print_entry(prefix, connector, &format!("{}/", name).red().bold(), extra, config);
+                return 1;
+            }
+        } else {
+            // For files, check if this specific file is tracked
+            if !git_files.contains(&canonical_path) {
+                return 0; // Skip this untracked file
             }
         }
+        // If we're in git mode and passed the check, skip all other filters
+    } else {
+        // Only apply normal ignore logic if NOT in git mode
+        // Check if we should skip this entry
+        let shown = visibility(&config.ignores, &path, &name, is_dir);
+        if is_dir && shown == Visibility::Collapsed {
+            // Count (and size) files in ignored directory in one walk
+            let tally = tally_dir(&path, count_budget(config));
+            if tally.elapsed > SLOW_DIR_WARN || !tally.complete {
+                // key=value on stderr so it can be grepped without touching stdout
+                eprintln!(
+                    "warning: slow-dir path={:?} entries={} elapsed_ms={} approximate={}",
+                    path.display().to_string(),
+                    tally.entries,
+                    tally.elapsed.as_millis(),
+                    !tally.complete
+                );
+            }
+            let approx = if tally.complete && !tally.saved { "" } else { "~" };
 
-        // Check size limit for directories
-        if is_dir {
-            if let Some(max_size) = config.max_size_bytes {
-                let size = get_dir_size(&path);
-                if size > max_size {
-                    let connector = if is_last_entry { "└── " } else { "├── " };
-                    let dir_name = format!("{}/", name).blue().bold();
-                    let size_mb = size / (1024 * 1024);
-                    let size_msg = format!(" ({}MB, skipped)", size_mb).bright_black();
-                    println!("{}{}{}{}", prefix, connector, dir_name, size_msg);
-                    continue;
+            // Tracked files in here are source, not build output — say so
+            if let Some(ref tracked) = config.tracked_files {
+                let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                let count = tracked.iter().filter(|f| f.starts_with(&canonical_path)).count();
+                if count > 0 {
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "note: {}/ is hidden by the built-in ignores but has {} tracked file(s) \
+                             — show it with -n {} or add unignore = [\"{}\"] to the config",
+                            path.display(),
+                            count,
+                            name,
+                            name
+                        )
+                        .yellow()
+                    );
                 }
             }
-        }
 
-        // Display the entry
-        let connector = if is_last_entry { "└── " } else { "├── " };
-        
-        // Color based on git status if in certain modes
-        let display_name = if is_symlink {
-            // Show symlink with arrow
-            if let Ok(target) = fs::read_link(&path) {
-                format!("{} -> {}", name, target.display()).cyan()
+            let connector = if is_last_entry { "└── " } else { "├── " };
+            let dir_name = if config.show_ignored {
+                format!("{}/", name).bright_black().strikethrough()
             } else {
-                name.cyan()
-            }
-        } else if is_dir {
-            format!("{}/", name).blue().bold()
-        } else {
-            // Color files based on git mode
-            if let Some(ref mode) = config.git_mode {
-                match mode {
-                    GitMode::Staged => name.green().bold(),
-                    GitMode::Changed => name.yellow().bold(),
-                    GitMode::Untracked => name.red(),
-                    _ => {
-                        if is_executable(&path) {
-                            name.green().bold()
-                        } else {
-                            name.normal()
-                        }
-                    }
+                paint_dir(&format!("{}/", name))
+            };
+
+            let mut count_msg = trf("{} files ignored", &[&format!("{}{}", approx, format_count(tally.files))]);
+            let size_str = if config.show_size {
+                if let Some(base) = percent_base {
+                    count_msg = format!("{}, {}", format_percent(tally.bytes, base), count_msg);
                 }
-            } else if is_executable(&path) {
-                name.green().bold()
+                Some(format!("{}{}", approx, format_size(tally.bytes)))
             } else {
-                name.normal()
+                None
+            };
+            let count_msg = with_unreadable(count_msg, tally.unreadable);
+            // No forge link — ignored dirs (.git, target, ...) aren't in the repo
+            print_entry(
+                prefix,
+                connector,
+                &dir_name,
+                Annotations {
+                    size: size_str,
+                    time: config.time.as_ref().and_then(|t| t.format(&path)),
+                    note: Some(count_msg),
+                    tags: config.delta.as_ref().and_then(|d| d.tag(&path)).into_iter().collect(),
+                    anchor: anchor_for(config, &path),
+                    columns: meta_columns(&path, config),
+                    ..Default::default()
+                },
+                config,
+            );
+            return 1;
+        }
+
+        if let Visibility::Hidden(reason) = shown {
+            if !config.show_ignored {
+                return 0;
             }
-        };
+            let connector = if is_last_entry { "└── " } else { "├── " };
+            let label = if is_dir { format!("{}/", name) } else { name.clone() };
+            let extra = Annotations {
+                note: Some(tr(reason).to_string()),
+                anchor: if is_dir { anchor_for(config, &path) } else { None },
+                columns: meta_columns(&path, config),
+                ..Default::default()
+            };
+            print_entry(prefix, connector, &label.bright_black().strikethrough(), extra, config);
+            return 1;
+        }
+    }
 
-        // Add size if requested
-        if config.show_size {
-            if is_dir {
-                println!("{}{}{}", prefix, connector, display_name);
-            } else {
-                if let Ok(metadata) = fs::metadata(&path) {
-                    let size_str = format!(" ({})", format_size(metadata.len())).bright_black();
-                    println!("{}{}{}{}", prefix, connector, display_name, size_str);
-                } else {
-                    println!("{}{}{}", prefix, connector, display_name);
+    // Check size limit for directories
+    if is_dir {
+        if let Some(max_size) = config.max_size_bytes {
+            let size = config.dir_sizes.get(&path).0;
+            if size > max_size {
+                let connector = if is_last_entry { "└── " } else { "├── " };
+                let dir_name = paint_dir(&format!("{}/", name));
+                let size_mb = size / (1024 * 1024);
+                let size_msg = format!("{}MB", size_mb);
+                let link = config.forge.as_ref().and_then(|f| f.url_for(&path, true));
+                let extra = Annotations {
+                    size: Some(size_msg),
+                    note: Some(with_unreadable(tr("skipped").to_string(), config.dir_sizes.unreadable(&path))),
+                    link,
+                    anchor: anchor_for(config, &path),
+                    columns: meta_columns(&path, config),
+                    ..Default::default()
+                };
+                print_entry(prefix, connector, &dir_name, extra, config);
+                return 1;
+            }
+        }
+    }
+
+    // Display the entry
+    let connector = if is_last_entry { "└── " } else { "├── " };
+    
+    // Color based on git status if in certain modes
+    let display_name = if is_symlink {
+        // --a11y says where it points in words instead
+        if config.a11y {
+            paint_symlink(&name)
+        } else {
+            symlink_label(&name, &path, config)
+        }
+    } else if is_dir {
+        paint_dir(&format!("{}/", name))
+    } else {
+        // Color files based on git mode
+        if let Some(ref mode) = config.git_mode {
+            match mode {
+                GitMode::Staged => name.green().bold(),
+                GitMode::Changed => name.yellow().bold(),
+                GitMode::Untracked => name.red(),
+                GitMode::Conflicts => name.red().bold(),
+                GitMode::Diff => {
+                    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                    match config.range_changes.as_ref().and_then(|c| c.get(&canonical_path)) {
+                        Some(RangeChange::Added) => name.green().bold(),
+                        Some(RangeChange::Deleted) => name.red().strikethrough(),
+                        Some(RangeChange::Renamed(_)) => name.cyan().bold(),
+                        _ => name.yellow().bold(),
+                    }
                 }
+                _ => paint_file(&name, &path, entry.is_executable(), |n| n.normal()),
             }
         } else {
-            println!("{}{}{}", prefix, connector, display_name);
+            paint_file(&name, &path, entry.is_executable(), |n| n.normal())
         }
+    };
 
-        // Recurse into directories
+    // Add size if requested (directories only get one with --percent)
+    let (size_str, percent_str) = match percent_base {
+        Some(base) if is_dir => {
+            let size = config.dir_sizes.get(&path).0;
+            let percent = with_unreadable(format_percent(size, base), config.dir_sizes.unreadable(&path));
+            (Some(format_size(size)), Some(percent))
+        }
+        _ if config.shallow_size && is_dir => {
+            (Some(trf("{} shallow", &[&format_size(shallow_dir_size(&path))])), None)
+        }
+        // --gd entries known only from git have no size
+        _ if config.show_size && !is_dir && entry.metadata().is_some() => {
+            (Some(format_size(entry.size())), None)
+        }
+        _ => (None, None),
+    };
+    let link = config.forge.as_ref().and_then(|f| f.url_for(&path, is_dir));
+    // Cloud placeholder dirs aren't listed unless --hydrate (that would download them),
+    // virtual filesystems like /proc unless --include-virtual
+    let virtual_kind = if is_dir { skip_virtual(&path) } else { None };
+    let offline = is_dir && (keep_offline(&path) || virtual_kind.is_some());
+    // --lines: after the size when -z is on too; binaries and placeholders get none
+    let lines = match config.lines {
+        Some(ref counts) if is_dir && !offline => Some(counts.get(&path, &config.ignores)),
+        Some(_) if !is_dir && !is_symlink && !keep_offline(&path) => count_lines(&path),
+        _ => None,
+    };
+    let size_str = match (size_str, lines.map(|n| trf("{} lines", &[&format_count(n)]))) {
+        (Some(size), Some(lines)) => Some(format!("{}, {}", size, lines)),
+        (size, lines) => size.or(lines),
+    };
+    let mut tags = match config.badges {
+        Some(ref badges) if is_dir && !offline => badges.for_dir(&path, &config.ignores),
+        _ => Vec::new(),
+    };
+    if config.meta_badges && is_dir && !offline {
+        tags.extend(meta_badges(&path));
+    }
+    if let Some(tag) = config.delta.as_ref().filter(|_| is_dir).and_then(|d| d.tag(&path)) {
+        tags.push(tag);
+    }
+    if is_cloud_placeholder(&path) {
+        tags.push("☁".cyan());
+    }
+    if let Some(ref stats) = config.diffstat {
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let (added, removed) = if is_dir {
+            stats
+                .iter()
+                .filter(|(p, _)| p.starts_with(&canonical_path))
+                .fold((0, 0), |(a, r), (_, &(pa, pr))| (a + pa, r + pr))
+        } else {
+            stats.get(&canonical_path).copied().unwrap_or((0, 0))
+        };
+        if added > 0 || removed > 0 {
+            tags.push(format!("+{}", added).green());
+            tags.push(format!("-{}", removed).red());
+        }
+    }
+    if let Some(ref conflicts) = config.conflicts {
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
         if is_dir {
-            let new_prefix = if is_last_entry {
-                format!("{}    ", prefix)
-            } else {
-                format!("{}│   ", prefix)
-            };
-            display_tree(&path, config, current_depth + 1, &new_prefix, is_last_entry);
+            let count = conflicts.keys().filter(|p| p.starts_with(&canonical_path)).count();
+            let label = if count == 1 { "[{} conflict]" } else { "[{} conflicts]" };
+            tags.push(trf(label, &[&count]).red());
+        } else if let Some(kind) = conflicts.get(&canonical_path) {
+            tags.push(format!("[{}]", kind).red());
+        }
+    }
+    if let Some(change) = config.range_changes.as_ref().filter(|_| !is_dir).and_then(|c| {
+        c.get(&path.canonicalize().unwrap_or_else(|_| path.clone()))
+    }) {
+        match change {
+            RangeChange::Added => tags.push(format!("[{}]", tr("added")).green()),
+            RangeChange::Deleted => tags.push(format!("[{}]", tr("deleted")).red()),
+            RangeChange::Renamed(from) => tags.push(format!("[{}]", trf("renamed from {}", &[&from.display()])).cyan()),
+            RangeChange::Modified => {}
+        }
+    }
+    if let Some(commit) = config.last_commits.as_ref().and_then(|c| c.get(&path)) {
+        tags.extend(commit_tags(commit));
+    }
+    if let Some(ref external) = config.external {
+        tags.extend(external.tags(&path));
+    }
+    // No lines left for this dir's contents (--max-lines): say how many are hidden
+    let more = match budget {
+        Some(0) if is_dir && current_depth + 1 < config.depth => {
+            let hidden = read_entries(&path)
+                .map(|entries| entries.iter().filter(|e| !is_hidden(e, config)).count())
+                .unwrap_or(0);
+            (hidden > 0).then(|| trf("+{} more", &[&format_count(hidden)]))
         }
+        _ => None,
+    };
+    let note = match (percent_str, more) {
+        (Some(p), Some(m)) => Some(format!("{}, {}", p, m)),
+        (p, m) => p.or(m),
+    };
+    let note = note.or_else(|| match virtual_kind {
+        Some(kind) => Some(trf("virtual {} filesystem, --include-virtual to list", &[&kind])),
+        None => offline.then(|| tr("in the cloud, --hydrate to list").to_string()),
+    });
+    // --archives: read the listing now, so a broken archive can say so on its own line
+    let archive = (config.archives && !is_dir && !is_symlink && is_archive(&name))
+        .then(|| read_archive(&path).map(|listing| ArchiveDir::from_listing(&listing)));
+    let note = match archive {
+        Some(Ok(ref contents)) => Some(trf("{} files", &[&format_count(contents.totals().0)])),
+        Some(Err(_)) => Some(tr("unreadable archive").to_string()),
+        None => note,
+    };
+    // Generated per .gitattributes (lockfiles, codegen): present but out of the way
+    let display_name = match config.generated {
+        Some(ref generated) if generated.contains(&path) => display_name.dimmed(),
+        _ => display_name,
+    };
+    let anchor = if is_dir { anchor_for(config, &path) } else { None };
+    let kind = if !config.a11y {
+        None
+    } else if is_symlink {
+        Some(match fs::read_link(&path) {
+            Ok(target) => trf("symbolic link to {}", &[&target.display()]),
+            Err(_) => tr("symbolic link").to_string(),
+        })
+    } else if is_dir {
+        Some(tr("directory").to_string())
+    } else if entry.is_executable() {
+        Some(tr("executable file").to_string())
+    } else {
+        Some(tr("file").to_string())
+    };
+    let time = config.time.as_ref().and_then(|t| t.format(&path));
+    let columns = meta_columns(&path, config);
+    let extra = Annotations { size: size_str, time, note, link, tags, anchor, kind, columns };
+    print_entry(prefix, connector, &display_name, extra, config);
+
+    let new_prefix = || if is_last_entry { format!("{}    ", prefix) } else { format!("{}│   ", prefix) };
+
+    // Recurse into directories, and archives with --archives
+    if is_dir && !offline {
+        return 1 + display_dir(&path, config, current_depth + 1, &new_prefix(), budget);
+    }
+    if let Some(Ok(ref contents)) = archive {
+        return 1 + display_archive(contents, &path, config, current_depth + 1, &new_prefix());
+    }
+    1
+}
+
+/// --archives: the inside of an archive, drawn like a directory (folders
+/// first, then files, by name) with the tree's depth and ignore rules.
+/// Sizes are the uncompressed ones.
+fn display_archive(dir: &ArchiveDir, path: &Path, config: &StructConfig, current_depth: usize, prefix: &str) -> usize {
+    if current_depth >= config.depth {
+        return 0;
+    }
+    let dirs: Vec<_> = dir.dirs.iter().filter(|(name, _)| !config.ignores.ignores_dir(name)).collect();
+    let files: Vec<_> = dir.files.iter().filter(|(name, _)| !config.ignores.ignores_file(name)).collect();
+    let count = dirs.len() + files.len();
+    let mut used = 0;
+
+    for (idx, (name, sub)) in dirs.iter().enumerate() {
+        let is_last = idx + 1 == count;
+        let connector = if is_last { "└── " } else { "├── " };
+        let size = config.show_size.then(|| format_size(sub.totals().1));
+        print_entry(prefix, connector, &paint_dir(&format!("{}/", name)), Annotations { size, ..Default::default() }, config);
+        let new_prefix = if is_last { format!("{}    ", prefix) } else { format!("{}│   ", prefix) };
+        used += 1 + display_archive(sub, &path.join(name), config, current_depth + 1, &new_prefix);
+    }
+    for (idx, (name, size)) in files.iter().enumerate() {
+        let connector = if dirs.len() + idx + 1 == count { "└── " } else { "├── " };
+        let label = match size {
+            Some(_) => paint_file(name, &path.join(name), false, |n| n.normal()),
+            None => paint_symlink(name),
+        };
+        let size = size.filter(|_| config.show_size).map(format_size);
+        print_entry(prefix, connector, &label, Annotations { size, ..Default::default() }, config);
+        used += 1;
     }
+    used
 }
\ No newline at end of file
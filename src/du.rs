@@ -0,0 +1,215 @@
+use colored::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::overview::{usage_bar, visible_dir, BAR_WIDTH};
+use crate::theme::paint_dir;
+use crate::utils::{format_count, format_size, unreadable_note};
+
+/// Totals of one directory, from the single walk `struct du` makes
+#[derive(Default)]
+struct DirUsage {
+    /// Everything visible below it
+    size: u64,
+    files: usize,
+    unreadable: usize,
+    /// Its own files, drawn together as one `(files)` row
+    own_size: u64,
+    own_files: usize,
+    subdirs: Vec<PathBuf>,
+}
+
+/// Every visible directory under `root` with its totals, from one walk
+fn walk_usage(root: &Path, ignores: &IgnoreRules) -> HashMap<PathBuf, DirUsage> {
+    let mut dirs: HashMap<PathBuf, DirUsage> = HashMap::new();
+    // Add to `from` and each of its ancestors up to the root
+    let add = |dirs: &mut HashMap<PathBuf, DirUsage>, from: &Path, size: u64, files: usize, unreadable: usize| {
+        for dir in from.ancestors() {
+            let usage = dirs.entry(dir.to_path_buf()).or_default();
+            usage.size += size;
+            usage.files += files;
+            usage.unreadable += unreadable;
+            if dir == root {
+                break;
+            }
+        }
+    };
+    for entry in WalkDir::new(root).follow_links(false).into_iter().filter_entry(|e| visible_dir(e, ignores)) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                if let Some(failed) = e.path() {
+                    add(&mut dirs, failed, 0, 0, 1);
+                }
+                continue;
+            }
+        };
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            dirs.entry(path.to_path_buf()).or_default();
+            if let Some(parent) = path.parent().filter(|_| entry.depth() > 0) {
+                dirs.entry(parent.to_path_buf()).or_default().subdirs.push(path.to_path_buf());
+            }
+            continue;
+        }
+        if !entry.file_type().is_file()
+            || ignores.ignores_file(&entry.file_name().to_string_lossy())
+            || ignores.is_project_ignored(path)
+        {
+            continue;
+        }
+        let Some(parent) = path.parent() else { continue };
+        match entry.metadata() {
+            Ok(meta) => {
+                add(&mut dirs, parent, meta.len(), 1, 0);
+                let usage = dirs.entry(parent.to_path_buf()).or_default();
+                usage.own_size += meta.len();
+                usage.own_files += 1;
+            }
+            Err(_) => add(&mut dirs, parent, 0, 0, 1),
+        }
+    }
+    dirs
+}
+
+/// One drawn line: a directory, or the loose files of one
+struct Row {
+    /// Tree art before the name
+    lead: String,
+    label: String,
+    is_dir: bool,
+    size: u64,
+    parent_size: u64,
+    files: usize,
+    unreadable: usize,
+}
+
+/// A directory's subdirectories and its `(files)` row, biggest first, as
+/// (path, is the files row)
+fn children(dir: &Path, dirs: &HashMap<PathBuf, DirUsage>) -> Vec<(PathBuf, bool)> {
+    let Some(usage) = dirs.get(dir) else { return Vec::new() };
+    let mut kids: Vec<(PathBuf, bool)> = usage.subdirs.iter().map(|d| (d.clone(), false)).collect();
+    if usage.own_files > 0 {
+        kids.push((dir.to_path_buf(), true));
+    }
+    let size = |(path, files_row): &(PathBuf, bool)| match dirs.get(path) {
+        Some(u) if *files_row => u.own_size,
+        Some(u) => u.size,
+        None => 0,
+    };
+    kids.sort_by(|a, b| size(b).cmp(&size(a)).then_with(|| a.0.cmp(&b.0)));
+    kids
+}
+
+fn collect_rows(dir: &Path, dirs: &HashMap<PathBuf, DirUsage>, depth: usize, prefix: &str, rows: &mut Vec<Row>) {
+    if depth == 0 {
+        return;
+    }
+    let parent_size = dirs.get(dir).map_or(0, |u| u.size);
+    let kids = children(dir, dirs);
+    for (i, (path, files_row)) in kids.iter().enumerate() {
+        let last = i + 1 == kids.len();
+        let lead = format!("{}{}", prefix, if last { "└── " } else { "├── " });
+        let Some(usage) = dirs.get(path) else { continue };
+        if *files_row {
+            let label = "(files)".to_string();
+            rows.push(Row { lead, label, is_dir: false, size: usage.own_size, parent_size, files: usage.own_files, unreadable: 0 });
+            continue;
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        rows.push(Row {
+            lead,
+            label: format!("{}/", name),
+            is_dir: true,
+            size: usage.size,
+            parent_size,
+            files: usage.files,
+            unreadable: usage.unreadable,
+        });
+        let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        collect_rows(path, dirs, depth - 1, &prefix, rows);
+    }
+}
+
+fn to_json(dir: &Path, dirs: &HashMap<PathBuf, DirUsage>, depth: usize) -> Value {
+    let usage = dirs.get(dir);
+    let mut node = json!({
+        "name": dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| dir.display().to_string()),
+        "size": usage.map_or(0, |u| u.size),
+        "files": usage.map_or(0, |u| u.files),
+        "unreadable": usage.map_or(0, |u| u.unreadable),
+        "own_size": usage.map_or(0, |u| u.own_size),
+    });
+    if depth > 0 {
+        let kids: Vec<Value> =
+            children(dir, dirs).iter().filter(|(_, files_row)| !files_row).map(|(p, _)| to_json(p, dirs, depth - 1)).collect();
+        node["children"] = json!(kids);
+    }
+    node
+}
+
+/// Share of the parent, one decimal
+fn percent(size: u64, parent: u64) -> String {
+    if parent == 0 {
+        return "0.0%".to_string();
+    }
+    format!("{:.1}%", size as f64 * 100.0 / parent as f64)
+}
+
+/// `struct du [PATH]`: an ncdu-lite — directories down to `depth` levels,
+/// biggest first, each with its size, share of its parent and a bar of that
+/// share. A directory's own files are summed into a `(files)` row; ignores
+/// apply as in the tree, so `-n all` gives the raw usage.
+pub fn display_du(path: &Path, depth: usize, ignores: &IgnoreRules, json_out: bool) {
+    if !path.is_dir() {
+        eprintln!("error: {} is not a directory", path.display());
+        std::process::exit(2);
+    }
+    let dirs = walk_usage(path, ignores);
+    let depth = if depth == 0 { usize::MAX } else { depth };
+
+    if json_out {
+        let mut out = to_json(path, &dirs, depth);
+        out["path"] = json!(path.display().to_string());
+        println!("{}", out);
+        return;
+    }
+
+    let root = dirs.get(path);
+    let total_note = format!(
+        "({}, {} files)",
+        format_size(root.map_or(0, |u| u.size)),
+        format_count(root.map_or(0, |u| u.files))
+    );
+    println!("{} {}", path.display().to_string().cyan().bold(), total_note.bright_black());
+
+    let mut rows = Vec::new();
+    collect_rows(path, &dirs, depth, "", &mut rows);
+    let name_width = rows.iter().map(|r| r.lead.chars().count() + r.label.chars().count()).max().unwrap_or(0);
+    let sizes: Vec<String> = rows.iter().map(|r| format_size(r.size)).collect();
+    let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    for (row, size) in rows.iter().zip(&sizes) {
+        let pad = " ".repeat(name_width - row.lead.chars().count() - row.label.chars().count());
+        let name = if row.is_dir { paint_dir(&row.label) } else { row.label.bright_black() };
+        let files = match unreadable_note(row.unreadable) {
+            Some(missed) => format!("{} files, {}", format_count(row.files), missed).yellow(),
+            None => format!("{} files", format_count(row.files)).bright_black(),
+        };
+        println!(
+            "{}{}{}  {:>size_width$}  {:>6}  {:<bar_width$}  {}",
+            row.lead,
+            name,
+            pad,
+            size,
+            percent(row.size, row.parent_size),
+            usage_bar(row.size, row.parent_size).green(),
+            files,
+            size_width = size_width,
+            bar_width = BAR_WIDTH
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use colored::*;
+use serde_json::json;
+
+use crate::config::{
+    get_config_path, get_roots_path, load_builtin_unignores, load_config_list, load_config_patterns,
+    load_config_setting,
+};
+use crate::i18n::language;
+use crate::utils::thousands_separator;
+
+/// Where an effective setting's value came from, lowest priority first
+#[derive(Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    ConfigFile,
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl Source {
+    fn label(&self) -> String {
+        match self {
+            Source::Default => "default".to_string(),
+            Source::ConfigFile => "config file".to_string(),
+            Source::Env(var) => format!("env {}", var),
+            Source::Flag(flag) => flag.to_string(),
+        }
+    }
+}
+
+/// One merged setting as the current invocation sees it
+pub struct Setting {
+    pub key: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+/// Global flags given on this command line (they apply to subcommands too)
+pub struct CliSettings<'a> {
+    pub strict: bool,
+    pub number_format: Option<&'a str>,
+    pub size_precision: Option<u8>,
+    pub size_unit_min: Option<&'a str>,
+    pub lang: Option<&'a str>,
+}
+
+/// Flag value, else the config file's `key = value`, else the built-in default
+fn layered(flag: Option<String>, flag_name: &'static str, config_key: &str, default: &str) -> (String, Source) {
+    if let Some(v) = flag {
+        return (v, Source::Flag(flag_name));
+    }
+    match load_config_setting(config_key) {
+        Some(v) => (v, Source::ConfigFile),
+        None => (default.to_string(), Source::Default),
+    }
+}
+
+/// First of `vars` that is set and non-empty
+fn env_source(vars: &[&'static str]) -> Option<(&'static str, String)> {
+    vars.iter()
+        .find_map(|v| std::env::var(v).ok().filter(|s| !s.is_empty()).map(|s| (*v, s)))
+}
+
+/// Every setting struct uses, merged from built-in defaults, the config file,
+/// environment variables and command-line flags (later layers win)
+pub fn effective_settings(cli: &CliSettings) -> Vec<Setting> {
+    let mut settings = Vec::new();
+    let mut push = |key, (value, source): (String, Source)| settings.push(Setting { key, value, source });
+
+    let home = if std::env::var_os("HOME").is_some() { Source::Env("HOME") } else { Source::Default };
+    push("config_file", (get_config_path().display().to_string(), home));
+    push("roots_file", (get_roots_path().display().to_string(), home));
+
+    let patterns = load_config_patterns();
+    push(
+        "ignore",
+        if patterns.is_empty() {
+            ("(none)".to_string(), Source::Default)
+        } else {
+            (patterns.join(", "), Source::ConfigFile)
+        },
+    );
+    let unignored = load_builtin_unignores();
+    push(
+        "unignore",
+        if unignored.is_empty() {
+            ("(none)".to_string(), Source::Default)
+        } else {
+            (unignored.join(", "), Source::ConfigFile)
+        },
+    );
+
+    let protected = load_config_list("protect");
+    push(
+        "protect",
+        if protected.is_empty() {
+            ("/, ~".to_string(), Source::Default)
+        } else {
+            (format!("/, ~, {}", protected.join(", ")), Source::ConfigFile)
+        },
+    );
+    push("guard_size_mb", layered(None, "", "guard_size_mb", "100"));
+    push("symlink_style", layered(None, "", "symlink_style", "arrow"));
+    push("time_format", layered(None, "", "time_format", "%Y-%m-%d %H:%M"));
+    push("hidden", layered(None, "", "hidden", "hide"));
+    push("history", layered(None, "", "history", "on"));
+    push("defaults.depth", layered(None, "", "defaults.depth", "(unlimited)"));
+    push("defaults.show_size", layered(None, "", "defaults.show_size", "false"));
+    push("defaults.sort", layered(None, "", "defaults.sort", "name"));
+    push("defaults.flags", layered(None, "", "defaults.flags", "(none)"));
+
+    push(
+        "size_precision",
+        layered(cli.size_precision.map(|p| p.to_string()), "--size-precision", "size_precision", "1"),
+    );
+    push(
+        "size_min_unit",
+        layered(cli.size_unit_min.map(String::from), "--size-unit-min", "size_min_unit", "B"),
+    );
+
+    let format = match cli.number_format {
+        Some(f) => (f.to_string(), Source::Flag("--number-format")),
+        None => ("locale".to_string(), Source::Default),
+    };
+    let separator = match thousands_separator() {
+        None => "(none)".to_string(),
+        Some('\u{202f}') => "thin space".to_string(),
+        Some(c) => format!("'{}'", c),
+    };
+    let separator_source = match (&format.1, env_source(&["LC_ALL", "LC_NUMERIC", "LANG"])) {
+        (Source::Flag(f), _) => Source::Flag(f),
+        (_, Some((var, _))) => Source::Env(var),
+        _ => Source::Default,
+    };
+    push("number_format", format);
+    push("thousands_separator", (separator, separator_source));
+
+    let lang_source = match (cli.lang, env_source(&["LC_ALL", "LC_MESSAGES", "LANG"])) {
+        (Some(_), _) => Source::Flag("--lang"),
+        (_, Some((var, _))) => Source::Env(var),
+        _ => Source::Default,
+    };
+    push("lang", (language().to_string(), lang_source));
+
+    push(
+        "strict",
+        if cli.strict { ("true".to_string(), Source::Flag("--strict")) } else { ("false".to_string(), Source::Default) },
+    );
+
+    // Same variables the `colored` crate honours
+    let color = if std::env::var_os("NO_COLOR").is_some() {
+        ("never".to_string(), Source::Env("NO_COLOR"))
+    } else if std::env::var("CLICOLOR_FORCE").is_ok_and(|v| v != "0") {
+        ("always".to_string(), Source::Env("CLICOLOR_FORCE"))
+    } else if std::env::var("CLICOLOR").is_ok_and(|v| v == "0") {
+        ("never".to_string(), Source::Env("CLICOLOR"))
+    } else {
+        ("auto".to_string(), Source::Default)
+    };
+    push("color", color);
+
+    push(
+        "tree_flags",
+        match env_source(&["STRUCT_FLAGS"]) {
+            Some((var, v)) => (v, Source::Env(var)),
+            None => ("(none)".to_string(), Source::Default),
+        },
+    );
+
+    settings
+}
+
+/// `struct config show`: the values the config file sets, or with
+/// `--effective` every setting after merging all layers, with its source
+pub fn show_config(cli: &CliSettings, effective: bool, json: bool) {
+    let settings: Vec<Setting> = effective_settings(cli)
+        .into_iter()
+        .filter(|s| effective || s.source == Source::ConfigFile)
+        .collect();
+
+    if json {
+        let rows: Vec<serde_json::Value> = settings
+            .iter()
+            .map(|s| json!({ "key": s.key, "value": s.value, "source": s.source.label() }))
+            .collect();
+        let out = json!({
+            "config_file": get_config_path().display().to_string(),
+            "effective": effective,
+            "settings": rows,
+        });
+        println!("{}", out);
+        return;
+    }
+
+    if settings.is_empty() {
+        println!("the config file sets nothing ({})", get_config_path().display().to_string().bright_black());
+        println!("see every value in use with: struct config show --effective");
+        return;
+    }
+    let key_width = settings.iter().map(|s| s.key.len()).max().unwrap_or(0);
+    for s in &settings {
+        println!(
+            "  {:<width$}  {}  {}",
+            s.key,
+            s.value.cyan(),
+            format!("({})", s.source.label()).bright_black(),
+            width = key_width
+        );
+    }
+    if !effective {
+        println!("\nconfig file: {}", get_config_path().display().to_string().bright_black());
+    }
+}
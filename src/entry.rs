@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::metadata_is_executable;
+
+/// One directory entry with its metadata fetched once, so sorting, filtering,
+/// sizing and rendering don't each stat the same path again
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String,
+    /// Metadata of the entry itself — a symlink describes the link, not its target
+    metadata: Option<fs::Metadata>,
+}
+
+impl Entry {
+    pub fn new(entry: &fs::DirEntry) -> Self {
+        Entry {
+            path: entry.path(),
+            name: entry.file_name().to_string_lossy().to_string(),
+            metadata: entry.metadata().ok(),
+        }
+    }
+
+    /// An entry known only from git (`--gd`: deleted, or on the other side of
+    /// the range), drawn as a file under `name`
+    pub fn missing(path: PathBuf, name: String) -> Self {
+        Entry { path, name, metadata: None }
+    }
+
+    pub fn metadata(&self) -> Option<&fs::Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.metadata.as_ref().is_some_and(|m| m.file_type().is_symlink())
+    }
+
+    /// A directory the tree descends into (symlinks are never followed)
+    pub fn is_dir(&self) -> bool {
+        self.metadata.as_ref().is_some_and(|m| m.is_dir())
+    }
+
+    /// Size in bytes; for a symlink, the size of what it points to
+    pub fn size(&self) -> u64 {
+        match self.metadata {
+            Some(ref m) if !m.file_type().is_symlink() => m.len(),
+            _ => fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0),
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.metadata.as_ref().is_some_and(|m| metadata_is_executable(m, &self.path))
+    }
+}
+
+/// The entries of `dir` (unsorted), or None when it can't be read
+pub fn read_entries(dir: &Path) -> Option<Vec<Entry>> {
+    let read = fs::read_dir(dir).ok()?;
+    Some(read.filter_map(|e| e.ok()).map(|e| Entry::new(&e)).collect())
+}
@@ -0,0 +1,340 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::entry::Entry;
+use crate::ignores::compile_pattern;
+
+/// What a `--where` comparison looks at
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Name,  // file name
+    Ext,   // extension, lowercase, without the dot
+    Path,  // path relative to the tree's root
+    Size,  // bytes
+    Mtime, // age: `mtime < 30d` is "modified in the last 30 days"
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match, // =~ regex
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Match => "=~",
+        }
+    }
+}
+
+/// The right-hand side, already converted to what the field compares against
+enum Value {
+    Glob(Regex),
+    Regex(Regex),
+    Bytes(u64),
+    Age(Duration),
+}
+
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Field, Op, Value),
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// A compiled `--where` expression, checked per file:
+///
+///   size > 10MB && ext == "log" && mtime < 30d
+///   name == "*.test.*" || path =~ "^tests/"
+///   !(ext == "md")
+///
+/// Fields: `name`, `ext`, `path` (relative to the root), `size`, `mtime`
+/// (how long ago). Strings compare with `==`/`!=` as `*` patterns, `=~`
+/// as a regex; sizes take B/K/M/G/T (1024-based) and ages s/m/h/d/w/y.
+pub struct Filter {
+    expr: Expr,
+    start: PathBuf,
+}
+
+impl Filter {
+    /// Compile `source` for a tree drawn from `start`
+    pub fn parse(source: &str, start: &Path) -> Result<Filter, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {} after the expression", describe(token)));
+        }
+        Ok(Filter { expr, start: start.to_path_buf() })
+    }
+
+    /// Whether a (non-directory) entry passes
+    pub fn matches(&self, entry: &Entry) -> bool {
+        self.eval(&self.expr, entry)
+    }
+
+    fn eval(&self, expr: &Expr, entry: &Entry) -> bool {
+        match expr {
+            Expr::And(a, b) => self.eval(a, entry) && self.eval(b, entry),
+            Expr::Or(a, b) => self.eval(a, entry) || self.eval(b, entry),
+            Expr::Not(e) => !self.eval(e, entry),
+            Expr::Cmp(field, op, value) => self.compare(*field, *op, value, entry),
+        }
+    }
+
+    fn compare(&self, field: Field, op: Op, value: &Value, entry: &Entry) -> bool {
+        match value {
+            Value::Glob(re) | Value::Regex(re) => {
+                let text = match field {
+                    Field::Name => entry.name.clone(),
+                    Field::Ext => {
+                        Path::new(&entry.name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+                    }
+                    _ => entry.path.strip_prefix(&self.start).unwrap_or(&entry.path).to_string_lossy().to_string(),
+                };
+                re.is_match(&text) == (op != Op::Ne)
+            }
+            Value::Bytes(bytes) => ordered(op, entry.size(), *bytes),
+            Value::Age(age) => {
+                let modified = entry.metadata().and_then(|m| m.modified().ok());
+                let Some(actual) = modified.map(|t| SystemTime::now().duration_since(t).unwrap_or_default()) else {
+                    return false;
+                };
+                ordered(op, actual, *age)
+            }
+        }
+    }
+}
+
+fn ordered<T: PartialOrd>(op: Op, actual: T, wanted: T) -> bool {
+    match op {
+        Op::Eq => actual == wanted,
+        Op::Ne => actual != wanted,
+        Op::Lt => actual < wanted,
+        Op::Le => actual <= wanted,
+        Op::Gt => actual > wanted,
+        Op::Ge => actual >= wanted,
+        Op::Match => false,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some(ch) if ch == c => break,
+                    Some('\\') => text.extend(chars.next()),
+                    Some(ch) => text.push(ch),
+                    None => return Err(format!("unterminated string {}{}", c, text)),
+                }
+            }
+            tokens.push(Token::Str(text));
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if !(ch.is_alphanumeric() || ch == '_' || ch == '.') {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+            tokens.push(match word.as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => Token::Word(word),
+            });
+            continue;
+        }
+        chars.next();
+        let next_is = |chars: &mut std::iter::Peekable<std::str::Chars>, want: char| chars.next_if_eq(&want).is_some();
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if next_is(&mut chars, '&') => Token::And,
+            '|' if next_is(&mut chars, '|') => Token::Or,
+            '=' if next_is(&mut chars, '=') => Token::Op(Op::Eq),
+            '=' if next_is(&mut chars, '~') => Token::Op(Op::Match),
+            '!' if next_is(&mut chars, '=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is(&mut chars, '=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            _ => return Err(format!("unexpected '{}'", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(w) => format!("'{}'", w),
+        Token::Str(s) => format!("\"{}\"", s),
+        Token::Op(op) => format!("'{}'", op.symbol()),
+        Token::And => "&&".to_string(),
+        Token::Or => "||".to_string(),
+        Token::Not => "!".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, want: &Token) -> bool {
+        let found = self.tokens.get(self.pos) == Some(want);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Open) {
+            let expr = self.or()?;
+            if !self.eat(&Token::Close) {
+                return Err("missing ')'".to_string());
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let word = match self.next() {
+            Some(Token::Word(w)) => w,
+            Some(other) => return Err(format!("expected a field, found {}", describe(&other))),
+            None => return Err("expected a field, found the end".to_string()),
+        };
+        let field = match word.as_str() {
+            "name" => Field::Name,
+            "ext" => Field::Ext,
+            "path" => Field::Path,
+            "size" => Field::Size,
+            "mtime" => Field::Mtime,
+            _ => return Err(format!("unknown field '{}' (use name, ext, path, size, mtime)", word)),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            Some(other) => return Err(format!("expected an operator after {}, found {}", word, describe(&other))),
+            None => return Err(format!("expected an operator after {}", word)),
+        };
+        let text = match self.next() {
+            Some(Token::Word(w)) | Some(Token::Str(w)) => w,
+            Some(other) => return Err(format!("expected a value, found {}", describe(&other))),
+            None => return Err("expected a value, found the end".to_string()),
+        };
+        let value = match (field, op) {
+            (Field::Name | Field::Ext | Field::Path, Op::Eq | Op::Ne) => {
+                let text = if field == Field::Ext { text.trim_start_matches('.').to_lowercase() } else { text };
+                Value::Glob(compile_pattern(&text).map_err(|_| format!("invalid pattern \"{}\"", text))?)
+            }
+            (Field::Name | Field::Ext | Field::Path, Op::Match) => {
+                Value::Regex(Regex::new(&text).map_err(|_| format!("invalid regex \"{}\"", text))?)
+            }
+            (Field::Name | Field::Ext | Field::Path, _) => return Err(format!("{} compares with ==, != or =~", word)),
+            (_, Op::Match) => return Err(format!("=~ needs name, ext or path, not {}", word)),
+            (Field::Size, _) => Value::Bytes(parse_bytes(&text)?),
+            (Field::Mtime, _) => Value::Age(parse_age(&text)?),
+        };
+        Ok(Expr::Cmp(field, op, value))
+    }
+}
+
+/// `10MB`, `1.5G`, `512` → bytes (1024-based)
+fn parse_bytes(text: &str) -> Result<u64, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid size '{}' (use e.g. 512, 10K, 1.5G)", text))?;
+    let power = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" => 1,
+        "M" | "MB" => 2,
+        "G" | "GB" => 3,
+        "T" | "TB" => 4,
+        _ => return Err(format!("invalid size '{}' (use e.g. 512, 10K, 1.5G)", text)),
+    };
+    Ok((value * 1024f64.powi(power)) as u64)
+}
+
+/// `30d`, `2h`, `1.5y` → a duration
+fn parse_age(text: &str) -> Result<Duration, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid age '{}' (use e.g. 90m, 2h, 30d)", text))?;
+    let seconds = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3_600.0,
+        "d" => 86_400.0,
+        "w" => 604_800.0,
+        "y" => 31_536_000.0,
+        _ => return Err(format!("invalid age '{}' (use e.g. 90m, 2h, 30d)", text)),
+    };
+    Ok(Duration::from_secs_f64(value * seconds))
+}
@@ -0,0 +1,93 @@
+use git2::Repository;
+use std::path::{Path, PathBuf};
+
+/// Builds web UI links (GitHub / GitLab style) for files in a repository
+pub struct ForgeLinks {
+    /// e.g. https://github.com/owner/repo
+    base: String,
+    branch: String,
+    workdir: PathBuf,
+    gitlab: bool,
+}
+
+impl ForgeLinks {
+    /// Detect the forge from the `origin` remote of the repo containing `path`
+    pub fn detect(path: &Path) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let remote = repo.find_remote("origin").ok()?;
+        let base = web_base_url(remote.url()?)?;
+        let gitlab = base.contains("gitlab");
+
+        Some(ForgeLinks { base, branch: default_branch(&repo), workdir, gitlab })
+    }
+
+    /// Web URL for a file or directory, or None when it's outside the repo
+    pub fn url_for(&self, path: &Path, is_dir: bool) -> Option<String> {
+        let abs = path.canonicalize().ok()?;
+        let rel = abs.strip_prefix(&self.workdir).ok()?;
+        let rel: Vec<String> = rel
+            .components()
+            .map(|c| percent_encode(&c.as_os_str().to_string_lossy()))
+            .collect();
+
+        let kind = if is_dir { "tree" } else { "blob" };
+        let sep = if self.gitlab { "/-/" } else { "/" };
+        Some(format!("{}{}{}/{}/{}", self.base, sep, kind, self.branch, rel.join("/")))
+    }
+}
+
+/// Wrap already-rendered text in an OSC 8 terminal hyperlink
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Turn `git@host:owner/repo.git` / `https://host/owner/repo.git` into `https://host/owner/repo`
+fn web_base_url(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches('/').trim_end_matches(".git");
+    let (host, repo_path) = if let Some(rest) = remote.strip_prefix("git@") {
+        rest.split_once(':')?
+    } else if let Some(rest) = remote.strip_prefix("ssh://") {
+        let rest = rest.split_once('@').map(|(_, r)| r).unwrap_or(rest);
+        rest.split_once('/')?
+    } else if let Some(rest) = remote
+        .strip_prefix("https://")
+        .or_else(|| remote.strip_prefix("http://"))
+    {
+        // Drop any user:token@ credentials
+        let rest = rest.split_once('@').map(|(_, r)| r).unwrap_or(rest);
+        rest.split_once('/')?
+    } else {
+        return None;
+    };
+    // ssh://git@host:22/owner/repo → strip the port
+    let host = host.split(':').next()?;
+    Some(format!("https://{}/{}", host, repo_path))
+}
+
+/// The remote's default branch (origin/HEAD), falling back to the current branch
+fn default_branch(repo: &Repository) -> String {
+    if let Ok(reference) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(target) = reference.symbolic_target() {
+            if let Some(branch) = target.strip_prefix("refs/remotes/origin/") {
+                return branch.to_string();
+            }
+        }
+    }
+    repo.head()
+        .ok()
+        .and_then(|h| h.shorthand().map(|s| s.to_string()))
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Percent-encode a single path segment for use in a URL
+fn percent_encode(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
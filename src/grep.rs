@@ -0,0 +1,254 @@
+use colored::*;
+use regex::{Regex, RegexBuilder};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::search::{display_search_tree, with_ancestors};
+use crate::utils::{format_count, skip_virtual};
+
+/// Snippets longer than this are cut (minified files, data dumps)
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// How `struct grep` matches and prints
+pub struct GrepOptions {
+    pub max_depth: usize,
+    /// Match the pattern literally instead of as a regex
+    pub fixed: bool,
+    /// Lines of context before / after each match (`-B`, `-A`, `-C`)
+    pub before: usize,
+    pub after: usize,
+    /// `path:line:text` per match instead of the tree
+    pub flat: bool,
+    pub quiet: bool,
+    /// Stop after this many matching lines in total (`--max-count`)
+    pub max_count: Option<usize>,
+    pub json: bool,
+}
+
+/// One printed line of a file: a match (with the byte ranges that matched)
+/// or a context line around one
+struct Line {
+    number: usize,
+    text: String,
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Line {
+    fn is_match(&self) -> bool {
+        !self.ranges.is_empty()
+    }
+}
+
+/// The regex to search with: smart case (case-insensitive unless the
+/// pattern has an uppercase letter), `-F` escapes it
+fn build_matcher(pattern: &str, fixed: bool) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("pattern cannot be empty".to_string());
+    }
+    let source = if fixed { regex::escape(pattern) } else { pattern.to_string() };
+    RegexBuilder::new(&source)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()
+        .map_err(|e| format!("invalid pattern: {}", e))
+}
+
+/// Matching lines of one file with their context, up to `limit` matches.
+/// None for binaries (a NUL byte in the first 8K) and unreadable files.
+fn grep_file(path: &Path, matcher: &Regex, opts: &GrepOptions, limit: Option<usize>) -> Option<Vec<Line>> {
+    let mut reader = BufReader::with_capacity(8192, fs::File::open(path).ok()?);
+    if reader.fill_buf().ok()?.contains(&0) {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+    let mut before: VecDeque<Line> = VecDeque::new();
+    let mut after_left = 0;
+    let mut matches = 0;
+    let mut buf = Vec::new();
+    let mut number = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).ok()? == 0 {
+            break;
+        }
+        number += 1;
+        let text = String::from_utf8_lossy(&buf).trim_end_matches(['\n', '\r']).to_string();
+        let ranges: Vec<(usize, usize)> = matcher.find_iter(&text).map(|m| (m.start(), m.end())).collect();
+
+        if !ranges.is_empty() {
+            if limit.is_some_and(|l| matches >= l) {
+                break;
+            }
+            lines.extend(before.drain(..));
+            lines.push(Line { number, text, ranges });
+            matches += 1;
+            after_left = opts.after;
+        } else if after_left > 0 {
+            lines.push(Line { number, text, ranges });
+            after_left -= 1;
+        } else if opts.before > 0 {
+            if before.len() == opts.before {
+                before.pop_front();
+            }
+            before.push_back(Line { number, text, ranges });
+        }
+    }
+    Some(lines)
+}
+
+/// A line cut to `MAX_SNIPPET_CHARS`, with the matched parts highlighted
+fn highlight(line: &Line) -> String {
+    let cut = line.text.char_indices().nth(MAX_SNIPPET_CHARS).map(|(i, _)| i);
+    let end = cut.unwrap_or(line.text.len());
+    let mut out = String::new();
+    let mut pos = 0;
+    for &(start, stop) in &line.ranges {
+        if start >= end {
+            break;
+        }
+        let stop = stop.min(end);
+        out.push_str(&line.text[pos..start]);
+        out.push_str(&line.text[start..stop].red().bold().to_string());
+        pos = stop;
+    }
+    out.push_str(&line.text[pos.min(end)..end]);
+    if cut.is_some() {
+        out.push_str(&"…".bright_black().to_string());
+    }
+    out
+}
+
+/// Print a file's snippets under `prefix`: `12: match`, `13- context`, and
+/// `--` between groups that aren't adjacent, like grep
+fn print_snippets(lines: &[Line], prefix: &str) {
+    let width = lines.last().map_or(1, |l| l.number.to_string().len());
+    let mut last = None;
+    for line in lines {
+        if last.is_some_and(|n| line.number > n + 1) {
+            println!("{}  {}", prefix, "--".bright_black());
+        }
+        let sep = if line.is_match() { ":" } else { "-" };
+        let number = format!("{:>width$}{}", line.number, sep, width = width);
+        let text = if line.is_match() { highlight(line) } else { highlight(line).bright_black().to_string() };
+        println!("{}  {} {}", prefix, number.green(), text);
+        last = Some(line.number);
+    }
+}
+
+/// Walk `start_path` with the tree's ignore rules and grep every text file
+fn collect_file_matches(
+    matcher: &Regex,
+    start_path: &Path,
+    ignores: &IgnoreRules,
+    opts: &GrepOptions,
+) -> Vec<(PathBuf, Vec<Line>)> {
+    let mut found = Vec::new();
+    let mut remaining = opts.max_count;
+    let walker = WalkDir::new(start_path)
+        .follow_links(false)
+        .max_depth(opts.max_depth)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+            || !e.file_type().is_dir()
+            || !(ignores.ignores_dir(&e.file_name().to_string_lossy())
+                || ignores.is_project_ignored(e.path())
+                || skip_virtual(e.path()).is_some())
+        });
+    for entry in walker.filter_map(|e| e.ok()) {
+        if remaining == Some(0) {
+            break;
+        }
+        if !entry.file_type().is_file()
+            || ignores.ignores_file(&entry.file_name().to_string_lossy())
+            || ignores.is_project_ignored(entry.path())
+        {
+            continue;
+        }
+        let Some(lines) = grep_file(entry.path(), matcher, opts, remaining) else { continue };
+        let matched = lines.iter().filter(|l| l.is_match()).count();
+        if matched > 0 {
+            remaining = remaining.map(|r| r - matched);
+            found.push((entry.path().to_path_buf(), lines));
+        }
+    }
+    found
+}
+
+/// `struct grep PATTERN [PATH]`: search file contents, skipping what the tree
+/// ignores, and show the matches in the same pruned tree as `struct search`
+/// with numbered, highlighted lines under each file.
+///
+/// Returns the number of matching lines, or None when the pattern is invalid
+/// — `main` turns that into grep-style exit codes (0 found, 1 none, 2 error).
+pub fn grep_files(pattern: &str, start_path: &Path, opts: &GrepOptions, ignores: &IgnoreRules) -> Option<usize> {
+    let matcher = match build_matcher(pattern, opts.fixed) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return None;
+        }
+    };
+    let found = collect_file_matches(&matcher, start_path, ignores, opts);
+    let total: usize = found.iter().map(|(_, lines)| lines.iter().filter(|l| l.is_match()).count()).sum();
+
+    if opts.json {
+        let files: Vec<_> = found
+            .iter()
+            .map(|(path, lines)| {
+                let lines: Vec<_> = lines
+                    .iter()
+                    .map(|l| json!({ "line": l.number, "text": l.text, "match": l.is_match(), "ranges": l.ranges }))
+                    .collect();
+                json!({ "path": path.display().to_string(), "lines": lines })
+            })
+            .collect();
+        let out = json!({
+            "pattern": pattern,
+            "path": start_path.display().to_string(),
+            "matches": total,
+            "files": files,
+        });
+        println!("{}", out);
+        return Some(total);
+    }
+
+    if total == 0 {
+        if !opts.quiet {
+            println!("{}", format!("no lines matching '{}' found", pattern).yellow());
+        }
+        return Some(0);
+    }
+
+    if opts.flat {
+        for (path, lines) in &found {
+            for line in lines.iter().filter(|l| l.is_match()) {
+                println!("{}:{}:{}", path.display().to_string().cyan(), line.number.to_string().green(), highlight(line));
+            }
+        }
+        return Some(total);
+    }
+
+    if !opts.quiet {
+        println!(
+            "{} {} {}\n",
+            format!("found {} line(s) matching", format_count(total)).green(),
+            pattern.cyan(),
+            format!("in {} file(s)", format_count(found.len())).green()
+        );
+    }
+    let matching_paths = with_ancestors(start_path, found.iter().map(|(path, _)| path.clone()));
+    let snippets = |path: &Path, prefix: &str| {
+        if let Some((_, lines)) = found.iter().find(|(p, _)| p == path) {
+            print_snippets(lines, prefix);
+        }
+    };
+    display_search_tree(start_path, &matching_paths, "", &snippets);
+    Some(total)
+}
@@ -0,0 +1,76 @@
+use dialoguer::Input;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_config_list, load_config_setting};
+use crate::utils::{expand_tilde, format_size, get_dir_size};
+
+/// Size above which replacing or deleting something needs its name typed back
+const DEFAULT_GUARD_SIZE_MB: u64 = 100;
+
+/// Paths no subcommand may replace or delete: `/`, the home directory and
+/// every `protect = [...]` entry in the config file
+fn protected_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/")];
+    if let Ok(home) = std::env::var("HOME") {
+        paths.push(PathBuf::from(home));
+    }
+    paths.extend(load_config_list("protect").iter().map(|p| expand_tilde(p)));
+    paths.iter().map(|p| p.canonicalize().unwrap_or_else(|_| p.clone())).collect()
+}
+
+/// Threshold from the config's `guard_size_mb`, in bytes
+fn guard_size() -> u64 {
+    let mb = load_config_setting("guard_size_mb").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_GUARD_SIZE_MB);
+    mb * 1024 * 1024
+}
+
+/// Guardrails before a subcommand replaces or deletes `target`:
+///
+///   1. protected paths are refused, `--force` or not
+///   2. anything else needs `--force`
+///   3. above `guard_size_mb` (default 100) the target's name must also be
+///      typed back at a prompt — never assumed without a terminal
+///
+/// `action` completes "… would <action> <target>" in the messages.
+pub fn check_destructive(target: &Path, action: &str, force: bool) -> Result<(), String> {
+    let canonical = target.canonicalize().unwrap_or_else(|_| target.to_path_buf());
+    if protected_paths().contains(&canonical) {
+        return Err(format!(
+            "{} is protected (/, home and `protect = [...]` in the config) — refusing to {} it",
+            target.display(),
+            action
+        ));
+    }
+    if !force {
+        return Err(format!("this would {} {} — pass --force to go ahead", action, target.display()));
+    }
+
+    let size = match fs::symlink_metadata(target) {
+        Ok(m) if m.is_dir() => get_dir_size(target),
+        Ok(m) => m.len(),
+        Err(_) => 0,
+    };
+    if size <= guard_size() {
+        return Ok(());
+    }
+    let name = canonical.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if !std::io::stdin().is_terminal() {
+        return Err(format!(
+            "{} is {} (over guard_size_mb) — confirm from a terminal by typing its name",
+            target.display(),
+            format_size(size)
+        ));
+    }
+    let typed: String = Input::new()
+        .with_prompt(format!("{} is {} — type '{}' to {} it", target.display(), format_size(size), name, action))
+        .allow_empty(true)
+        .interact_text()
+        .unwrap_or_default();
+    if typed.trim() == name {
+        Ok(())
+    } else {
+        Err("name didn't match — nothing changed".to_string())
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{Local, TimeZone};
+use colored::*;
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config::{get_config_path, load_config_setting};
+use crate::debugbundle::exit_code;
+use crate::utils::{format_count, shell_quote};
+
+/// Invocations kept; the oldest are dropped past this
+const MAX_ENTRIES: usize = 500;
+
+/// One JSON object per line: `{"time": unix secs, "cwd": "...", "args": [...]}`.
+/// Lives next to ignores.txt, apart from the config undo log in `history/`;
+/// it holds local paths, so `struct config export` leaves it out.
+fn history_path() -> PathBuf {
+    get_config_path().with_file_name("invocations.jsonl")
+}
+
+/// A recorded run: where it was started and its arguments (without `struct`)
+struct Invocation {
+    time: i64,
+    cwd: String,
+    args: Vec<String>,
+}
+
+fn load() -> Vec<Invocation> {
+    let content = fs::read_to_string(history_path()).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).ok()?;
+            Some(Invocation {
+                time: value["time"].as_i64().unwrap_or(0),
+                cwd: value["cwd"].as_str()?.to_string(),
+                args: value["args"].as_array()?.iter().filter_map(|a| a.as_str().map(String::from)).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Append this run to the history (best effort: a read-only config dir just
+/// means nothing is recorded). `history = off` in the config turns it off.
+///
+/// Takes the arguments as typed, without STRUCT_FLAGS, so a replay on
+/// another machine picks up that machine's defaults instead of doubling them.
+pub fn record(args: &[String]) {
+    if load_config_setting("history").as_deref() == Some("off") {
+        return;
+    }
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+    let line = json!({ "time": Local::now().timestamp(), "cwd": cwd, "args": args }).to_string();
+
+    let file = history_path();
+    if let Some(parent) = file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let content = fs::read_to_string(&file).unwrap_or_default();
+    let count = content.lines().count();
+    if count >= MAX_ENTRIES {
+        let kept: Vec<&str> = content.lines().skip(count + 1 - MAX_ENTRIES).collect();
+        let _ = fs::write(&file, kept.join("\n") + "\n" + &line + "\n");
+        return;
+    }
+    if let Ok(mut out) = fs::OpenOptions::new().create(true).append(true).open(&file) {
+        let _ = writeln!(out, "{}", line);
+    }
+}
+
+fn command_line(args: &[String]) -> String {
+    std::iter::once("struct".to_string()).chain(args.iter().map(|a| shell_quote(a))).collect::<Vec<_>>().join(" ")
+}
+
+/// `struct history`: recorded invocations, oldest first, each numbered the
+/// way `struct again N` takes it (1 = the most recent)
+pub fn show_history(json_out: bool) {
+    let entries = load();
+    if json_out {
+        let items: Vec<serde_json::Value> = entries
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, e)| json!({ "n": i + 1, "time": e.time, "cwd": e.cwd, "args": e.args }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&items).unwrap_or_default());
+        return;
+    }
+    if entries.is_empty() {
+        println!("{}", "no history yet".bright_black());
+        return;
+    }
+    let width = entries.len().to_string().len();
+    for (i, entry) in entries.iter().enumerate() {
+        let n = entries.len() - i;
+        let when = Local
+            .timestamp_opt(entry.time, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:>width$}  {}  {}  {}",
+            n.to_string().yellow(),
+            when.bright_black(),
+            entry.cwd.cyan(),
+            command_line(&entry.args),
+            width = width
+        );
+    }
+}
+
+/// `struct history --clear`
+pub fn clear_history() {
+    match fs::remove_file(history_path()) {
+        Ok(()) => println!("{}", "history cleared".green()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => println!("{}", "no history yet".bright_black()),
+        Err(e) => {
+            eprintln!("error: cannot remove {}: {}", history_path().display(), e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// `struct again [N]`: run the Nth most recent invocation again, from the
+/// directory it was started in (the current one if that's gone). Echoes the
+/// command on stderr and exits with its status.
+pub fn run_again(n: usize) -> ! {
+    let entries = load();
+    let Some(entry) = n.checked_sub(1).and_then(|i| entries.iter().rev().nth(i)) else {
+        if entries.is_empty() {
+            eprintln!("error: no history yet");
+        } else {
+            eprintln!("error: no invocation {} (history has {})", n, format_count(entries.len()));
+        }
+        std::process::exit(2);
+    };
+
+    let cwd = PathBuf::from(&entry.cwd);
+    eprintln!("{}", format!("{}  {}", entry.cwd, command_line(&entry.args)).bright_black());
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("struct"));
+    let mut command = Command::new(exe);
+    command.args(&entry.args);
+    if cwd.is_dir() {
+        command.current_dir(&cwd);
+    } else {
+        eprintln!("warning: {} no longer exists; running in the current directory", entry.cwd);
+    }
+    match command.status() {
+        Ok(status) => std::process::exit(exit_code(&status)),
+        Err(e) => {
+            eprintln!("error: cannot start struct: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
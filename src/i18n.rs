@@ -0,0 +1,285 @@
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// English message → translation. English is the key, so a message missing
+/// from a catalog simply stays in English.
+type Catalog = &'static [(&'static str, &'static str)];
+
+const DE: Catalog = &[
+    // Summary view
+    ("total:", "gesamt:"),
+    ("visible:", "sichtbar:"),
+    ("types:", "Typen:"),
+    ("ignored:", "ignoriert:"),
+    ("skipped:", "übersprungen:"),
+    ("over {}", "über {}"),
+    ("size:", "Größe:"),
+    ("modified:", "geändert:"),
+    ("perms:", "Rechte:"),
+    ("git:", "git:"),
+    ("lines:", "Zeilen:"),
+    ("binary", "binär"),
+    ("{} dirs", "{} Ordner"),
+    ("{} files", "{} Dateien"),
+    ("({} bytes)", "({} Bytes)"),
+    ("{}({} files)", "{}({} Dateien)"),
+    ("── ignored (top level) ──", "── ignoriert (oberste Ebene) ──"),
+    ("in the cloud (--hydrate to count)", "in der Cloud (--hydrate zum Zählen)"),
+    ("ignored", "ignoriert"),
+    ("untracked", "nicht versioniert"),
+    ("conflicted", "Konflikt"),
+    ("staged", "vorgemerkt"),
+    ("modified", "geändert"),
+    ("tracked, clean", "versioniert, unverändert"),
+    // Tree view
+    ("{} files ignored", "{} Dateien ignoriert"),
+    ("skipped", "übersprungen"),
+    ("… +{} more", "… +{} weitere"),
+    ("+{} more", "+{} weitere"),
+    ("(truncated)", "(abgeschnitten)"),
+    ("ignored by pattern", "durch Muster ignoriert"),
+    ("ignored by project file", "durch Projektdatei ignoriert"),
+    ("ignored by default", "standardmäßig ignoriert"),
+    ("hidden, -a to show", "versteckt, -a zum Anzeigen"),
+    ("{} new file", "{} neue Datei"),
+    ("{} new files", "{} neue Dateien"),
+    ("[{} conflict]", "[{} Konflikt]"),
+    ("[{} conflicts]", "[{} Konflikte]"),
+    ("added", "hinzugefügt"),
+    ("deleted", "gelöscht"),
+    ("renamed from {}", "umbenannt von {}"),
+    ("in the cloud, --hydrate to list", "in der Cloud, --hydrate zum Auflisten"),
+    ("{} lines", "{} Zeilen"),
+    ("incomplete, {} unreadable", "unvollständig, {} nicht lesbar"),
+    ("unreadable archive", "Archiv nicht lesbar"),
+    ("virtual {} filesystem, --include-virtual to list", "virtuelles {}-Dateisystem, --include-virtual zum Auflisten"),
+    ("{} shallow", "{} direkt"),
+    ("just now", "gerade eben"),
+    ("{} ago", "vor {}"),
+    // --a11y
+    ("level {}: {}", "Ebene {}: {}"),
+    ("directory", "Ordner"),
+    ("file", "Datei"),
+    ("executable file", "ausführbare Datei"),
+    ("symbolic link", "symbolischer Link"),
+    ("symbolic link to {}", "symbolischer Link auf {}"),
+    ("anchor {}", "Anker {}"),
+    ("git branch {}. ", "Git-Zweig {}. "),
+    ("about {}", "etwa {}"),
+    ("modified {}", "geändert {}"),
+    ("{} byte", "{} Byte"),
+    ("{} bytes", "{} Bytes"),
+    ("{} kilobytes", "{} Kilobyte"),
+    ("{} megabytes", "{} Megabyte"),
+    ("{} gigabytes", "{} Gigabyte"),
+    // Status messages
+    ("error: not in a git repository", "Fehler: kein Git-Repository"),
+    (
+        "warning: stopped after --timeout {} — output is partial (marked truncated)",
+        "Warnung: nach --timeout {} abgebrochen — Ausgabe unvollständig (als abgeschnitten markiert)",
+    ),
+];
+
+const FR: Catalog = &[
+    // Units: octets
+    ("B", "o"),
+    ("K", "Ko"),
+    ("M", "Mo"),
+    ("G", "Go"),
+    ("total:", "total :"),
+    ("visible:", "visible :"),
+    ("types:", "types :"),
+    ("ignored:", "ignoré :"),
+    ("skipped:", "omis :"),
+    ("over {}", "plus de {}"),
+    ("size:", "taille :"),
+    ("modified:", "modifié :"),
+    ("perms:", "droits :"),
+    ("git:", "git :"),
+    ("lines:", "lignes :"),
+    ("binary", "binaire"),
+    ("{} dirs", "{} dossiers"),
+    ("{} files", "{} fichiers"),
+    ("({} bytes)", "({} octets)"),
+    ("{}({} files)", "{}({} fichiers)"),
+    ("── ignored (top level) ──", "── ignorés (premier niveau) ──"),
+    ("in the cloud (--hydrate to count)", "dans le cloud (--hydrate pour compter)"),
+    ("ignored", "ignoré"),
+    ("untracked", "non suivi"),
+    ("conflicted", "en conflit"),
+    ("staged", "indexé"),
+    ("modified", "modifié"),
+    ("tracked, clean", "suivi, inchangé"),
+    ("{} files ignored", "{} fichiers ignorés"),
+    ("skipped", "omis"),
+    ("… +{} more", "… +{} autres"),
+    ("+{} more", "+{} autres"),
+    ("(truncated)", "(tronqué)"),
+    ("ignored by pattern", "ignoré par motif"),
+    ("ignored by project file", "ignoré par fichier du projet"),
+    ("ignored by default", "ignoré par défaut"),
+    ("hidden, -a to show", "caché, -a pour afficher"),
+    ("{} new file", "{} nouveau fichier"),
+    ("{} new files", "{} nouveaux fichiers"),
+    ("[{} conflict]", "[{} conflit]"),
+    ("[{} conflicts]", "[{} conflits]"),
+    ("added", "ajouté"),
+    ("deleted", "supprimé"),
+    ("renamed from {}", "renommé depuis {}"),
+    ("in the cloud, --hydrate to list", "dans le cloud, --hydrate pour lister"),
+    ("{} lines", "{} lignes"),
+    ("incomplete, {} unreadable", "incomplet, {} illisibles"),
+    ("unreadable archive", "archive illisible"),
+    ("virtual {} filesystem, --include-virtual to list", "système de fichiers virtuel {}, --include-virtual pour lister"),
+    ("{} shallow", "{} directs"),
+    ("just now", "à l'instant"),
+    ("{} ago", "il y a {}"),
+    ("level {}: {}", "niveau {} : {}"),
+    ("directory", "dossier"),
+    ("file", "fichier"),
+    ("executable file", "fichier exécutable"),
+    ("symbolic link", "lien symbolique"),
+    ("symbolic link to {}", "lien symbolique vers {}"),
+    ("anchor {}", "ancre {}"),
+    ("git branch {}. ", "branche git {}. "),
+    ("about {}", "environ {}"),
+    ("modified {}", "modifié {}"),
+    ("{} byte", "{} octet"),
+    ("{} bytes", "{} octets"),
+    ("{} kilobytes", "{} kilooctets"),
+    ("{} megabytes", "{} mégaoctets"),
+    ("{} gigabytes", "{} gigaoctets"),
+    ("error: not in a git repository", "erreur : pas dans un dépôt git"),
+    (
+        "warning: stopped after --timeout {} — output is partial (marked truncated)",
+        "attention : arrêté après --timeout {} — sortie partielle (marquée tronquée)",
+    ),
+];
+
+const ES: Catalog = &[
+    ("total:", "total:"),
+    ("visible:", "visible:"),
+    ("types:", "tipos:"),
+    ("ignored:", "ignorado:"),
+    ("skipped:", "omitido:"),
+    ("over {}", "más de {}"),
+    ("size:", "tamaño:"),
+    ("modified:", "cambio:"),
+    ("perms:", "permisos:"),
+    ("git:", "git:"),
+    ("lines:", "líneas:"),
+    ("binary", "binario"),
+    ("{} dirs", "{} carpetas"),
+    ("{} files", "{} archivos"),
+    ("({} bytes)", "({} bytes)"),
+    ("{}({} files)", "{}({} archivos)"),
+    ("── ignored (top level) ──", "── ignorados (primer nivel) ──"),
+    ("in the cloud (--hydrate to count)", "en la nube (--hydrate para contar)"),
+    ("ignored", "ignorado"),
+    ("untracked", "sin seguimiento"),
+    ("conflicted", "en conflicto"),
+    ("staged", "preparado"),
+    ("modified", "modificado"),
+    ("tracked, clean", "con seguimiento, sin cambios"),
+    ("{} files ignored", "{} archivos ignorados"),
+    ("skipped", "omitido"),
+    ("… +{} more", "… +{} más"),
+    ("+{} more", "+{} más"),
+    ("(truncated)", "(truncado)"),
+    ("ignored by pattern", "ignorado por patrón"),
+    ("ignored by project file", "ignorado por archivo del proyecto"),
+    ("ignored by default", "ignorado por defecto"),
+    ("hidden, -a to show", "oculto, -a para mostrar"),
+    ("{} new file", "{} archivo nuevo"),
+    ("{} new files", "{} archivos nuevos"),
+    ("[{} conflict]", "[{} conflicto]"),
+    ("[{} conflicts]", "[{} conflictos]"),
+    ("added", "añadido"),
+    ("deleted", "eliminado"),
+    ("renamed from {}", "renombrado desde {}"),
+    ("in the cloud, --hydrate to list", "en la nube, --hydrate para listar"),
+    ("{} lines", "{} líneas"),
+    ("incomplete, {} unreadable", "incompleto, {} ilegibles"),
+    ("unreadable archive", "archivo ilegible"),
+    ("virtual {} filesystem, --include-virtual to list", "sistema de archivos virtual {}, --include-virtual para listar"),
+    ("{} shallow", "{} directos"),
+    ("just now", "ahora mismo"),
+    ("{} ago", "hace {}"),
+    ("level {}: {}", "nivel {}: {}"),
+    ("directory", "carpeta"),
+    ("file", "archivo"),
+    ("executable file", "archivo ejecutable"),
+    ("symbolic link", "enlace simbólico"),
+    ("symbolic link to {}", "enlace simbólico a {}"),
+    ("anchor {}", "ancla {}"),
+    ("git branch {}. ", "rama git {}. "),
+    ("about {}", "unos {}"),
+    ("modified {}", "modificado {}"),
+    ("{} byte", "{} byte"),
+    ("{} bytes", "{} bytes"),
+    ("{} kilobytes", "{} kilobytes"),
+    ("{} megabytes", "{} megabytes"),
+    ("{} gigabytes", "{} gigabytes"),
+    ("error: not in a git repository", "error: no es un repositorio git"),
+    (
+        "warning: stopped after --timeout {} — output is partial (marked truncated)",
+        "aviso: detenido tras --timeout {} — salida parcial (marcada como truncada)",
+    ),
+];
+
+/// Languages with a catalog, for `--lang`
+pub const LANGUAGES: &[&str] = &["en", "de", "fr", "es"];
+
+/// The chosen language code and its catalog (None for English)
+static LANGUAGE: OnceLock<(&'static str, Option<Catalog>)> = OnceLock::new();
+
+/// Language from the environment: LC_ALL, LC_MESSAGES, then LANG
+/// (`de_DE.UTF-8` → `de`); English for anything without a catalog
+fn env_language() -> &'static str {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|v| !v.is_empty())
+        .unwrap_or_default();
+    let lang = locale.split(['_', '.', '@']).next().unwrap_or("");
+    LANGUAGES.iter().copied().find(|l| *l == lang).unwrap_or("en")
+}
+
+/// Pick the message language once at startup: `--lang`, else the locale
+pub fn set_language(flag: Option<&str>) {
+    let code = flag.and_then(|f| LANGUAGES.iter().copied().find(|l| *l == f)).unwrap_or_else(env_language);
+    let catalog = match code {
+        "de" => Some(DE),
+        "fr" => Some(FR),
+        "es" => Some(ES),
+        _ => None,
+    };
+    let _ = LANGUAGE.set((code, catalog));
+}
+
+/// Code of the language messages are shown in
+pub fn language() -> &'static str {
+    LANGUAGE.get().map(|(code, _)| *code).unwrap_or("en")
+}
+
+/// A message in the chosen language (English when there's no translation)
+pub fn tr(msg: &'static str) -> &'static str {
+    let catalog = LANGUAGE.get().and_then(|(_, c)| *c);
+    catalog.and_then(|c| c.iter().find(|(en, _)| *en == msg)).map_or(msg, |(_, t)| *t)
+}
+
+/// `tr` for messages with `{}` placeholders, filled in order
+pub fn trf(msg: &'static str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(msg).split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+        }
+    }
+    out
+}
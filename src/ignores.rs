@@ -1,4 +1,10 @@
+use colored::*;
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::config::load_project_patterns;
 
 /// Check if a directory should be ignored by default
 pub fn should_ignore_dir(name: &str) -> bool {
@@ -21,12 +27,315 @@ pub fn should_ignore_dir(name: &str) -> bool {
 /// Check if a file should be ignored by default
 pub fn should_ignore_file(name: &str) -> bool {
     matches!(
-        name.split('.').last().unwrap_or(""),
+        name.split('.').next_back().unwrap_or(""),
         "pyc" | "pyo" | "pyd" | "swp" | "swo"
     ) || name == "package-lock.json" || name == ".DS_Store"
 }
 
+/// Compile a config/inline ignore pattern (`*` wildcards) into an anchored regex
+pub fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let p = pattern.trim().replace("*", ".*");
+    Regex::new(&format!("^{}$", p))
+}
+
+/// Like `compile_pattern`, for callers that report invalid patterns themselves
+pub fn pattern_to_regex(pattern: &str) -> Option<Regex> {
+    compile_pattern(pattern).ok()
+}
+
+/// One-line reason a pattern failed to compile (regex errors span several lines)
+pub fn pattern_error_reason(err: &regex::Error) -> String {
+    let text = err.to_string();
+    let last = text.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("invalid pattern");
+    last.trim().trim_start_matches("error: ").to_string()
+}
+
 /// Check if a name matches any of the custom patterns
 pub fn matches_custom_pattern(name: &str, patterns: &[Regex]) -> bool {
     patterns.iter().any(|re| re.is_match(name))
-}
\ No newline at end of file
+}
+
+/// Compiled patterns of one directory's project ignore files, with their `file:line`
+type ProjectPatterns = Arc<Vec<(Regex, String)>>;
+
+/// `.structignore` / `.struct.toml` files met while walking the tree, read
+/// once per directory
+#[derive(Debug, Default)]
+pub struct ProjectIgnores {
+    by_dir: Mutex<HashMap<PathBuf, ProjectPatterns>>,
+}
+
+impl ProjectIgnores {
+    /// The patterns `dir` adds, reading its files the first time it is asked
+    fn patterns(&self, dir: &Path) -> ProjectPatterns {
+        let mut by_dir = self.by_dir.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(found) = by_dir.get(dir) {
+            return Arc::clone(found);
+        }
+        let mut compiled = Vec::new();
+        for (pattern, source) in load_project_patterns(dir) {
+            match compile_pattern(&pattern) {
+                Ok(re) => compiled.push((re, format!("{} from {}", pattern, source))),
+                Err(e) => eprintln!(
+                    "{}",
+                    format!("warning: invalid ignore pattern {:?} (from {}): {}", pattern, source, pattern_error_reason(&e))
+                        .yellow()
+                ),
+            }
+        }
+        if tracing() && !compiled.is_empty() {
+            eprintln!("trace-ignores: layer=project dir={:?} patterns={}", dir.display().to_string(), compiled.len());
+        }
+        let found = Arc::new(compiled);
+        by_dir.insert(dir.to_path_buf(), Arc::clone(&found));
+        found
+    }
+}
+
+/// The one place that decides what's hidden — shared by tree, search and summary
+/// so `-i` and `-n` behave the same everywhere.
+///
+/// Built-in dirs are *collapsed* (shown with a file count in the tree), while
+/// built-in files, custom-pattern matches and (unless `-a`) dotfiles are
+/// *hidden* outright.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    /// Config + inline (`-i`) patterns
+    pub custom: Vec<Regex>,
+    /// Where each `custom` pattern came from, same order (`*.log from
+    /// ~/.config/struct/ignores.txt:4`, `*.tmp from -i`), for --trace-ignores
+    pub custom_sources: Vec<String>,
+    /// `-n defaults` / `-n all`: built-in dir and file ignores are off
+    pub skip_defaults: bool,
+    /// `-n NAME`: names that are never ignored, whatever rule would match
+    pub unignored: Vec<String>,
+    /// Entries taken off the built-in lists by the config (`unignore = [...]`)
+    pub removed_defaults: Vec<String>,
+    /// Leave out names starting with a dot (tree, search and summary without `-a`)
+    pub hide_dotfiles: bool,
+    /// `-n project` / `-n all`: `.structignore` and `.struct.toml` files are not read
+    pub skip_project: bool,
+    /// Roots the command walks, whose project ignore files apply from each
+    /// root down; a path goes by the nearest root holding it
+    pub project_roots: Vec<PathBuf>,
+    pub project: ProjectIgnores,
+}
+
+impl IgnoreRules {
+    fn is_unignored(&self, name: &str) -> bool {
+        self.unignored.iter().any(|n| n == name)
+    }
+
+    fn is_removed_default(&self, name: &str) -> bool {
+        self.removed_defaults.iter().any(|n| n == name)
+    }
+
+    /// Built-in ignored directory (collapsed with a count in the tree view);
+    /// a hidden dotfile dir like `.git` is left out instead
+    pub fn is_default_dir(&self, name: &str) -> bool {
+        !self.skip_defaults
+            && !self.is_unignored(name)
+            && !self.is_removed_default(name)
+            && !self.is_dotfile(name)
+            && should_ignore_dir(name)
+    }
+
+    /// Name starting with a dot while dotfiles are hidden (hidden entirely).
+    /// `-n defaults`/`-n all` show them too, `-n NAME` just that one.
+    pub fn is_dotfile(&self, name: &str) -> bool {
+        self.hide_dotfiles
+            && !self.skip_defaults
+            && !self.is_unignored(name)
+            && name.starts_with('.')
+            && name != "."
+            && name != ".."
+    }
+
+    /// Matches a config or inline pattern (hidden entirely)
+    pub fn is_custom(&self, name: &str) -> bool {
+        !self.is_unignored(name) && matches_custom_pattern(name, &self.custom)
+    }
+
+    /// Built-in ignored file (hidden entirely)
+    pub fn is_default_file(&self, name: &str) -> bool {
+        !self.skip_defaults
+            && !self.is_unignored(name)
+            && !self.is_removed_default(name)
+            && should_ignore_file(name)
+    }
+
+    /// The project file pattern hiding `path` (`*.snap from ./web/.structignore:2`),
+    /// from the files in its own directory up to the root's. A pattern only
+    /// applies below the directory whose file holds it; `-n NAME` still wins.
+    pub fn project_rule(&self, path: &Path) -> Option<String> {
+        if self.skip_project {
+            return None;
+        }
+        let root = self
+            .project_roots
+            .iter()
+            .filter(|root| path.starts_with(root) && path != root.as_path())
+            .max_by_key(|root| root.components().count())?;
+        let name = path.file_name()?.to_string_lossy();
+        if self.is_unignored(&name) {
+            return None;
+        }
+        for dir in path.parent()?.ancestors() {
+            let patterns = self.project.patterns(dir);
+            if let Some((_, rule)) = patterns.iter().find(|(re, _)| re.is_match(&name)) {
+                return Some(rule.clone());
+            }
+            if dir == root.as_path() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Whether a project ignore file hides `path` (see `project_rule`)
+    pub fn is_project_ignored(&self, path: &Path) -> bool {
+        self.project_rule(path).is_some()
+    }
+
+    /// Whether a directory is ignored for any reason
+    pub fn ignores_dir(&self, name: &str) -> bool {
+        self.is_default_dir(name) || self.is_custom(name) || self.is_dotfile(name)
+    }
+
+    /// Whether a file is ignored for any reason
+    pub fn ignores_file(&self, name: &str) -> bool {
+        self.is_default_file(name) || self.is_custom(name) || self.is_dotfile(name)
+    }
+
+    /// What the rules do with one entry and which rule decided it, for
+    /// --trace-ignores: `("collapsed" | "hidden" | "kept", rule)`. Checked in
+    /// the tree's order; "kept" means a rule matched but an override won.
+    /// None when no rule is involved at all.
+    pub fn explain(&self, name: &str, is_dir: bool) -> Option<(&'static str, String)> {
+        let custom_rule = || {
+            let idx = self.custom.iter().position(|re| re.is_match(name))?;
+            Some(self.custom_sources.get(idx).cloned().unwrap_or_else(|| self.custom[idx].as_str().to_string()))
+        };
+        let dotfile = self.is_dotfile(name);
+        if is_dir {
+            if dotfile {
+                return Some(("hidden", "dotfile (-a to show)".to_string()));
+            }
+            if self.is_default_dir(name) {
+                return Some(("collapsed", "built-in dir list".to_string()));
+            }
+        } else if self.is_custom(name) {
+            return custom_rule().map(|rule| ("hidden", rule));
+        } else if self.is_default_file(name) {
+            return Some(("hidden", "built-in file list".to_string()));
+        } else if dotfile {
+            return Some(("hidden", "dotfile (-a to show)".to_string()));
+        }
+        if is_dir && self.is_custom(name) {
+            return custom_rule().map(|rule| ("hidden", rule));
+        }
+
+        // Nothing ignores it: say so only when a rule would have without an override
+        let builtin = if is_dir { should_ignore_dir(name) } else { should_ignore_file(name) };
+        let dotted = name.starts_with('.') && name != "." && name != "..";
+        let kept = if self.is_unignored(name) && (builtin || dotted || custom_rule().is_some()) {
+            format!("-n {}", name)
+        } else if self.skip_defaults && (builtin || (dotted && self.hide_dotfiles)) {
+            "-n defaults".to_string()
+        } else if builtin && self.is_removed_default(name) {
+            "unignore in config".to_string()
+        } else {
+            return None;
+        };
+        Some(("kept", kept))
+    }
+
+    /// Print the layers these rules were built from (--trace-ignores)
+    pub fn trace_layers(&self) {
+        if !tracing() {
+            return;
+        }
+        eprintln!("trace-ignores: layer=defaults active={}", !self.skip_defaults);
+        eprintln!("trace-ignores: layer=dotfiles hidden={}", self.hide_dotfiles);
+        for source in &self.custom_sources {
+            eprintln!("trace-ignores: layer=pattern rule={:?}", source);
+        }
+        if !self.removed_defaults.is_empty() {
+            eprintln!("trace-ignores: layer=unignore names={:?}", self.removed_defaults.join(","));
+        }
+        if !self.unignored.is_empty() {
+            eprintln!("trace-ignores: layer=-n names={:?}", self.unignored.join(","));
+        }
+        if self.skip_project || self.project_roots.is_empty() {
+            eprintln!("trace-ignores: layer=project active=false");
+        }
+        for root in self.project_roots.iter().filter(|_| !self.skip_project) {
+            eprintln!("trace-ignores: layer=project root={:?} files=.structignore,.struct.toml", root.display().to_string());
+        }
+    }
+
+    /// Log the decision for one entry, if tracing and any rule is involved
+    pub fn trace(&self, path: &Path, name: &str, is_dir: bool) {
+        if !tracing() {
+            return;
+        }
+        // Project files hide what no global rule does (built-in dirs still collapse)
+        let decision = match self.explain(name, is_dir) {
+            Some((decision, rule)) if decision != "kept" => Some((decision, rule)),
+            other => self.project_rule(path).map(|rule| ("hidden", rule)).or(other),
+        };
+        if let Some((decision, rule)) = decision {
+            trace_decision(path, is_dir, decision, &rule);
+        }
+    }
+}
+
+/// --trace-ignores: decisions printed per rule before the rest are only counted
+const TRACE_PER_RULE: usize = 20;
+
+/// Decisions seen so far per rule, once --trace-ignores turned tracing on
+static TRACE: OnceLock<Mutex<BTreeMap<String, usize>>> = OnceLock::new();
+
+/// Turn on --trace-ignores for the rest of the run
+pub fn enable_trace() {
+    let _ = TRACE.set(Mutex::new(BTreeMap::new()));
+}
+
+pub fn tracing() -> bool {
+    TRACE.get().is_some()
+}
+
+/// Log one ignore decision on stderr as `trace-ignores: key=value ...`.
+/// Each rule prints its first `TRACE_PER_RULE` decisions; the rest are
+/// counted for `finish_trace`, so a huge `node_modules` can't flood the log.
+pub fn trace_decision(path: &Path, is_dir: bool, decision: &str, rule: &str) {
+    let Some(counts) = TRACE.get() else { return };
+    let mut counts = counts.lock().unwrap_or_else(|e| e.into_inner());
+    let seen = counts.entry(rule.to_string()).or_insert(0);
+    *seen += 1;
+    if *seen <= TRACE_PER_RULE {
+        eprintln!(
+            "trace-ignores: path={:?} kind={} decision={} rule={:?}",
+            path.display().to_string(),
+            if is_dir { "dir" } else { "file" },
+            decision,
+            rule
+        );
+    } else if *seen == TRACE_PER_RULE + 1 {
+        eprintln!("trace-ignores: rule={:?} more decisions suppressed, counted at the end", rule);
+    }
+}
+
+/// One `decisions=N` line per rule that decided anything
+pub fn finish_trace() {
+    let Some(counts) = TRACE.get() else { return };
+    let counts = counts.lock().unwrap_or_else(|e| e.into_inner());
+    for (rule, seen) in counts.iter() {
+        eprintln!(
+            "trace-ignores: summary rule={:?} decisions={} suppressed={}",
+            rule,
+            seen,
+            seen.saturating_sub(TRACE_PER_RULE)
+        );
+    }
+}
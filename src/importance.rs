@@ -0,0 +1,122 @@
+use regex::Regex;
+
+use crate::config::load_config_settings_with_prefix;
+use crate::ignores::compile_pattern;
+
+/// Built-in `--sort smart` scores, lowest first. A trailing `/` matches
+/// directories only; everything is compared case-insensitively.
+const DEFAULT_RULES: &[(&str, i32)] = &[
+    // What someone opens first
+    ("readme*", 0),
+    ("license*", 5),
+    ("licence*", 5),
+    ("copying*", 5),
+    ("cargo.toml", 10),
+    ("package.json", 10),
+    ("go.mod", 10),
+    ("pyproject.toml", 10),
+    ("setup.py", 10),
+    ("pom.xml", 10),
+    ("build.gradle*", 10),
+    ("gemfile", 10),
+    ("composer.json", 10),
+    ("cmakelists.txt", 10),
+    ("makefile", 15),
+    ("justfile", 15),
+    ("dockerfile", 15),
+    ("contributing*", 20),
+    ("changelog*", 20),
+    // Locks and generated output last
+    ("*.lock", 90),
+    ("package-lock.json", 90),
+    ("pnpm-lock.yaml", 90),
+    ("go.sum", 90),
+    ("*.min.js", 90),
+    ("*.min.css", 90),
+    ("*.map", 90),
+    // Source before the rest, assets after
+    ("src/", 30),
+    ("lib/", 30),
+    ("app/", 30),
+    ("cmd/", 30),
+    ("pkg/", 30),
+    ("internal/", 30),
+    ("crates/", 30),
+    ("packages/", 30),
+    ("include/", 35),
+    ("tests/", 45),
+    ("test/", 45),
+    ("examples/", 45),
+    ("benches/", 50),
+    ("docs/", 50),
+    ("doc/", 50),
+    ("scripts/", 55),
+    ("assets/", 70),
+    ("static/", 70),
+    ("public/", 70),
+    ("images/", 70),
+    ("img/", 70),
+    ("fixtures/", 75),
+    ("vendor/", 80),
+    ("third_party/", 80),
+];
+
+/// Score of a directory no rule matches
+const OTHER_DIR: i32 = 40;
+/// Score of a file no rule matches
+const OTHER_FILE: i32 = 60;
+/// Added to dotfiles and dot-dirs no rule matches
+const DOTFILE_PENALTY: i32 = 5;
+
+/// The `--sort smart` scoring table: `sort.PATTERN = N` lines from the config
+/// file first (they override the built-in ones), then the built-in rules
+///
+///   sort.docs/ = 5          put docs/ right after the README
+///   sort.*.proto = 25       schema files up with the source
+pub struct Importance {
+    rules: Vec<(Regex, bool, i32)>,
+}
+
+impl Importance {
+    pub fn load() -> Self {
+        let mut rules = Vec::new();
+        let configured = load_config_settings_with_prefix("sort.");
+        let user = configured.iter().filter_map(|(key, value)| {
+            let pattern = key.strip_prefix("sort.")?;
+            match value.parse::<i32>() {
+                Ok(score) => Some((pattern.to_string(), score)),
+                Err(_) => {
+                    eprintln!("warning: config {} = \"{}\": not a whole number", key, value);
+                    None
+                }
+            }
+        });
+        let builtin = DEFAULT_RULES.iter().map(|&(p, s)| (p.to_string(), s));
+        for (pattern, score) in user.collect::<Vec<_>>().into_iter().chain(builtin) {
+            let lower = pattern.to_lowercase();
+            let (glob, dirs_only) = match lower.strip_suffix('/') {
+                Some(g) => (g.to_string(), true),
+                None => (lower, false),
+            };
+            match compile_pattern(&glob) {
+                Ok(re) => rules.push((re, dirs_only, score)),
+                Err(_) => eprintln!("warning: invalid sort pattern {:?} in config", pattern),
+            }
+        }
+        Importance { rules }
+    }
+
+    /// Lower sorts first: the first matching rule's score, else a default by kind
+    pub fn score(&self, name: &str, is_dir: bool) -> i32 {
+        let lower = name.to_lowercase();
+        let matched = self
+            .rules
+            .iter()
+            .find(|(re, dirs_only, _)| (is_dir || !dirs_only) && re.is_match(&lower))
+            .map(|(_, _, score)| *score);
+        matched.unwrap_or_else(|| {
+            let base = if is_dir { OTHER_DIR } else { OTHER_FILE };
+            if name.starts_with('.') { base + DOTFILE_PENALTY } else { base }
+        })
+    }
+}
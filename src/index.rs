@@ -0,0 +1,213 @@
+use colored::*;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::ignores::IgnoreRules;
+use crate::utils::{format_count, format_size, keep_offline};
+
+/// File name used for generated indexes
+pub const INDEX_FILE: &str = "INDEX.md";
+
+/// Options for `struct index-md`
+pub struct IndexOptions {
+    pub depth: usize,
+    pub describe: bool,
+    pub per_dir: bool,
+    /// Single-file output target; "-" means stdout. Ignored with per_dir.
+    pub output: Option<PathBuf>,
+}
+
+/// One directory's visible contents, sorted dirs-first then by name
+struct DirListing {
+    dirs: Vec<PathBuf>,
+    files: Vec<(PathBuf, u64)>,
+}
+
+fn list_dir(path: &Path, ignores: &IgnoreRules) -> DirListing {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let p = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if p.is_symlink() || ignores.is_project_ignored(&p) {
+                continue;
+            }
+            if p.is_dir() {
+                if !ignores.ignores_dir(&name) {
+                    dirs.push(p);
+                }
+            } else if name != INDEX_FILE && !ignores.ignores_file(&name) {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push((p, size));
+            }
+        }
+    }
+
+    dirs.sort_by_key(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+    files.sort_by_key(|(p, _)| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+    DirListing { dirs, files }
+}
+
+/// First meaningful line of a text file, stripped of common comment markers
+fn first_line_description(path: &Path) -> Option<String> {
+    if keep_offline(path) {
+        return None; // reading it would download it
+    }
+    let mut buf = vec![0u8; 4096];
+    let n = fs::File::open(path).ok()?.read(&mut buf).ok()?;
+    let buf = &buf[..n];
+    if buf.contains(&0) {
+        return None; // binary
+    }
+
+    let text = String::from_utf8_lossy(buf);
+    let line = text
+        .lines()
+        .map(|l| l.trim())
+        // Shebangs and <!DOCTYPE>/<!-- openers say nothing about the file
+        .filter(|l| !l.starts_with("#!") && !l.starts_with("<!"))
+        .map(|l| {
+            l.trim_start_matches(['#', '/', '*', '-', ';', '"'])
+                .trim_end_matches("*/")
+                .trim_end_matches("-->")
+                .trim()
+        })
+        .find(|l| !l.is_empty())?;
+
+    let mut desc: String = line.chars().take(80).collect();
+    if line.chars().count() > 80 {
+        desc.push('…');
+    }
+    Some(desc)
+}
+
+/// Markdown link target relative to `base`, always with forward slashes
+fn rel_link(path: &Path, base: &Path) -> String {
+    let rel = path.strip_prefix(base).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy().replace(' ', "%20"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Append the file bullets for one directory
+fn write_files(out: &mut String, listing: &DirListing, base: &Path, describe: bool) {
+    for (file, size) in &listing.files {
+        let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        out.push_str(&format!("- [{}]({}) — {}", name, rel_link(file, base), format_size(*size)));
+        if describe {
+            if let Some(desc) = first_line_description(file) {
+                out.push_str(&format!(" — {}", desc));
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// One INDEX.md for the whole tree: a section per directory
+fn build_single_index(root: &Path, opts: &IndexOptions, ignores: &IgnoreRules) -> String {
+    let root_name = root
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| root.display().to_string());
+    let mut out = format!("# Index of {}\n", root_name);
+
+    // Breadth-first isn't needed — depth-first keeps subtrees together
+    let mut stack: Vec<(PathBuf, usize)> = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        let listing = list_dir(&dir, ignores);
+        let heading = if dir == root { "./".to_string() } else { format!("{}/", rel_link(&dir, root)) };
+        out.push_str(&format!("\n## {}\n\n", heading));
+
+        if listing.files.is_empty() && listing.dirs.is_empty() {
+            out.push_str("_(empty)_\n");
+        }
+        write_files(&mut out, &listing, root, opts.describe);
+
+        if depth + 1 < opts.depth {
+            for sub in listing.dirs.iter().rev() {
+                stack.push((sub.clone(), depth + 1));
+            }
+        }
+    }
+    out
+}
+
+/// An INDEX.md inside each directory, listing its subdirs (linked to their
+/// own indexes) and files. Returns the paths written.
+fn write_per_dir_indexes(root: &Path, opts: &IndexOptions, ignores: &IgnoreRules) -> std::io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    let mut stack: Vec<(PathBuf, usize)> = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = stack.pop() {
+        let listing = list_dir(&dir, ignores);
+        let name = if dir == root {
+            root.canonicalize()
+                .ok()
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_else(|| root.display().to_string())
+        } else {
+            rel_link(&dir, root)
+        };
+        let mut out = format!("# {}\n", name);
+        let descend = depth + 1 < opts.depth;
+
+        if !listing.dirs.is_empty() {
+            out.push_str("\n## Directories\n\n");
+            for sub in &listing.dirs {
+                let sub_name = rel_link(sub, &dir);
+                if descend {
+                    out.push_str(&format!("- [{}/]({}/{})\n", sub_name, sub_name, INDEX_FILE));
+                } else {
+                    out.push_str(&format!("- {}/\n", sub_name));
+                }
+            }
+        }
+        if !listing.files.is_empty() {
+            out.push_str("\n## Files\n\n");
+            write_files(&mut out, &listing, &dir, opts.describe);
+        }
+
+        let target = dir.join(INDEX_FILE);
+        fs::write(&target, out)?;
+        written.push(target);
+
+        if descend {
+            for sub in listing.dirs.iter().rev() {
+                stack.push((sub.clone(), depth + 1));
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// `struct index-md` — generate Markdown indexes of the tree
+pub fn generate_index(root: &Path, opts: &IndexOptions, ignores: &IgnoreRules) {
+    if !root.is_dir() {
+        eprintln!("error: '{}' is not a directory", root.display());
+        return;
+    }
+
+    if opts.per_dir {
+        match write_per_dir_indexes(root, opts, ignores) {
+            Ok(written) => println!("{} {} files", "wrote".green(), format_count(written.len())),
+            Err(e) => eprintln!("failed to write index: {}", e),
+        }
+        return;
+    }
+
+    let content = build_single_index(root, opts, ignores);
+    let target = opts.output.clone().unwrap_or_else(|| root.join(INDEX_FILE));
+    if target.as_os_str() == "-" {
+        print!("{}", content);
+        return;
+    }
+    match fs::write(&target, content) {
+        Ok(()) => println!("{} {}", "wrote".green(), target.display()),
+        Err(e) => eprintln!("failed to write {}: {}", target.display(), e),
+    }
+}
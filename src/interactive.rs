@@ -0,0 +1,144 @@
+use colored::*;
+use console::{truncate_str, Key, Term};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::tree::{node_label, render_tree, TreeNode};
+use crate::utils::format_count;
+
+/// One visible line of the tree
+struct Line<'a> {
+    node: &'a TreeNode,
+    /// Tree art before the name (`│   ├── `)
+    lead: String,
+    /// Index of the parent directory's line (None at the top level)
+    parent: Option<usize>,
+}
+
+/// The lines left visible when the dirs in `folded` hide their contents
+fn flatten<'a>(root: &'a TreeNode, folded: &HashSet<PathBuf>) -> Vec<Line<'a>> {
+    fn walk<'a>(
+        nodes: &'a [TreeNode],
+        prefix: &str,
+        parent: Option<usize>,
+        folded: &HashSet<PathBuf>,
+        out: &mut Vec<Line<'a>>,
+    ) {
+        for (idx, node) in nodes.iter().enumerate() {
+            let last = idx + 1 == nodes.len();
+            out.push(Line { node, lead: format!("{}{}", prefix, if last { "└── " } else { "├── " }), parent });
+            let here = out.len() - 1;
+            if let Some(ref children) = node.children {
+                if !folded.contains(&node.path) {
+                    let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                    walk(children, &prefix, Some(here), folded, out);
+                }
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(root.children.as_deref().unwrap_or_default(), "", None, folded, &mut out);
+    out
+}
+
+/// Whether a line's node has loaded children it can show or hide
+fn foldable(node: &TreeNode) -> bool {
+    node.children.as_ref().is_some_and(|c| !c.is_empty())
+}
+
+/// Leaves the alternate screen and shows the cursor again, even on an error
+struct Screen<'a>(&'a Term);
+
+impl Drop for Screen<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.write_str("\x1b[?1049l");
+        let _ = self.0.show_cursor();
+    }
+}
+
+/// `--interactive`: the tree in a pager. ↑/↓ (j/k), PgUp/PgDn and Home/End
+/// move the cursor; ← (h) folds the directory under it or jumps to its
+/// parent, → (l, Enter) unfolds it or steps inside; q or Esc quits. Works on
+/// the tree already built, so nothing is read from disk while browsing.
+/// Without a terminal the tree is printed as usual.
+pub fn browse(root: &TreeNode, show_size: bool) -> io::Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return render_tree(root, show_size, &mut io::stdout());
+    }
+
+    let mut folded: HashSet<PathBuf> = HashSet::new();
+    let (mut cursor, mut top) = (0usize, 0usize);
+    term.write_str("\x1b[?1049h")?;
+    term.hide_cursor()?;
+    let _screen = Screen(&term);
+
+    loop {
+        let lines = flatten(root, &folded);
+        let (rows, cols) = term.size();
+        let height = (rows as usize).saturating_sub(1).max(1);
+        cursor = cursor.min(lines.len().saturating_sub(1));
+        if cursor < top {
+            top = cursor;
+        } else if cursor >= top + height {
+            top = cursor + 1 - height;
+        }
+
+        let mut frame = String::from("\x1b[H");
+        for (i, line) in lines.iter().enumerate().skip(top).take(height) {
+            let (name, mut note) = node_label(line.node, show_size);
+            if folded.contains(&line.node.path) {
+                let hidden = line.node.children.as_ref().map_or(0, Vec::len);
+                note = format!(" (+{} folded){}", format_count(hidden), note);
+            }
+            let text = if i == cursor {
+                format!("{}{}{}", line.lead, name.clear(), note).reversed().to_string()
+            } else {
+                format!("{}{}{}", line.lead, name, note.bright_black())
+            };
+            frame.push_str(&truncate_str(&text, cols as usize, "…"));
+            frame.push_str("\x1b[K\r\n");
+        }
+        frame.push_str("\x1b[J");
+        let status = format!(
+            " {} · {}/{} · ↑↓ move  ← fold  → unfold  q quit",
+            root.path.display(),
+            format_count(cursor + 1),
+            format_count(lines.len())
+        );
+        frame.push_str(&format!("\x1b[{};1H{}", rows, truncate_str(&status, cols as usize, "…").bright_black()));
+        term.write_str(&frame)?;
+
+        let Some(line) = lines.get(cursor) else {
+            if matches!(term.read_key()?, Key::Char('q') | Key::Escape | Key::CtrlC) {
+                return Ok(());
+            }
+            continue;
+        };
+        let path: &Path = &line.node.path;
+        match term.read_key()? {
+            Key::Char('q') | Key::Escape | Key::CtrlC => return Ok(()),
+            Key::ArrowUp | Key::Char('k') => cursor = cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => cursor += 1,
+            Key::PageUp => cursor = cursor.saturating_sub(height),
+            Key::PageDown | Key::Char(' ') => cursor += height,
+            Key::Home | Key::Char('g') => cursor = 0,
+            Key::End | Key::Char('G') => cursor = lines.len().saturating_sub(1),
+            Key::ArrowLeft | Key::Char('h') => {
+                if foldable(line.node) && !folded.contains(path) {
+                    folded.insert(path.to_path_buf());
+                } else if let Some(parent) = line.parent {
+                    cursor = parent;
+                }
+            }
+            Key::ArrowRight | Key::Char('l') | Key::Enter => {
+                let unfolded = folded.remove(path);
+                if !unfolded && foldable(line.node) {
+                    cursor += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
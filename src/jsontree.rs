@@ -0,0 +1,71 @@
+use colored::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::ignores::IgnoreRules;
+use crate::tree::{render_tree, NodeKind, TreeBuilder, TreeNode};
+
+/// Directory entries as JSON, same visibility rules as the tree view.
+/// Built-in ignored dirs appear collapsed with a file count.
+pub fn list_entries(path: &Path, depth: usize, ignores: IgnoreRules) -> Vec<Value> {
+    let root = TreeBuilder::new(path).depth(depth).ignores(ignores).build();
+    root.children.unwrap_or_default().iter().map(TreeNode::to_json).collect()
+}
+
+/// The whole tree as one JSON document (`--output json`); same shape as the
+/// `list` result of `struct serve`, and what `struct render` reads back.
+pub fn tree_document(path: &Path, entries: &[TreeNode]) -> Value {
+    json!({
+        "path": path.display().to_string(),
+        "entries": entries.iter().map(TreeNode::to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// `struct render FILE`: draw a tree saved with `--output json` without
+/// touching the filesystem it came from. FILE may be `-` for stdin.
+pub fn render_json_tree(file: &Path, show_size: bool) {
+    let text = if file == Path::new("-") {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf).map(|_| buf)
+    } else {
+        fs::read_to_string(file)
+    };
+    let text = match text {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("error: cannot read {}: {}", file.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let doc: Value = match serde_json::from_str(&text) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("error: {} is not valid JSON: {}", file.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Accept the full document or a bare entries array
+    let entries = match doc.get("entries").or(if doc.is_array() { Some(&doc) } else { None }) {
+        Some(Value::Array(entries)) => entries,
+        _ => {
+            eprintln!("error: {} has no tree entries (expected output of --output json)", file.display());
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(root) = doc.get("path").and_then(|p| p.as_str()) {
+        println!("{}", root.cyan());
+    }
+    let root = TreeNode {
+        name: String::new(),
+        path: PathBuf::new(),
+        kind: NodeKind::Dir,
+        size: None,
+        ignored_files: None,
+        children: Some(entries.iter().map(TreeNode::from_json).collect()),
+    };
+    let _ = render_tree(&root, show_size, &mut io::stdout().lock());
+}
@@ -0,0 +1,219 @@
+use colored::*;
+use git2::{DiffOptions, Oid, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::utils::{cache_dir, read_only, relative_time};
+
+/// The commit shown next to an entry
+pub struct CommitInfo {
+    pub short: String,
+    pub author: String,
+    /// Commit time, unix seconds
+    pub time: i64,
+}
+
+/// --gh: the most recent commit that touched each file and directory of
+/// HEAD, like the listing on a forge's repository page.
+///
+/// Found with one walk over the history that stops once every path has a
+/// commit. The result is saved per repository with the HEAD it belongs to;
+/// later runs reuse it as is while HEAD stays put, and walk only the new
+/// commits when HEAD moved forward.
+pub struct LastCommits {
+    workdir: PathBuf,
+    commits: Vec<CommitInfo>,
+    /// Path relative to the work tree → index into `commits`
+    by_path: HashMap<PathBuf, usize>,
+}
+
+/// One file per repository, named after a hash of its git dir:
+/// `head<TAB>oid`, then `c<TAB>short<TAB>time<TAB>author` per commit and
+/// `p<TAB>commit index<TAB>path` per path
+fn cache_file(repo: &Repository) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    repo.path().hash(&mut hasher);
+    cache_dir().join("lastcommit").join(format!("{:016x}.txt", hasher.finish()))
+}
+
+impl LastCommits {
+    /// None outside a git work tree or before the first commit
+    pub fn new(path: &Path) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let head = repo.head().ok()?.peel_to_commit().ok()?;
+
+        // HEAD moved forward from the cached one: only the new commits need a look
+        let base = match load_cache(&cache_file(&repo)) {
+            Some((oid, commits, by_path)) if oid == head.id() => return Some(LastCommits { workdir, commits, by_path }),
+            Some(cached) if repo.graph_descendant_of(head.id(), cached.0).unwrap_or(false) => Some(cached),
+            _ => None,
+        };
+
+        // Every path of HEAD's tree, files and directories, until it has a commit
+        let mut head_paths = HashSet::new();
+        head.tree()
+            .ok()?
+            .walk(TreeWalkMode::PreOrder, |dir, entry| {
+                if let Some(name) = entry.name() {
+                    head_paths.insert(Path::new(dir).join(name));
+                }
+                TreeWalkResult::Ok
+            })
+            .ok()?;
+
+        let mut result = LastCommits { workdir, commits: Vec::new(), by_path: HashMap::new() };
+        walk_history(&repo, head.id(), base.as_ref().map(|(oid, _, _)| *oid), &mut head_paths, &mut result);
+
+        if let Some((_, commits, by_path)) = base {
+            let offset = result.commits.len();
+            result.commits.extend(commits);
+            for (path, index) in by_path {
+                if head_paths.remove(&path) {
+                    result.by_path.insert(path, index + offset);
+                }
+            }
+        }
+        result.commits_prune();
+        save_cache(&cache_file(&repo), head.id(), &result);
+        Some(result)
+    }
+
+    /// The last commit touching `path`, if it's in HEAD
+    pub fn get(&self, path: &Path) -> Option<&CommitInfo> {
+        let canonical = path.canonicalize().ok()?;
+        let rel = canonical.strip_prefix(&self.workdir).ok()?;
+        self.by_path.get(rel).map(|&i| &self.commits[i])
+    }
+
+    /// Drop commits no path points at anymore (superseded after an update)
+    fn commits_prune(&mut self) {
+        let used: HashSet<usize> = self.by_path.values().copied().collect();
+        let mut remap = HashMap::new();
+        let mut kept = Vec::new();
+        for (i, commit) in std::mem::take(&mut self.commits).into_iter().enumerate() {
+            if used.contains(&i) {
+                remap.insert(i, kept.len());
+                kept.push(commit);
+            }
+        }
+        self.commits = kept;
+        for index in self.by_path.values_mut() {
+            *index = remap[index];
+        }
+    }
+}
+
+/// The `abc1234 alice 3d ago` tags drawn after an entry
+pub fn commit_tags(commit: &CommitInfo) -> Vec<ColoredString> {
+    let when = SystemTime::UNIX_EPOCH + Duration::from_secs(commit.time.max(0) as u64);
+    vec![
+        commit.short.yellow(),
+        commit.author.cyan(),
+        relative_time(when).bright_black(),
+    ]
+}
+
+/// Walk from `head` (newest first, merges skipped) down to `stop` or the
+/// root, giving each pending path the first commit that changed it or
+/// something below it
+fn walk_history(
+    repo: &Repository,
+    head: Oid,
+    stop: Option<Oid>,
+    pending: &mut HashSet<PathBuf>,
+    out: &mut LastCommits,
+) {
+    let Ok(mut revwalk) = repo.revwalk() else { return };
+    let _ = revwalk.set_sorting(Sort::TIME);
+    if revwalk.push(head).is_err() {
+        return;
+    }
+    if let Some(stop) = stop {
+        let _ = revwalk.hide(stop);
+    }
+    let mut opts = DiffOptions::new();
+    opts.skip_binary_check(true).ignore_submodules(true);
+
+    for oid in revwalk.filter_map(|o| o.ok()) {
+        if pending.is_empty() {
+            break;
+        }
+        let Ok(commit) = repo.find_commit(oid) else { continue };
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        let Ok(tree) = commit.tree() else { continue };
+        let Ok(diff) = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts)) else { continue };
+
+        let index = out.commits.len();
+        let mut used = false;
+        for delta in diff.deltas() {
+            let Some(file) = delta.new_file().path().or(delta.old_file().path()) else { continue };
+            for path in file.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+                if pending.remove(path) {
+                    out.by_path.insert(path.to_path_buf(), index);
+                    used = true;
+                }
+            }
+        }
+        if used {
+            out.commits.push(CommitInfo {
+                short: oid.to_string().chars().take(7).collect(),
+                author: commit.author().name().unwrap_or("?").to_string(),
+                time: commit.time().seconds(),
+            });
+        }
+    }
+}
+
+type Cached = (Oid, Vec<CommitInfo>, HashMap<PathBuf, usize>);
+
+fn load_cache(file: &Path) -> Option<Cached> {
+    let text = fs::read_to_string(file).ok()?;
+    let mut lines = text.lines();
+    let head = Oid::from_str(lines.next()?.strip_prefix("head\t")?).ok()?;
+    let (mut commits, mut by_path) = (Vec::new(), HashMap::new());
+    for line in lines {
+        let mut fields = line.splitn(4, '\t');
+        match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some("c"), Some(short), Some(time), Some(author)) => commits.push(CommitInfo {
+                short: short.to_string(),
+                author: author.to_string(),
+                time: time.parse().ok()?,
+            }),
+            (Some("p"), Some(index), Some(path), None) => {
+                let index: usize = index.parse().ok()?;
+                if index >= commits.len() {
+                    return None;
+                }
+                by_path.insert(PathBuf::from(path), index);
+            }
+            _ => return None,
+        }
+    }
+    Some((head, commits, by_path))
+}
+
+/// Failures are ignored: the cache only saves time
+fn save_cache(file: &Path, head: Oid, last: &LastCommits) {
+    if read_only() {
+        return;
+    }
+    let mut out = format!("head\t{}\n", head);
+    for commit in &last.commits {
+        out.push_str(&format!("c\t{}\t{}\t{}\n", commit.short, commit.time, commit.author.replace(['\t', '\n'], " ")));
+    }
+    for (path, index) in &last.by_path {
+        out.push_str(&format!("p\t{}\t{}\n", index, path.display()));
+    }
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(file, out);
+}
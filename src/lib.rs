@@ -0,0 +1,29 @@
+//! struct's walking and ignore logic as a library.
+//!
+//! [`tree::TreeBuilder`] walks a directory with the same rules as the `struct`
+//! command — built-in ignores for `node_modules/`, `target/` and friends,
+//! custom patterns via [`tree::IgnoreRules`] — into a [`tree::TreeNode`]
+//! you can render yourself, or draw with [`tree::render_tree`].
+//!
+//! ```no_run
+//! use struct_cli::tree::{render_tree, TreeBuilder};
+//!
+//! let root = TreeBuilder::new("src").depth(2).build();
+//! render_tree(&root, true, &mut std::io::stdout()).unwrap();
+//! ```
+
+pub mod tree;
+
+// What the tree needs from the binary's modules; most of them goes unused here
+#[allow(dead_code)]
+mod config;
+#[allow(dead_code)]
+mod entry;
+#[allow(dead_code)]
+mod i18n;
+#[allow(dead_code)]
+mod ignores;
+#[allow(dead_code)]
+mod theme;
+#[allow(dead_code)]
+mod utils;
@@ -0,0 +1,151 @@
+use colored::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ignores::IgnoreRules;
+
+/// What a symlink to a directory runs into
+enum LoopKind {
+    /// Points at one of its own ancestors — following it never ends
+    Cycle,
+    /// Points at a directory already reached by another path
+    Revisit,
+    /// Can't be resolved at all (the link chain refers back to itself)
+    SelfReferential,
+}
+
+struct FoundLoop {
+    kind: LoopKind,
+    /// Links followed from the re-entered directory down to the offending one
+    chain: Vec<PathBuf>,
+    /// Directory the last link leads back to
+    target: Option<PathBuf>,
+}
+
+/// One directory on the current walk path
+struct Frame {
+    real: PathBuf,
+    shown: PathBuf,
+    via_link: Option<PathBuf>,
+}
+
+/// `struct loops [PATH]`: follow directory symlinks and list every link that
+/// leads back to an ancestor (a cycle) or to a directory already visited.
+/// Returns true when any were found.
+pub fn report_symlink_loops(root: &Path, ignores: &IgnoreRules) -> bool {
+    let Ok(real_root) = root.canonicalize() else {
+        eprintln!("error: cannot open {}", root.display());
+        return false;
+    };
+
+    let mut stack = vec![Frame { real: real_root.clone(), shown: PathBuf::new(), via_link: None }];
+    let mut visited: HashMap<PathBuf, PathBuf> = HashMap::new();
+    visited.insert(real_root, PathBuf::new());
+    let mut found = Vec::new();
+    walk(root, &mut stack, &mut visited, &mut found, ignores);
+
+    if found.is_empty() {
+        println!("{} {}", "no symlink loops under".green(), root.display().to_string().cyan());
+        return false;
+    }
+
+    println!(
+        "{} {}",
+        format!("{} symlink loop(s) under", found.len()).bright_black(),
+        root.display().to_string().cyan()
+    );
+    for l in &found {
+        let chain: Vec<String> = l.chain.iter().map(|p| link_label(root, p)).collect();
+        let (label, tail) = match (&l.kind, &l.target) {
+            (LoopKind::Cycle, Some(t)) => ("cycle".red().bold(), format!("back to {}", shown_dir(t))),
+            (LoopKind::Revisit, Some(t)) => ("revisit".yellow(), format!("already reached as {}", shown_dir(t))),
+            _ => ("self-referential".red().bold(), "never resolves".to_string()),
+        };
+        println!("  {:<16} {}  {}", label, chain.join(" → "), format!("({})", tail).bright_black());
+    }
+    true
+}
+
+/// A walked directory relative to the root ("." for the root itself)
+fn shown_dir(path: &Path) -> String {
+    if path.as_os_str().is_empty() { ".".to_string() } else { format!("{}/", path.display()) }
+}
+
+/// `path -> target` as the link is written on disk, relative to the root
+fn link_label(root: &Path, link: &Path) -> String {
+    let shown = link.strip_prefix(root).unwrap_or(link);
+    match fs::read_link(link) {
+        Ok(target) => format!("{} -> {}", shown.display(), target.display()),
+        Err(_) => shown.display().to_string(),
+    }
+}
+
+fn walk(
+    dir: &Path,
+    stack: &mut Vec<Frame>,
+    visited: &mut HashMap<PathBuf, PathBuf>,
+    found: &mut Vec<FoundLoop>,
+    ignores: &IgnoreRules,
+) {
+    let Ok(read) = fs::read_dir(dir) else { return };
+    let mut entries: Vec<_> = read.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else { continue };
+        let shown = stack.last().map(|f| f.shown.join(&name)).unwrap_or_else(|| PathBuf::from(&name));
+
+        if file_type.is_dir() {
+            if ignores.ignores_dir(&name) || ignores.is_project_ignored(&path) {
+                continue;
+            }
+            let Ok(real) = path.canonicalize() else { continue };
+            visited.entry(real.clone()).or_insert_with(|| shown.clone());
+            stack.push(Frame { real, shown, via_link: None });
+            walk(&path, stack, visited, found, ignores);
+            stack.pop();
+            continue;
+        }
+        if !file_type.is_symlink() {
+            continue;
+        }
+
+        // Links followed so far on this walk path, plus this one
+        let chain_from = |start: usize| -> Vec<PathBuf> {
+            let mut chain: Vec<PathBuf> = stack[start..].iter().filter_map(|f| f.via_link.clone()).collect();
+            chain.push(path.clone());
+            chain
+        };
+
+        let real = match path.canonicalize() {
+            Ok(real) => real,
+            // ELOOP: the link chain never reaches a real file
+            Err(e) if e.raw_os_error() == Some(libc::ELOOP) => {
+                found.push(FoundLoop { kind: LoopKind::SelfReferential, chain: vec![path.clone()], target: None });
+                continue;
+            }
+            Err(_) => continue, // dangling link, not a loop
+        };
+        if !real.is_dir() {
+            continue;
+        }
+
+        if let Some(pos) = stack.iter().position(|f| f.real == real) {
+            found.push(FoundLoop {
+                kind: LoopKind::Cycle,
+                chain: chain_from(pos + 1),
+                target: Some(stack[pos].shown.clone()),
+            });
+        } else if let Some(first) = visited.get(&real) {
+            found.push(FoundLoop { kind: LoopKind::Revisit, chain: vec![path.clone()], target: Some(first.clone()) });
+        } else {
+            visited.insert(real.clone(), shown.clone());
+            stack.push(Frame { real, shown, via_link: Some(path.clone()) });
+            walk(&path, stack, visited, found, ignores);
+            stack.pop();
+        }
+    }
+}
@@ -3,25 +3,101 @@ use colored::*;
 use git2::Repository;
 use regex::Regex;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+mod anchors;
+mod annotate;
+mod archive;
+mod attest;
+mod badges;
+mod bookmarks;
+mod bundle;
+mod compare;
 mod config;
+mod copy;
+mod debugbundle;
+mod delta;
 mod display;
+mod du;
+mod effective;
+mod entry;
+mod filter;
+mod forge;
+mod grep;
+mod guard;
+mod history;
+mod i18n;
 mod ignores;
+mod importance;
+mod index;
+mod interactive;
+mod jsontree;
+mod lastcommit;
+mod loops;
+mod overview;
+mod pack;
+mod packages;
+mod sandbox;
 mod search;
+mod serve;
+mod snapshot;
 mod summary;
+mod theme;
+// Also the library's API, which the binary doesn't use all of
+#[allow(dead_code, unused_imports)]
+mod tree;
+mod usage;
 mod utils;
 
-use crate::config::{
-    add_config_pattern, clear_config_patterns, list_config_patterns, load_config_patterns,
-    remove_config_pattern,
+use anchors::{goto_anchor, Anchors};
+use annotate::{configured_annotators, ExternalAnnotations};
+use attest::Attestation;
+use badges::{Badges, DEFAULT_BADGE_SIZE_MB};
+use bookmarks::{add_bookmark, find_bookmark, list_bookmarks, remove_bookmark};
+use bundle::{export_bundle, import_bundle, Conflicts};
+use compare::compare_archive;
+use config::{
+    add_config_pattern, clear_config_patterns, config_pattern_stats, get_config_path,
+    get_roots_path, list_config_patterns, load_builtin_unignores, load_numbered_config_patterns,
+    load_config_setting,
+    load_search_roots, migrate_config, remove_config_pattern, remove_config_patterns_interactive,
+    restore_config_backup, undo_config_change,
 };
+use copy::{copy_tree, CopyOptions};
+use debugbundle::{run_with_debug_bundle, DEBUG_BUNDLE_FLAG};
+use delta::SizeDelta;
 use display::{
-    display_tree, get_git_changed_files, get_git_staged_files, get_git_tracked_files,
-    get_git_untracked_files, GitMode, StructConfig,
+    display_long, display_tree, get_git_changed_files, get_git_conflicts, get_git_diffstat, get_git_range_diff,
+    get_git_staged_files, get_git_tracked_files, get_git_untracked_dirs, get_git_untracked_files, listed_entries, meta_columns, tree_nodes, GeneratedFiles, GitMode, LineCounts, PercentMode, SortKey, StructConfig,
+    SymlinkStyle, TimeStyle, DirSizes,
+};
+use du::display_du;
+use effective::{show_config, CliSettings};
+use filter::Filter;
+use forge::ForgeLinks;
+use grep::{grep_files, GrepOptions};
+use history::{clear_history, record, run_again, show_history};
+use i18n::{set_language, tr, trf, LANGUAGES};
+use ignores::{compile_pattern, enable_trace, finish_trace, pattern_error_reason, IgnoreRules};
+use index::{generate_index, IndexOptions};
+use interactive::browse;
+use jsontree::{render_json_tree, tree_document};
+use lastcommit::LastCommits;
+use loops::report_symlink_loops;
+use overview::display_overview;
+use pack::{pack_tree, PackOptions};
+use packages::display_packages;
+use sandbox::enter as enter_sandbox;
+use search::{search_files, SearchOptions};
+use serve::serve_stdio;
+use snapshot::{diff_snapshot, list_snapshots, load_snapshot, remove_snapshot, save_snapshot};
+use summary::{display_summary, display_target_summary, expand_glob};
+use tree::{NodeKind, TreeNode};
+use usage::display_usage;
+use utils::{
+    format_count, get_dir_size, parse_duration, parse_size_unit, save_size_cache, set_hydrate, set_read_only, set_include_virtual,
+    set_number_format, set_size_format, tally_stats, SizeFormat,
 };
-use search::search_files;
-use summary::display_summary;
 
 // ─── Help ─────────────────────────────────────────────────────────────────────
 
@@ -31,17 +107,62 @@ A smarter tree — intelligent defaults, git awareness, fast search
 USAGE:
   struct [DEPTH] [PATH] [FLAGS]
   struct search \"PATTERN\" [PATH] [DEPTH] [FLAGS]
+  struct grep \"REGEX\" [PATH] [DEPTH] [FLAGS]
   struct 0 [PATH]                      → detailed summary view
+  struct summary FILE|GLOB|DIR         → stat-like card (size, mtime, perms, git, lines)
+  struct overview [PATH] [-a] [--json]  → size bars per top-level dir (du -sh * | sort -h)
+  struct du [PATH] [-d N] [-a] [--json]  → dirs biggest first, with % of parent and bars
+  struct usage --by owner|ext|age [PATH]  → disk usage per user, file type or age (--json)
+  struct loops [PATH]                  → symlink cycles and links to already-seen dirs
+  struct packages [PATH] [--dot]       → workspace members and their dependencies
+  struct goto 1.2.3                    → path of a dir numbered by the last --anchors tree
+  struct history [--json]              → recent invocations with their directory, numbered
+  struct again [N]                     → run invocation N again (default 1: the last one)
+  struct bookmark add NAME [PATH] [--flags \"-z 2\"]  → name a directory (list, rm NAME)
+  struct @NAME [DEPTH] [FLAGS]         → the tree of a bookmarked directory, with its flags
+  struct copy SRC DEST [--dry-run]     → clean copy without ignored files (and .gitignored)
+  struct pack OUT.zip [PATH]           → same, as .zip/.tar/.tar.gz (--level 0-9;
+                                       --force to overwrite OUT)
+  struct compare OUT.zip [PATH]        → files missing from / extra in / differing in an archive
+  struct snapshot save NAME [PATH] [--hash]  → record a tree (list, rm NAME)
+  struct snapshot diff NAME            → what was added, deleted, resized, renamed since
 
 GIT:
   struct --gr                          tracked files from git root
   struct --gur                         untracked files from git root
   struct --gsr                         staged files from git root
   struct --gcr                         changed (unstaged) from git root
-  struct --gu ~/projects               untracked (from given path)
+  struct --from-git-root 2             any view, started at the repo root
+                                       (struct -g --from-git-root = struct --gr)
+  struct --gu ~/projects               untracked (from given path); dirs with nothing
+                                       tracked collapse to 'newdir/ (14 new files)'
+                                       unless --expand-untracked
   struct --gc ~/projects               changed (from given path)
+  struct --gh                          last commit per file and dir: hash, author, age
+  struct --conflicts                   only conflicted files of a merge/rebase, with
+                                       markers like [both modified]
+  struct --gd main..feature            files that differ between two commits or branches:
+                                       added green, modified yellow, deleted struck red,
+                                       renamed cyan, with +/- lines (A...B: from the merge base)
+  (--gc/--gs show +added -removed lines per file, totals per directory)
+  (the header shows MERGING / REBASING / ... while one is in progress)
   (when multiple git flags conflict, highest priority wins:
-   changed > staged > untracked > tracked > history)
+   conflicts > diff > changed > staged > untracked > tracked > history)
+
+SCRIPTING:
+  struct tree [PATH] [--depth N] [FLAGS]   same as the default view, no positional
+  struct tree --path DIR --depth 2 -z      guessing (every FLAG below works)
+
+EXPORT:
+  struct index-md [PATH] [--describe]  write INDEX.md (dirs as sections, files with sizes)
+  struct index-md . --per-dir          an INDEX.md in every directory
+  struct index-md . -o -               print the index instead of writing it
+  struct 3 --output json > tree.json   the tree as JSON (same shape as serve's list)
+  struct render tree.json [-z]         draw a saved JSON tree, e.g. captured on a server
+
+EDITORS:
+  struct serve --stdio                 JSON-RPC 2.0 (one request per line): list, search,
+                                       summarize, shutdown
 
 SEARCH:
   struct search \"*.py\" ~/projects      find .py files (tree view)
@@ -50,23 +171,121 @@ SEARCH:
   struct search \"gui*\" . -f            flat output (full paths)
   struct search \"*.log\" . -i \"venv\"    search, ignoring venv
   struct search \"*.wav\" . -i \"win,Linux\"
+  struct search \"*.pyc\" . -n all       search everything (same -n as the tree)
+  struct search --root code \"*.toml\"  search every path of a named root
+                                       (roots.txt: code = ~/work, ~/oss)
+  struct search \"*.pem\" ~ -m 1         stop at the first match
+                                       (exit 0 = found, 1 = none, 2 = error)
+  struct search \"*.rs\" . --stats       entries scanned/skipped/matched + time
+  struct grep \"TODO\" src               file contents: matching lines under each file
+  struct grep \"fn main\" . -C 2         with 2 lines of context (-A/-B for after/before)
+  struct grep -F \"a.b(\" . -f           literal pattern; flat path:line:text output
+  struct grep \"todo\" . --json          files, line numbers, text and match ranges
 
 CONFIG:
   struct add \"pattern\"                 add to persistent ignores
   struct remove \"pattern\"              remove from persistent ignores
+  struct remove                        pick patterns to remove interactively
   struct list                          list config patterns
-  struct clear                         clear all config patterns
+  struct clear [--yes]                 clear all config patterns (asks first, keeps a backup)
+  struct config undo                   revert the last add/remove/clear
+  struct config restore [BACKUP]       restore the config from a clear backup
+  struct config stats [PATH]           how much each pattern hides (flags unused ones)
+  struct config migrate                move ignores.txt into config.toml: [defaults] depth,
+                                       show_size, sort, flags; [ignore] patterns, unignore;
+                                       [display]; [colors] (add/remove/list edit it from then on)
+  color.* lines in ignores.txt         recolor output: color.dir = \"blue bold\",
+                                       color.ext.rs = \"orange\", color.exec, color.symlink,
+                                       color.symlink_external (links leaving the tree)
+  symlink_style line in ignores.txt    what follows a symlink's name: name, arrow (default),
+                                       absolute or relative (to the tree's root)
+  struct config show [--effective]     settings from the config file (--effective: merged
+                                       with defaults, env and flags, plus each source)
+  struct config export FILE.tar        bundle config, roots, themes and backups
+  struct config import FILE.tar        unpack a bundle (asks per conflict; --overwrite/--keep)
+  (add/remove/list/clear accept --json for machine-readable output)
 
 FLAGS:
+  -L N         max depth (same as positional DEPTH; use it when a dir is named
+               like a number: struct -L 2 2024)
   -i \"p1,p2\"   ignore patterns (dirs or files, comma-separated)
-  -n TARGET    un-ignore: a pattern name, 'defaults', 'config', or 'all'
-               (can be specified multiple times: -n defaults -n config)
+  -n TARGET    un-ignore: a pattern name, 'defaults', 'config', 'project' (.structignore
+               and .struct.toml files), or 'all' (can be given several times)
+  -a, --all    show dotfiles, hidden by default in the tree, search, grep and summary
+               (config: hidden = show)
+  --hide-generated  leave out files .gitattributes marks linguist-generated or
+               export-ignore (shown dimmed otherwise)
+  --show-ignored  dry run of the ignore rules: ignored entries shown struck through
+  --trace-ignores  log each ignore decision and the rule behind it to stderr
+               (config file:line, -i, -n, built-ins, git mode), 20 per rule
+  --archives   list what .zip, .jar, .tar and .tar.gz files hold, as subtrees under
+               them (read in place, nothing is extracted)
+  --long       tab-separated perms, owner, bytes, mtime, path per line (for awk/cut)
+  -p, --perms  permission column (drwxr-xr-x) before the tree art
+  --owner      user and group columns (attributes on windows), like tree -pug
+  --interactive  browse the tree in a pager: ↑↓ move, ← fold, → unfold, q quit
+  --annotate   labels from your own commands after each entry (build status, tickets):
+               annotator.NAME = \"cmd\" in the config; --annotate=NAME for some only
+  --attest     end with a sha256 of the listing (paths, sizes, file hashes) and the
+               flags used, to compare directory states by one line
+  --delta      how much each dir grew (+120M) or shrank (-3.2M) since the last
+               --delta run on the same root (the first run records sizes)
+  --anchors    number every dir (1.2.3); then cd \"$(struct goto 1.2.3)\"
+  --a11y       screen-reader friendly: \"level 2: main.rs, file, 3.2 kilobytes\"
+               instead of box-drawing connectors, and no colors
+  --sandbox    Linux: read only the root, write and run nothing (landlock + seccomp),
+               for untrusted trees; refuses to run where it can't do that
+  --number-format STYLE  thousands separator for counts: locale (default, from LANG),
+               plain, comma, dot, space
+  --size-precision N  decimals for K/M/G sizes (default 1; config: size_precision = N)
+  --size-unit-min U   smallest size unit: B K M G (config: size_min_unit = K)
+  --lang CODE  labels, units and messages in en, de, fr or es (default: from LANG)
   -z           show file/dir sizes
-  -s SIZE      skip dirs larger than SIZE megabytes
+  -q, --quiet  only the tree: no root path / git header (search: no banner)
+  --align-sizes  right-align sizes in a fixed column (implies -z)
+  --shallow-size  each dir's own files only, labeled \"shallow\" (cheap on huge trees)
+  --lines      lines of text per file and per dir, binaries skipped (after the size with -z)
+  -t, --time   modification time per entry (format: time_format in ignores.txt,
+               default %Y-%m-%d %H:%M); -t=relative for \"2h ago\"; combines with -z
+  --percent    show each dir's share of its parent (--percent=root: of the root)
+  --hydrate    Windows: list and read OneDrive placeholders (☁), downloading them;
+               by default they're shown but never opened
+  --include-virtual  walk into /proc, /sys, /dev and other pseudo-filesystems (skipped
+               with a note by default, in every view and size count)
+  --strict     invalid ignore patterns are an error (default: warn and skip them)
+  -s SIZE      skip dirs larger than SIZE megabytes (also for struct 0, summary, search)
+  --forge-links  link names to the file on GitHub/GitLab (clickable terminals)
+  --max-lines N  fit the tree in N lines: dirs and the largest files first,
+               the rest elided per directory as '… +k more'
+  --group-by ext  group files in each dir under extension headers (.rs (12), .toml (3))
+  --sort smart README and manifests first, source dirs before assets, locks and
+               generated files last (tune with sort.PATTERN = N in the config)
+  --sort KEY   also size (largest first, dirs by total), mtime (newest first),
+               count (dirs with the most files first), extension, name (default)
+  --reverse    flip the --sort order (--sort size --reverse: smallest first)
+  --only PATTERNS  only files matching these comma-separated patterns, plus their dirs
+               (the inverse of -i): --only \"*.rs,*.toml\"
+  --where EXPR  only files matching EXPR: 'size > 10MB && ext == \"log\" && mtime < 30d'
+               (fields name, ext, path, size, mtime; && || ! ( ); == != < <= > >= =~)
+  --badges     mark dirs: ● uncommitted changes  ▲ oversized  ⚠ secrets-like files  ∅ empty
+               (--badge-size MB sets the oversized threshold, default 100)
+  --meta-badges  project dirs show R L C ⚙ for README, LICENSE, CONTRIBUTING, CI
+               config; a missing one is shown as ·
+  --df         show filesystem used/free space in the header
+  --git-overhead  show .git size (packs, loose objects, LFS) vs the working tree
+  --timeout T  stop walking after T (5s, 500ms, 2m); partial dirs say (truncated)
+  --budget MS  stop counting an ignored dir after MS ms (shown as ~approximate)
+  --timings    print total time, time spent counting dirs and size-cache hits
+  --debug-bundle  on a crash or error, write args, config, platform and stderr to a
+               temp file for bug reports (works with every command)
   -g/--git     git mode flags: --gu --gs --gc --gh  (current dir)
                root variants:  --gr --gur --gsr --gcr --ghr
   -h, --help   print this help
-  -V, --version";
+  -V, --version
+
+ENVIRONMENT:
+  STRUCT_FLAGS   extra flags applied to every tree run, e.g. STRUCT_FLAGS='-z -i \"*.log\"'
+                 (command-line flags override them)";
 
 // ─── Clap — flags only, no positionals ───────────────────────────────────────
 // Positionals (DEPTH and PATH) are extracted from argv before clap sees them,
@@ -76,11 +295,56 @@ FLAGS:
 #[command(name = "struct")]
 #[command(version)]
 #[command(disable_help_flag = true)]
+#[command(args_override_self = true)]
 #[command(override_usage = "struct [DEPTH] [PATH] [FLAGS]\n       struct search \"PATTERN\" [PATH] [DEPTH] [FLAGS]")]
 struct Flags {
     #[command(subcommand)]
     command: Option<Commands>,
 
+    #[command(flatten)]
+    tree: TreeFlags,
+
+    #[arg(short = 'h', long = "help", action = clap::ArgAction::SetTrue, hide = true)]
+    help: bool,
+
+    /// -p for --perms on the plain tree (`struct tree` keeps -p for --path)
+    #[arg(short = 'p', hide = true)]
+    perms_short: bool,
+
+    /// Fail on ignore patterns that don't compile instead of skipping them
+    #[arg(long = "strict", global = true, hide = true)]
+    strict: bool,
+
+    /// Open and list cloud placeholders (OneDrive), downloading them as needed
+    #[arg(long = "hydrate", global = true, hide = true)]
+    hydrate: bool,
+
+    /// Walk into /proc, /sys, /dev and other virtual filesystems too
+    #[arg(long = "include-virtual", global = true, hide = true)]
+    include_virtual: bool,
+
+    /// Thousands separator for counts: locale (default), plain, comma, dot, space
+    #[arg(long = "number-format", value_name = "STYLE", global = true,
+          value_parser = ["locale", "plain", "comma", "dot", "space"], hide = true)]
+    number_format: Option<String>,
+
+    /// Decimal places for sizes in K/M/G (default 1)
+    #[arg(long = "size-precision", value_name = "N", global = true,
+          value_parser = clap::value_parser!(u8).range(0..=3), hide = true)]
+    size_precision: Option<u8>,
+
+    /// Smallest unit sizes are shown in: B (default), K, M or G
+    #[arg(long = "size-unit-min", value_name = "UNIT", global = true, hide = true)]
+    size_unit_min: Option<String>,
+
+    /// Language for labels, units and messages (default: from LANG)
+    #[arg(long = "lang", value_name = "CODE", global = true, value_parser = LANGUAGES.to_vec(), hide = true)]
+    lang: Option<String>,
+}
+
+/// Everything the tree view understands — shared by the default mode and `struct tree`
+#[derive(clap::Args, Debug)]
+struct TreeFlags {
     #[arg(short = 'g', long = "git", hide = true)]
     git_tracked: bool,
     #[arg(long = "gu", hide = true)]
@@ -101,34 +365,494 @@ struct Flags {
     git_changed_root: bool,
     #[arg(long = "ghr", hide = true)]
     git_history_root: bool,
+    /// --gu: list the files inside wholly untracked dirs instead of collapsing them
+    #[arg(long = "expand-untracked", hide = true)]
+    expand_untracked: bool,
+    /// Start at the enclosing repository's root, whatever the current directory
+    #[arg(long = "from-git-root", hide = true)]
+    from_git_root: bool,
+    /// Only conflicted files of an in-progress merge/rebase
+    #[arg(long = "conflicts", hide = true)]
+    git_conflicts: bool,
+    /// Only files that differ between two commits: A..B, A...B, or A (to HEAD)
+    #[arg(long = "gd", value_name = "RANGE", hide = true)]
+    git_diff: Option<String>,
+
+    /// Max depth (same as the positional DEPTH, tree-compatible)
+    #[arg(short = 'L', long = "level", value_name = "N", hide = true)]
+    level: Option<usize>,
 
     #[arg(short = 'i', long = "ignore", value_name = "PATTERNS", hide = true)]
     ignore_patterns: Option<String>,
 
+    /// Show only files matching these comma-separated patterns (and the dirs holding them)
+    #[arg(long = "only", value_name = "PATTERNS", hide = true)]
+    only_patterns: Option<String>,
+
     #[arg(short = 's', long = "skip-large", value_name = "SIZE", hide = true)]
     max_size_mb: Option<u64>,
 
     #[arg(short = 'z', long = "size", hide = true)]
     show_size: bool,
 
-    /// Can be given multiple times: -n defaults -n config
+    /// Right-align sizes in a fixed column (implies -z)
+    #[arg(long = "align-sizes", hide = true)]
+    align_sizes: bool,
+
+    /// Directories show the size of the files directly inside them, not a
+    /// recursive total — one read per directory instead of a full walk
+    #[arg(long = "shallow-size", hide = true)]
+    shallow_size: bool,
+
+    /// Show modification times; --time=relative for "2h ago"
+    #[arg(short = 't', long = "time", value_name = "STYLE", num_args = 0..=1, require_equals = true,
+          default_missing_value = "date", value_parser = ["date", "relative"], hide = true)]
+    time: Option<String>,
+
+    /// Show each directory's share of its parent, or of the root with --percent=root (implies -z)
+    #[arg(long = "percent", value_name = "BASE", num_args = 0..=1, require_equals = true,
+          default_missing_value = "parent", value_parser = ["parent", "root"], hide = true)]
+    percent: Option<String>,
+
+    /// Print only the tree: no root path line, no git/disk header
+    #[arg(short = 'q', long = "quiet", hide = true)]
+    quiet: bool,
+
+    /// Make names clickable links to the file on GitHub/GitLab (OSC 8)
+    #[arg(long = "forge-links", hide = true)]
+    forge_links: bool,
+
+    /// Show total/used/free space of the filesystem in the header
+    #[arg(long = "df", hide = true)]
+    df: bool,
+
+    /// Show how much of the repo is .git (packs, loose objects, LFS) vs the working tree
+    #[arg(long = "git-overhead", hide = true)]
+    git_overhead: bool,
+
+    /// Stop the whole traversal after this long (5s, 500ms, 2m) and show what was gathered
+    #[arg(long = "timeout", value_name = "DURATION", value_parser = parse_duration, hide = true)]
+    timeout: Option<std::time::Duration>,
+
+    /// Print where the time went (walk vs ignored-dir counts, count cache hits) to stderr
+    #[arg(long = "timings", hide = true)]
+    timings: bool,
+
+    /// Stop counting an ignored dir after MS milliseconds and mark it approximate
+    #[arg(long = "budget", value_name = "MS", hide = true)]
+    budget_ms: Option<u64>,
+
+    /// Mark dirs that are dirty in git, oversized, hold secrets-like files, or are empty
+    #[arg(long = "badges", hide = true)]
+    badges: bool,
+
+    /// Mark project dirs with which of README, LICENSE, CONTRIBUTING and CI config they have
+    #[arg(long = "meta-badges", hide = true)]
+    meta_badges: bool,
+
+    /// Size in megabytes above which --badges marks a dir as oversized
+    #[arg(long = "badge-size", value_name = "MB", hide = true)]
+    badge_size_mb: Option<u64>,
+
+    /// Group files within each directory under per-extension headers
+    #[arg(long = "group-by", value_name = "KEY", value_parser = ["ext"], hide = true)]
+    group_by: Option<String>,
+
+    /// Entry order: name (dirs first, alphabetical), smart (README and
+    /// manifests first, source before assets, locks and generated files last),
+    /// size and count (largest first), mtime (newest first) or extension
+    #[arg(long = "sort", value_name = "KEY",
+          value_parser = ["name", "smart", "size", "mtime", "extension", "count"], hide = true)]
+    sort: Option<String>,
+
+    /// Show only files matching an expression, e.g. 'size > 10MB && ext == "log"'
+    #[arg(long = "where", value_name = "EXPR", hide = true)]
+    filter: Option<String>,
+
+    /// Flip the --sort order
+    #[arg(long = "reverse", hide = true)]
+    reverse: bool,
+
+    /// Fit the tree into N lines, eliding the least important entries per directory
+    #[arg(long = "max-lines", value_name = "N", hide = true)]
+    max_lines: Option<usize>,
+
+    /// Print the tree in a machine-readable format instead of drawing it
+    #[arg(long = "output", value_name = "FORMAT", value_parser = ["json"], hide = true)]
+    output: Option<String>,
+
+    /// Leave out files .gitattributes marks linguist-generated / export-ignore (dimmed by default)
+    #[arg(long = "hide-generated", hide = true)]
+    hide_generated: bool,
+
+    /// Show how much each directory grew or shrank since the last --delta run
+    #[arg(long = "delta", hide = true)]
+    delta: bool,
+
+    /// Print a sha256 of the listing (paths, sizes, content hashes) and the flags used
+    #[arg(long = "attest", hide = true)]
+    attest: bool,
+
+    /// Screen-reader output: "level N:" lines with plain-language
+    /// annotations instead of box-drawing connectors and colors
+    #[arg(long = "a11y", hide = true)]
+    a11y: bool,
+
+    /// Linux: restrict the process to reading the root (landlock + seccomp)
+    /// before walking it
+    #[arg(long = "sandbox", hide = true)]
+    sandbox: bool,
+
+    /// Number every directory (1.2.3) for `struct goto`
+    #[arg(long = "anchors", hide = true)]
+    anchors: bool,
+
+    /// List what's inside .zip, .jar, .tar and .tar.gz files as subtrees, without extracting
+    #[arg(long = "archives", hide = true)]
+    archives: bool,
+
+    /// Count lines of text per file and per directory (binaries skipped)
+    #[arg(long = "lines", hide = true)]
+    lines: bool,
+
+    /// Labels from the annotator commands in the config (all, or --annotate=NAME,NAME)
+    #[arg(long = "annotate", value_name = "NAMES", num_args = 0..=1, require_equals = true,
+          default_missing_value = "", conflicts_with = "sandbox", hide = true)]
+    annotate: Option<String>,
+
+    /// Show what the ignore rules filter, dimmed and struck through, instead of hiding it
+    #[arg(long = "show-ignored", hide = true)]
+    show_ignored: bool,
+
+    /// One tab-separated line per entry (perms, owner, bytes, mtime, path), no tree art
+    #[arg(long = "long", hide = true)]
+    long: bool,
+
+    /// Permissions column (`drwxr-xr-x`) before the tree art
+    #[arg(long = "perms", hide = true)]
+    perms: bool,
+
+    /// User and group columns (unix) or attribute letters (windows) before the tree art
+    #[arg(long = "owner", hide = true)]
+    owner: bool,
+
+    /// Browse the tree in a pager: arrows move, left/right fold and unfold directories
+    #[arg(long = "interactive", hide = true)]
+    interactive: bool,
+
+    /// Log every ignore decision to stderr with the rule that made it
+    #[arg(long = "trace-ignores", hide = true)]
+    trace_ignores: bool,
+
+    /// Can be given multiple times: -n defaults -n config -n project
     #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append, hide = true)]
     no_ignore: Vec<String>,
 
-    #[arg(short = 'h', long = "help", action = clap::ArgAction::SetTrue, hide = true)]
-    help: bool,
+    /// Show dotfiles (hidden by default)
+    #[arg(short = 'a', long = "all", hide = true)]
+    all: bool,
 }
 
 #[derive(clap::Subcommand, Debug)]
 enum Commands {
     /// Add a pattern to the persistent ignore config
-    Add { pattern: String },
+    Add {
+        pattern: String,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
     /// Remove a pattern from the persistent ignore config
-    Remove { pattern: String },
+    ///
+    /// With no pattern, opens an interactive multi-select of configured patterns.
+    Remove {
+        pattern: Option<String>,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
     /// List all persistent ignore patterns
-    List,
-    /// Clear all persistent ignore patterns
-    Clear,
+    List {
+        /// Print the patterns as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Clear all persistent ignore patterns (backs up the old file first)
+    Clear {
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Manage the ignore config itself
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// The default tree view with explicit flags, for scripts
+    ///
+    /// Same output and flags as plain `struct`, but depth and path never come
+    /// from positional guessing.
+    Tree {
+        /// Directory to show (same as --path)
+        #[arg(value_name = "PATH")]
+        path_pos: Option<PathBuf>,
+        /// Directory to show
+        #[arg(short = 'p', long = "path", value_name = "PATH", conflicts_with = "path_pos")]
+        path: Option<PathBuf>,
+        /// Max depth (0 = summary view)
+        #[arg(short = 'd', long = "depth", value_name = "N", conflicts_with = "level")]
+        depth: Option<usize>,
+        #[command(flatten)]
+        flags: Box<TreeFlags>,
+    },
+    /// Detailed card for a file, glob or directory
+    ///
+    /// Files get size, modification time, permissions, git status and line
+    /// count; directories get the same view as `struct 0`.
+    Summary {
+        /// File, directory, or glob like "src/*.rs"
+        target: String,
+        /// Skip directories larger than SIZE megabytes
+        #[arg(short = 's', long = "skip-large", value_name = "SIZE")]
+        max_size_mb: Option<u64>,
+        /// Count dotfiles too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+    },
+    /// Size and file count of each top-level directory, with usage bars
+    ///
+    /// Like `du -sh * | sort -h`, but ignored dirs and files are left out
+    /// exactly as in the tree (use -n to count them).
+    Overview {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Print the sizes as JSON
+        #[arg(long = "json")]
+        json: bool,
+        /// Count dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+    },
+    /// Directories biggest first, with their share of the parent as bars (ncdu-lite)
+    ///
+    /// Each directory's own files are summed into one (files) row; ignores
+    /// apply as in the tree.
+    Du {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// How many directory levels to show (0 = all)
+        #[arg(short = 'd', long = "depth", value_name = "N", default_value = "3")]
+        depth: usize,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Print the tree of sizes as JSON
+        #[arg(long = "json")]
+        json: bool,
+        /// Count dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+    },
+    /// Disk usage summed per owner, extension or age, with bars
+    ///
+    /// Counts the files the tree would show (use -n to count ignored ones).
+    Usage {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// What to group by
+        #[arg(long = "by", value_name = "KEY", value_parser = ["owner", "ext", "age"], default_value = "owner")]
+        by: String,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Print the table as JSON
+        #[arg(long = "json")]
+        json: bool,
+        /// Count dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+    },
+    /// List recorded invocations, numbered for `struct again N`
+    History {
+        /// Print the history as JSON
+        #[arg(long = "json")]
+        json: bool,
+        /// Delete the history
+        #[arg(long = "clear")]
+        clear: bool,
+    },
+    /// Run a recorded invocation again, from the directory it ran in
+    Again {
+        /// Which one: 1 = the most recent (see `struct history`)
+        #[arg(default_value = "1")]
+        n: usize,
+    },
+    /// Named directories for `struct @NAME`, each with its own default flags
+    Bookmark {
+        #[command(subcommand)]
+        action: BookmarkAction,
+    },
+    /// Save a directory's tree and later see what was added, deleted or resized
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Copy a directory without what the ignore rules hide
+    ///
+    /// Leaves out built-in ignores, config and -i patterns, and whatever the
+    /// repo's .gitignore ignores. DEST must not exist or be empty.
+    Copy {
+        src: PathBuf,
+        dest: PathBuf,
+        /// Show the tree of what would be copied, copy nothing
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Copy files .gitignore ignores too
+        #[arg(long = "no-gitignore")]
+        no_gitignore: bool,
+    },
+    /// Archive the visible tree as .zip, .tar or .tar.gz (format from OUT's extension)
+    ///
+    /// Same rules as `struct copy`: built-in ignores, config and -i patterns
+    /// and .gitignored files are left out.
+    Pack {
+        out: PathBuf,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Compression level, 0 (store) to 9 (smallest)
+        #[arg(long = "level", value_name = "N")]
+        level: Option<u32>,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Pack files .gitignore ignores too
+        #[arg(long = "no-gitignore")]
+        no_gitignore: bool,
+        /// Overwrite OUT if it exists (large files also ask for the name)
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Compare the live tree against a .zip/.tar/.tar.gz without extracting it
+    ///
+    /// Lists files missing from the archive, extra in it, or of another size.
+    /// Exits 0 when they match, 1 when they differ, 2 on errors.
+    Compare {
+        archive: PathBuf,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Count files .gitignore ignores too
+        #[arg(long = "no-gitignore")]
+        no_gitignore: bool,
+        /// Print the differences as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Print the path of a directory numbered by the last `--anchors` tree
+    ///
+    /// For scripts: cd "$(struct goto 2.4)"
+    Goto {
+        /// Index shown before the directory, like 1.2.3
+        anchor: String,
+    },
+    /// Workspace members and how they depend on each other
+    ///
+    /// Reads Cargo workspaces, npm/pnpm/yarn workspaces and go.work; each
+    /// package is shown with its path and size.
+    Packages {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Print a Graphviz DOT graph instead of the tree
+        #[arg(long = "dot")]
+        dot: bool,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+    },
+    /// Find symlinks that lead back to an ancestor (cycles) or an already visited dir
+    ///
+    /// Exits with status 1 when any are found.
+    Loops {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+    },
+    /// Write a Markdown index (INDEX.md) of the tree
+    IndexMd {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// How many directory levels to include (0 = all)
+        #[arg(short = 'd', long = "depth", value_name = "N", default_value = "0")]
+        depth: usize,
+        /// Add each file's first line as a description
+        #[arg(long = "describe")]
+        describe: bool,
+        /// Write an INDEX.md into every directory instead of one file
+        #[arg(long = "per-dir")]
+        per_dir: bool,
+        /// Output file for the single index ("-" for stdout)
+        #[arg(short = 'o', long = "output", value_name = "FILE", conflicts_with = "per_dir")]
+        output: Option<PathBuf>,
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// List dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+    },
+    /// Draw a tree saved with `--output json` (no filesystem access needed)
+    Render {
+        /// JSON file from `struct --output json` ("-" for stdin)
+        file: PathBuf,
+        /// Show the sizes stored in the file
+        #[arg(short = 'z', long = "size")]
+        size: bool,
+    },
+    /// Answer JSON-RPC requests (list, search, summarize) for editor plugins
+    Serve {
+        /// Newline-delimited JSON-RPC 2.0 over stdin/stdout
+        #[arg(long = "stdio", required = true)]
+        stdio: bool,
+    },
     /// Search for files/dirs matching a pattern
     ///
     /// Plain text = substring match. Wildcards (* ?) = glob match.
@@ -142,6 +866,196 @@ enum Commands {
         flat: bool,
         #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
         ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Print only the results, without the "found N items" banner
+        #[arg(short = 'q', long = "quiet")]
+        quiet: bool,
+        /// Search every path of a named root from ~/.config/struct/roots.txt
+        #[arg(short = 'r', long = "root", value_name = "NAME")]
+        root: Option<String>,
+        /// Stop after N matches
+        #[arg(short = 'm', long = "max-count", value_name = "N")]
+        max_count: Option<usize>,
+        /// Print entries scanned / skipped / matched and elapsed time
+        #[arg(long = "stats")]
+        stats: bool,
+        /// Don't search inside directories larger than SIZE megabytes
+        #[arg(short = 's', long = "skip-large", value_name = "SIZE")]
+        max_size_mb: Option<u64>,
+        /// Search dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+    },
+    /// Search file contents; matching lines are shown under each file
+    ///
+    /// Regex, case-insensitive unless it has an uppercase letter.
+    Grep {
+        pattern: String,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        #[arg(value_name = "DEPTH", default_value = "0")]
+        depth: usize,
+        /// Match PATTERN literally, not as a regex
+        #[arg(short = 'F', long = "fixed-strings")]
+        fixed: bool,
+        /// Lines of context after each match
+        #[arg(short = 'A', long = "after-context", value_name = "N")]
+        after: Option<usize>,
+        /// Lines of context before each match
+        #[arg(short = 'B', long = "before-context", value_name = "N")]
+        before: Option<usize>,
+        /// Lines of context before and after each match
+        #[arg(short = 'C', long = "context", value_name = "N")]
+        context: Option<usize>,
+        /// One path:line:text per match instead of the tree
+        #[arg(short = 'f', long = "flat")]
+        flat: bool,
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Print only the results, without the "found N lines" banner
+        #[arg(short = 'q', long = "quiet")]
+        quiet: bool,
+        /// Search inside dotfiles and dot-directories too
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+        /// Stop after N matching lines
+        #[arg(short = 'm', long = "max-count", value_name = "N")]
+        max_count: Option<usize>,
+        /// Print the matches as JSON (for editors)
+        #[arg(long = "json")]
+        json: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigAction {
+    /// Revert the last add/remove/clear
+    Undo {
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Report how many entries each configured pattern hides under PATH
+    Stats {
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Print the report as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Show what the config file sets (--effective: every setting in use, with its source)
+    Show {
+        /// Merge defaults, config file, environment and flags
+        #[arg(long = "effective")]
+        effective: bool,
+        /// Print the settings as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Pack the config, roots, themes and backups into a tar bundle
+    Export {
+        /// Bundle file to write
+        bundle: PathBuf,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Unpack a bundle made by `struct config export`, asking before replacing files
+    Import {
+        /// Bundle file to read
+        bundle: PathBuf,
+        /// Replace every local file that differs, without asking
+        #[arg(long = "overwrite", conflicts_with = "keep")]
+        overwrite: bool,
+        /// Keep every local file that differs, importing only new files
+        #[arg(long = "keep")]
+        keep: bool,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Restore the config from a backup made by `struct clear` (latest by default)
+    Restore {
+        /// Backup file name (or path) to restore
+        backup: Option<String>,
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Move ignores.txt into config.toml, with sections (or start a commented one)
+    Migrate {
+        /// Print the result as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum BookmarkAction {
+    /// Save PATH (default: the current directory) as NAME, for `struct @NAME`
+    Add {
+        name: String,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Flags `struct @NAME` always runs with (flags typed after it win)
+        #[arg(long = "flags", value_name = "FLAGS", allow_hyphen_values = true)]
+        flags: Option<String>,
+    },
+    /// Show every bookmark with its path and flags
+    List {
+        /// Print the bookmarks as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Delete a bookmark
+    Rm {
+        name: String,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum SnapshotAction {
+    /// Record PATH's tree (paths, sizes, mtimes) as NAME, replacing an older one
+    Save {
+        name: String,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Also record each file's sha256: catches same-size edits and renames
+        #[arg(long = "hash")]
+        hash: bool,
+        /// Extra ignore patterns (comma-separated)
+        #[arg(short = 'i', long = "ignore", value_name = "PATTERNS")]
+        ignore_patterns: Option<String>,
+        /// Un-ignore: a name, 'defaults', 'config' or 'all' (repeatable)
+        #[arg(short = 'n', long = "no-ignore", value_name = "TARGET", action = clap::ArgAction::Append)]
+        no_ignore: Vec<String>,
+        /// Record files .gitignore ignores too
+        #[arg(long = "no-gitignore")]
+        no_gitignore: bool,
+    },
+    /// Show what changed in the snapshot's directory since it was taken
+    ///
+    /// Exits 0 when nothing changed, 1 when something did, 2 on errors.
+    Diff {
+        name: String,
+        /// Print the changes as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Show every snapshot with its directory and age
+    List {
+        /// Print the snapshots as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Delete a snapshot
+    Rm {
+        name: String,
     },
 }
 
@@ -149,9 +1063,24 @@ enum Commands {
 
 /// Inspect the subcommands to know if argv[1] is a subcommand keyword.
 fn is_subcommand(s: &str) -> bool {
-    matches!(s, "search" | "add" | "remove" | "list" | "clear" | "help")
+    matches!(
+        s,
+        "search" | "grep" | "tree" | "summary" | "overview" | "du" | "usage" | "packages" | "goto" | "copy" | "pack" | "compare" | "loops" | "index-md" | "render" | "serve" | "add" | "remove" | "list" | "clear" | "config" | "history" | "again" | "bookmark" | "snapshot" | "help"
+    )
 }
 
+/// Flags that consume the next token as their value — we must not mistake
+/// that value for a DEPTH or PATH (or an @bookmark).
+const VALUE_FLAGS: &[&str] = &[
+    "-i", "--ignore", "-s", "--skip-large", "-n", "--no-ignore", "--budget",
+    "-L", "--level", "--badge-size", "--group-by", "--max-lines", "--output",
+    "--number-format", "--size-precision", "--size-unit-min", "--timeout", "--sort",
+    "--lang", "--where", "--only", "--gd",
+];
+
+/// (depth, path, argv with those tokens removed)
+type PreprocessedArgs = (Option<usize>, Option<PathBuf>, Vec<OsString>);
+
 /// Extract DEPTH and PATH from argv before handing to clap.
 /// Returns (depth, path, cleaned_argv_without_those_tokens).
 ///
@@ -159,22 +1088,18 @@ fn is_subcommand(s: &str) -> bool {
 ///   - Skip argv[0] (binary name) and any subcommand keyword at argv[1].
 ///   - A token that starts with '-' is a flag — leave it alone.
 ///   - A token that is a flag VALUE (follows a flag that takes a value) — skip it.
-///   - First remaining bare token that parses as usize → DEPTH (removed).
+///   - First remaining bare token that parses as usize → DEPTH (removed),
+///     unless depth is given with -L, in which case every bare token is a PATH.
 ///   - First remaining bare token that doesn't → PATH (removed).
-///   - Any further bare tokens are silently discarded (they'd cause clap
-///     "unrecognized subcommand" errors since clap has no positionals defined).
-fn preprocess_argv() -> (Option<usize>, Option<PathBuf>, Vec<OsString>) {
-    // Flags that consume the next token as their value — we must not mistake
-    // that value for a DEPTH or PATH.
-    const VALUE_FLAGS: &[&str] = &["-i", "--ignore", "-s", "--skip-large", "-n", "--no-ignore"];
-
-    let raw: Vec<String> = std::env::args().collect();
+///   - A number that is also an existing path is ambiguous → error.
+///   - Any further bare tokens → error (a second path, a second depth).
+fn preprocess_argv(raw: &[String]) -> Result<PreprocessedArgs, String> {
     let mut cleaned: Vec<OsString> = Vec::new();
     let mut depth: Option<usize> = None;
     let mut path: Option<PathBuf> = None;
 
     // Always keep argv[0]
-    if let Some(bin) = raw.get(0) {
+    if let Some(bin) = raw.first() {
         cleaned.push(bin.into());
     }
 
@@ -183,9 +1108,14 @@ fn preprocess_argv() -> (Option<usize>, Option<PathBuf>, Vec<OsString>) {
         for tok in raw.iter().skip(1) {
             cleaned.push(tok.into());
         }
-        return (None, None, cleaned);
+        return Ok((None, None, cleaned));
     }
 
+    // With an explicit -L the positionals can only be a path
+    let has_level_flag = raw.iter().skip(1).any(|t| {
+        t == "-L" || t == "--level" || t.starts_with("--level=") || (t.starts_with("-L") && t.len() > 2)
+    });
+
     let mut skip_next = false;
     for tok in raw.iter().skip(1) {
         if skip_next {
@@ -206,8 +1136,23 @@ fn preprocess_argv() -> (Option<usize>, Option<PathBuf>, Vec<OsString>) {
         }
 
         // Bare token — try to claim as DEPTH or PATH
-        if depth.is_none() {
+        let is_number = tok.parse::<usize>().is_ok();
+        if is_number && !std::path::Path::new(tok).exists() {
+            if has_level_flag {
+                return Err(format!("depth given twice ('{}' and -L) — use one or the other", tok));
+            }
+            if depth.is_some() {
+                return Err(format!("unexpected extra depth '{}' — give the depth only once", tok));
+            }
+        }
+        if depth.is_none() && !has_level_flag {
             if let Ok(n) = tok.parse::<usize>() {
+                if std::path::Path::new(tok).exists() {
+                    return Err(format!(
+                        "'{}' could be a depth or the path ./{} — use -L {} for the depth or ./{} for the path",
+                        tok, tok, tok, tok
+                    ));
+                }
                 depth = Some(n);
                 continue; // consumed — don't push to cleaned
             }
@@ -217,48 +1162,294 @@ fn preprocess_argv() -> (Option<usize>, Option<PathBuf>, Vec<OsString>) {
             continue; // consumed
         }
 
-        // Extra bare token (second path, extra number, etc.) — silently discard.
-        // Passing these to clap causes "unrecognized subcommand" errors since clap
-        // has no positionals defined and treats bare tokens as subcommand names.
-        let _ = tok; // consumed, ignored
+        // Extra bare token (second path, extra number, etc.)
+        return Err(format!(
+            "unexpected extra argument '{}' — struct takes one PATH (already got '{}')",
+            tok,
+            path.as_ref().map(|p| p.display().to_string()).unwrap_or_default()
+        ));
+    }
+
+    Ok((depth, path, cleaned))
+}
+
+// ─── STRUCT_FLAGS ─────────────────────────────────────────────────────────────
+
+/// Environment variable holding extra default flags, for wrappers and editor plugins
+const FLAGS_ENV: &str = "STRUCT_FLAGS";
+
+/// Split a flags string like a shell would: whitespace-separated, with
+/// 'single' / "double" quotes and backslash escapes.
+fn split_flags(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_token = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_token = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// Flags from the config file's `[defaults]` (`defaults.*` in ignores.txt):
+/// `show_size = true` → `-z`, `sort = "size"` → `--sort size`, plus the
+/// free-form `flags`. The default depth is applied in `run_tree`, so a
+/// depth on the command line never clashes with it.
+fn config_default_flags() -> Vec<String> {
+    let mut flags = Vec::new();
+    if load_config_setting("defaults.show_size").as_deref() == Some("true") {
+        flags.push("-z".to_string());
+    }
+    if let Some(sort) = load_config_setting("defaults.sort") {
+        flags.extend(["--sort".to_string(), sort]);
+    }
+    if let Some(extra) = load_config_setting("defaults.flags") {
+        match split_flags(&extra) {
+            Ok(extra) => flags.extend(extra),
+            Err(e) => eprintln!("warning: {} in the config's defaults.flags, ignoring them", e),
+        }
+    }
+    flags
+}
+
+/// argv with the config's default flags and $STRUCT_FLAGS spliced in right
+/// after the binary name (or after `tree`), in that order. Flags from the
+/// command line come later, so they win.
+/// Other subcommands have their own flags and are left alone, and don't
+/// read `[defaults]` at all, so `struct config` still works when it's broken.
+fn args_with_env_flags() -> Result<Vec<String>, String> {
+    let mut raw = expand_bookmark(std::env::args().collect())?;
+    let insert_at = match raw.get(1).map(|s| s.as_str()) {
+        Some("tree") => 2,
+        Some(s) if is_subcommand(s) => return Ok(raw),
+        _ => 1,
+    };
+
+    let mut extra = config_default_flags();
+    if let Ok(v) = std::env::var(FLAGS_ENV) {
+        extra.extend(split_flags(&v).map_err(|e| format!("{} in {}", e, FLAGS_ENV))?);
+    }
+    let insert_at = insert_at.min(raw.len());
+    raw.splice(insert_at..insert_at, extra);
+    Ok(raw)
+}
+
+/// `struct @NAME ...`: the bookmark's path in place of `@NAME`, and its flags
+/// right after the binary name (or `tree`), so flags typed on the command
+/// line come later and win. STRUCT_FLAGS go in front of both.
+/// A directory really named `@NAME` is left alone.
+fn expand_bookmark(mut raw: Vec<String>) -> Result<Vec<String>, String> {
+    let insert_at = match raw.get(1).map(|s| s.as_str()) {
+        Some("tree") => 2,
+        Some(s) if is_subcommand(s) => return Ok(raw),
+        _ => 1,
+    };
+
+    let mut skip_next = false;
+    let mut found = None;
+    for (i, tok) in raw.iter().enumerate().skip(insert_at) {
+        if std::mem::take(&mut skip_next) {
+            continue;
+        }
+        if tok.starts_with('-') {
+            skip_next = VALUE_FLAGS.contains(&tok.as_str());
+            continue;
+        }
+        if tok.len() > 1 && tok.starts_with('@') && !std::path::Path::new(tok).exists() {
+            found = Some(i);
+            break;
+        }
     }
+    let Some(i) = found else { return Ok(raw) };
 
-    (depth, path, cleaned)
+    let name = &raw[i][1..];
+    let Some(bookmark) = find_bookmark(name) else {
+        return Err(format!("no bookmark named {} — see struct bookmark list", name));
+    };
+    let flags = split_flags(&bookmark.flags).map_err(|e| format!("{} in the flags of bookmark {}", e, name))?;
+    raw[i] = bookmark.path.display().to_string();
+    raw.splice(insert_at..insert_at, flags);
+    Ok(raw)
 }
 
 // ─── Ignore flag processing ───────────────────────────────────────────────────
 
-/// Fold multiple -n values into (skip_defaults, skip_config, skip_specific_patterns).
-fn parse_no_ignore(values: &[String]) -> (bool, bool, Vec<String>) {
+/// Fold multiple -n values into (skip_defaults, skip_config, skip_project, skip_specific_patterns).
+fn parse_no_ignore(values: &[String]) -> (bool, bool, bool, Vec<String>) {
     let mut skip_defaults = false;
     let mut skip_config = false;
+    let mut skip_project = false;
     let mut specifics: Vec<String> = Vec::new();
 
     for v in values {
         match v.as_str() {
-            "all"      => { skip_defaults = true; skip_config = true; }
+            "all"      => { skip_defaults = true; skip_config = true; skip_project = true; }
             "defaults" => { skip_defaults = true; }
             "config"   => { skip_config = true; }
+            "project"  => { skip_project = true; }
             pattern    => { specifics.push(pattern.to_string()); }
         }
     }
-    (skip_defaults, skip_config, specifics)
+    (skip_defaults, skip_config, skip_project, specifics)
 }
 
-fn build_ignores_from_patterns(patterns: Vec<String>) -> Vec<Regex> {
-    patterns
+/// Compile patterns, collecting `(pattern, reason)` for each one that fails
+fn build_ignores_from_patterns(patterns: &[String], errors: &mut Vec<(String, String)>) -> Vec<Regex> {
+    let mut compiled = Vec::new();
+    for p in patterns {
+        match compile_pattern(p) {
+            Ok(re) => compiled.push(re),
+            Err(e) => errors.push((p.clone(), pattern_error_reason(&e))),
+        }
+    }
+    compiled
+}
+
+/// Build the shared ignore rules from `-n` values and inline `-i` patterns,
+/// plus the project ignore files under `roots` (the paths the command walks).
+/// Used by every mode so the flags mean the same thing everywhere.
+///
+/// Patterns that don't compile are skipped with a warning naming where they
+/// came from; with `strict` they're a hard error instead.
+fn build_ignore_rules(no_ignore: &[String], inline: Option<&str>, strict: bool, roots: &[PathBuf]) -> IgnoreRules {
+    let (skip_defaults, skip_config, skip_project, unignored) = parse_no_ignore(no_ignore);
+
+    let config_patterns = if skip_config { Vec::new() } else { load_numbered_config_patterns() };
+    let inline_patterns: Vec<String> = inline
+        .map(|i| i.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+
+    // Each pattern keeps where it came from, for error messages and --trace-ignores
+    let config_path = get_config_path().display().to_string();
+    let sourced = config_patterns
         .iter()
-        .filter_map(|p| {
-            let p = p.trim().replace("*", ".*");
-            Regex::new(&format!("^{}$", p)).ok()
-        })
-        .collect()
+        .map(|(line, p)| (p, format!("{}:{}", config_path, line)))
+        .chain(inline_patterns.iter().map(|p| (p, "-i".to_string())));
+    let mut custom = Vec::new();
+    let mut custom_sources = Vec::new();
+    let mut errors: Vec<(String, String, String)> = Vec::new();
+    for (pattern, source) in sourced {
+        match compile_pattern(pattern) {
+            Ok(re) => {
+                custom.push(re);
+                custom_sources.push(format!("{} from {}", pattern, source));
+            }
+            Err(e) => errors.push((pattern.clone(), pattern_error_reason(&e), source)),
+        }
+    }
+    for (pattern, reason, source) in &errors {
+        let level = if strict { "error" } else { "warning" };
+        eprintln!(
+            "{}",
+            format!("{}: invalid ignore pattern {:?} (from {}): {}", level, pattern, source, reason).yellow()
+        );
+    }
+    if strict && !errors.is_empty() {
+        std::process::exit(2);
+    }
+
+    IgnoreRules {
+        custom,
+        custom_sources,
+        skip_defaults,
+        unignored,
+        removed_defaults: if skip_config { Vec::new() } else { load_builtin_unignores() },
+        hide_dotfiles: false,
+        skip_project,
+        project_roots: roots.to_vec(),
+        project: Default::default(),
+    }
+}
+
+/// Whether the tree, search and summary leave dotfiles out: yes unless `-a`
+/// or `hidden = show` in the config file
+fn hide_dotfiles(all: bool) -> bool {
+    if all {
+        return false;
+    }
+    match load_config_setting("hidden").as_deref() {
+        None | Some("hide") => true,
+        Some("show") => false,
+        Some(other) => {
+            eprintln!("warning: config hidden = \"{}\": use show or hide", other);
+            true
+        }
+    }
+}
+
+/// Size format from the flags, falling back to the config file's
+/// `size_precision` / `size_min_unit` settings, then to `12.3K`-style defaults
+fn resolve_size_format(precision: Option<u8>, min_unit: Option<&str>) -> SizeFormat {
+    let precision = precision
+        .map(usize::from)
+        .or_else(|| load_config_setting("size_precision").and_then(|p| p.parse().ok()))
+        .unwrap_or(1)
+        .min(3);
+
+    let unit_name = min_unit.map(String::from).or_else(|| load_config_setting("size_min_unit"));
+    let min_unit = match unit_name {
+        Some(name) => parse_size_unit(&name).unwrap_or_else(|| {
+            eprintln!("warning: unknown size unit '{}' (use B, K, M or G)", name);
+            0
+        }),
+        None => 0,
+    };
+    SizeFormat { precision, min_unit }
 }
 
 // ─── Main ─────────────────────────────────────────────────────────────────────
 
 fn main() {
-    let raw_strs: Vec<String> = std::env::args().collect();
+    // Die quietly on a closed pipe (`struct --long | head`) instead of panicking
+    #[cfg(unix)]
+    // SAFETY: restoring the default disposition of a signal before any threads exist
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let raw_strs = match args_with_env_flags() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if raw_strs.iter().any(|a| a == DEBUG_BUNDLE_FLAG) {
+        run_with_debug_bundle(&raw_strs);
+    }
 
     // Intercept -h / --help for top-level (not subcommands)
     let top_level = !raw_strs.get(1).map(|s| is_subcommand(s)).unwrap_or(false);
@@ -268,37 +1459,382 @@ fn main() {
     }
 
     // Pre-process: pull out DEPTH and PATH before clap sees argv
-    let (raw_depth, raw_path, cleaned_argv) = preprocess_argv();
+    let (raw_depth, raw_path, cleaned_argv) = match preprocess_argv(&raw_strs) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
 
     // Parse only flags
     let flags = Flags::parse_from(cleaned_argv);
+    let strict = flags.strict;
+    set_hydrate(flags.hydrate);
+    set_include_virtual(flags.include_virtual);
+    set_language(flags.lang.as_deref());
+    set_number_format(flags.number_format.as_deref().unwrap_or("locale"));
+    set_size_format(resolve_size_format(flags.size_precision, flags.size_unit_min.as_deref()));
+    if !matches!(flags.command, Some(Commands::History { .. } | Commands::Again { .. })) {
+        record(&std::env::args().skip(1).collect::<Vec<_>>());
+    }
 
     // ── Subcommands ───────────────────────────────────────────────────────────
     if let Some(command) = flags.command {
         match command {
-            Commands::Add { pattern } => { add_config_pattern(pattern); return; }
-            Commands::Remove { pattern } => { remove_config_pattern(pattern); return; }
-            Commands::List => { list_config_patterns(); return; }
-            Commands::Clear => { clear_config_patterns(); return; }
+            Commands::Add { pattern, json } => { add_config_pattern(pattern, json); return; }
+            Commands::Remove { pattern, json } => {
+                match pattern {
+                    Some(pattern) => remove_config_pattern(pattern, json),
+                    None => remove_config_patterns_interactive(json),
+                }
+                return;
+            }
+            Commands::List { json } => { list_config_patterns(json); return; }
+            Commands::Clear { yes, json } => { clear_config_patterns(yes, json); return; }
+            Commands::Config { action } => {
+                match action {
+                    ConfigAction::Undo { json } => undo_config_change(json),
+                    ConfigAction::Stats { path, json } => config_pattern_stats(&path, json),
+                    ConfigAction::Restore { backup, json } => restore_config_backup(backup, json),
+                    ConfigAction::Migrate { json } => migrate_config(json),
+                    ConfigAction::Export { bundle, json } => export_bundle(&bundle, json),
+                    ConfigAction::Import { bundle, overwrite, keep, json } => {
+                        let conflicts = if overwrite {
+                            Conflicts::Overwrite
+                        } else if keep {
+                            Conflicts::Keep
+                        } else {
+                            Conflicts::Ask
+                        };
+                        import_bundle(&bundle, conflicts, json)
+                    }
+                    ConfigAction::Show { effective, json } => {
+                        let cli = CliSettings {
+                            strict,
+                            number_format: flags.number_format.as_deref(),
+                            size_precision: flags.size_precision,
+                            size_unit_min: flags.size_unit_min.as_deref(),
+                            lang: flags.lang.as_deref(),
+                        };
+                        show_config(&cli, effective, json);
+                    }
+                }
+                return;
+            }
 
-            Commands::Search { pattern, path, depth, flat, ignore_patterns } => {
+            Commands::Search {
+                pattern, path, depth, flat, ignore_patterns, no_ignore, quiet, root, max_count, stats, max_size_mb, all,
+            } => {
                 let max_depth = if depth == 0 { usize::MAX } else { depth };
-                let mut all_patterns = load_config_patterns();
-                if let Some(inline) = ignore_patterns {
-                    for p in inline.split(',') {
-                        let p = p.trim().to_string();
-                        if !p.is_empty() { all_patterns.push(p); }
+                let start_paths = match root {
+                    Some(name) => match load_search_roots().into_iter().find(|(n, _)| *n == name) {
+                        Some((_, paths)) if !paths.is_empty() => paths,
+                        _ => {
+                            eprintln!(
+                                "error: no search root named '{}' in {}",
+                                name,
+                                get_roots_path().display()
+                            );
+                            std::process::exit(2);
+                        }
+                    },
+                    None => vec![path],
+                };
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, &start_paths);
+                // Looking for a dotfile by name (".env*") implies -a
+                ignores.hide_dotfiles = hide_dotfiles(all || pattern.starts_with('.'));
+                let opts = SearchOptions {
+                    max_depth,
+                    flat,
+                    quiet,
+                    max_count,
+                    stats,
+                    max_size_bytes: max_size_mb.map(|mb| mb * 1024 * 1024),
+                };
+                // Like grep: 0 = found something, 1 = nothing found, 2 = error
+                match search_files(&pattern, &start_paths, &opts, &ignores) {
+                    Some(0) => std::process::exit(1),
+                    Some(_) => return,
+                    None => std::process::exit(2),
+                }
+            }
+
+            Commands::Grep {
+                pattern, path, depth, fixed, after, before, context, flat, ignore_patterns, no_ignore, quiet, all,
+                max_count, json,
+            } => {
+                let opts = GrepOptions {
+                    max_depth: if depth == 0 { usize::MAX } else { depth },
+                    fixed,
+                    before: before.or(context).unwrap_or(0),
+                    after: after.or(context).unwrap_or(0),
+                    flat,
+                    quiet,
+                    max_count,
+                    json,
+                };
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                // Like search: 0 = found something, 1 = nothing found, 2 = error
+                match grep_files(&pattern, &path, &opts, &ignores) {
+                    Some(0) => std::process::exit(1),
+                    Some(_) => return,
+                    None => std::process::exit(2),
+                }
+            }
+
+            Commands::IndexMd { path, depth, describe, per_dir, output, ignore_patterns, no_ignore, all } => {
+                let opts = IndexOptions {
+                    depth: if depth == 0 { usize::MAX } else { depth },
+                    describe,
+                    per_dir,
+                    output,
+                };
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                generate_index(&path, &opts, &ignores);
+                return;
+            }
+
+            Commands::Render { file, size } => {
+                render_json_tree(&file, size);
+                return;
+            }
+
+            Commands::Serve { stdio: _ } => {
+                // A bad pattern in one request shouldn't take the server down
+                serve_stdio(&|no_ignore: &[String], inline: Option<&str>, all: bool, path: &Path| {
+                    let mut ignores = build_ignore_rules(no_ignore, inline, false, &[path.to_path_buf()]);
+                    ignores.hide_dotfiles = hide_dotfiles(all);
+                    ignores
+                });
+                return;
+            }
+
+            Commands::Loops { path, no_ignore } => {
+                if report_symlink_loops(&path, &build_ignore_rules(&no_ignore, None, strict, std::slice::from_ref(&path))) {
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            Commands::Overview { path, ignore_patterns, no_ignore, json, all } => {
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                display_overview(&path, &ignores, json);
+                return;
+            }
+
+            Commands::Du { path, depth, ignore_patterns, no_ignore, json, all } => {
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                display_du(&path, depth, &ignores, json);
+                return;
+            }
+
+            Commands::Usage { path, by, ignore_patterns, no_ignore, json, all } => {
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                display_usage(&path, &by, &ignores, json);
+                return;
+            }
+
+            Commands::Copy { src, dest, dry_run, ignore_patterns, no_ignore, no_gitignore } => {
+                let ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&src));
+                let opts = CopyOptions { dry_run, gitignore: !no_gitignore };
+                if !copy_tree(&src, &dest, &ignores, &opts) {
+                    std::process::exit(2);
+                }
+                return;
+            }
+
+            Commands::Pack { out, path, level, ignore_patterns, no_ignore, no_gitignore, force } => {
+                let ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                let opts = PackOptions { level, gitignore: !no_gitignore, force };
+                if !pack_tree(&out, &path, &ignores, &opts) {
+                    std::process::exit(2);
+                }
+                return;
+            }
+
+            Commands::Compare { archive, path, ignore_patterns, no_ignore, no_gitignore, json } => {
+                let ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                match compare_archive(&archive, &path, &ignores, !no_gitignore, json) {
+                    Some(true) => return,
+                    Some(false) => std::process::exit(1),
+                    None => std::process::exit(2),
+                }
+            }
+
+            Commands::History { json, clear } => {
+                if clear { clear_history() } else { show_history(json) }
+                return;
+            }
+
+            Commands::Again { n } => run_again(n),
+
+            Commands::Bookmark { action } => {
+                match action {
+                    BookmarkAction::Add { name, path, flags } => {
+                        let flags = flags.unwrap_or_default();
+                        if let Err(e) = split_flags(&flags)
+                            .map_err(|e| format!("{} in --flags", e))
+                            .and_then(|_| add_bookmark(&name, &path, &flags)) {
+                            eprintln!("error: {}", e);
+                            std::process::exit(2);
+                        }
+                    }
+                    BookmarkAction::List { json } => list_bookmarks(json),
+                    BookmarkAction::Rm { name } => {
+                        if !remove_bookmark(&name) {
+                            std::process::exit(1);
+                        }
                     }
                 }
-                let custom_ignores = build_ignores_from_patterns(all_patterns);
-                search_files(&pattern, &path, max_depth, flat, &custom_ignores);
                 return;
             }
+
+            Commands::Snapshot { action } => {
+                match action {
+                    SnapshotAction::Save { name, path, hash, ignore_patterns, no_ignore, no_gitignore } => {
+                        let ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, std::slice::from_ref(&path));
+                        let saved = save_snapshot(
+                            &name,
+                            &path,
+                            hash,
+                            ignore_patterns.as_deref(),
+                            &no_ignore,
+                            !no_gitignore,
+                            &ignores,
+                        );
+                        if let Err(e) = saved {
+                            eprintln!("error: {}", e);
+                            std::process::exit(2);
+                        }
+                    }
+                    SnapshotAction::Diff { name, json } => {
+                        let snap = match load_snapshot(&name) {
+                            Ok(snap) => snap,
+                            Err(e) => {
+                                eprintln!("error: {}", e);
+                                std::process::exit(2);
+                            }
+                        };
+                        // Read the live tree with the rules the snapshot was taken with
+                        let ignores = build_ignore_rules(&snap.no_ignore, snap.ignore.as_deref(), strict, std::slice::from_ref(&snap.root));
+                        match diff_snapshot(&snap, &ignores, json) {
+                            Some(true) => {}
+                            Some(false) => std::process::exit(1),
+                            None => std::process::exit(2),
+                        }
+                    }
+                    SnapshotAction::List { json } => list_snapshots(json),
+                    SnapshotAction::Rm { name } => {
+                        if !remove_snapshot(&name) {
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                return;
+            }
+
+            Commands::Goto { anchor } => {
+                if !goto_anchor(&anchor) {
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            Commands::Packages { path, dot, no_ignore } => {
+                if !display_packages(&path, &build_ignore_rules(&no_ignore, None, strict, std::slice::from_ref(&path)), dot) {
+                    std::process::exit(2);
+                }
+                return;
+            }
+
+            Commands::Summary { target, max_size_mb, all, ignore_patterns, no_ignore } => {
+                let max_size = max_size_mb.map(|mb| mb * 1024 * 1024);
+                let mut ignores = build_ignore_rules(&no_ignore, ignore_patterns.as_deref(), strict, &expand_glob(&target));
+                ignores.hide_dotfiles = hide_dotfiles(all);
+                display_target_summary(&target, &ignores, max_size);
+                save_size_cache();
+                return;
+            }
+
+            Commands::Tree { path_pos, path, depth, flags } => {
+                let depth = depth.or(flags.level);
+                run_tree(*flags, depth, path.or(path_pos), strict, &raw_strs);
+                save_size_cache();
+                return;
+            }
+        }
+    }
+
+    // -L N is the explicit spelling of the positional DEPTH
+    let raw_depth = match (raw_depth, flags.tree.level) {
+        (Some(_), Some(_)) => {
+            eprintln!("error: depth given twice — use either a positional DEPTH or -L, not both");
+            std::process::exit(2);
         }
+        (positional, level) => positional.or(level),
+    };
+
+    let mut tree = flags.tree;
+    tree.perms |= flags.perms_short;
+    run_tree(tree, raw_depth, raw_path, strict, &raw_strs);
+    save_size_cache();
+}
+
+/// The flags --attest hashes and prints: the command line without the path
+/// (so checkouts in different places compare equal), `tree`, and --attest
+/// itself, with the depth spelled as a leading `-L N` however it was given.
+/// `-p` is `--path DIR` under `struct tree` and `--perms` otherwise, so the
+/// latter is spelled out and both command lines attest the same.
+fn attest_params(args: &[String], depth: usize) -> Vec<String> {
+    let mut params = Vec::new();
+    if depth != usize::MAX {
+        params.extend(["-L".to_string(), depth.to_string()]);
+    }
+    let tree_cmd = args.get(1).map(|s| s.as_str()) == Some("tree");
+    let rest = if tree_cmd { args.get(2..) } else { args.get(1..) }.unwrap_or_default();
+    // Flags whose value is the depth or the path, dropped with it
+    let mut positional = vec!["-L", "--level", "--depth", "--path"];
+    if tree_cmd {
+        positional.extend(["-d", "-p"]);
     }
 
+    let mut tokens = rest.iter();
+    while let Some(tok) = tokens.next() {
+        let without_value = ["--level=", "--depth=", "--path="].iter().any(|p| tok.starts_with(p))
+            || positional.iter().any(|p| p.len() == 2 && tok.starts_with(p) && tok.len() > 2);
+        if tok == "--attest" || without_value || !tok.starts_with('-') {
+            continue;
+        }
+        if positional.contains(&tok.as_str()) {
+            tokens.next();
+            continue;
+        }
+        if tok == "-p" {
+            params.push("--perms".to_string());
+            continue;
+        }
+        params.push(tok.clone());
+        if VALUE_FLAGS.contains(&tok.as_str()) {
+            params.extend(tokens.next().cloned());
+        }
+    }
+    params
+}
+
+/// Render the tree (or the summary for depth 0) — the default mode and `struct tree`.
+/// `args` is the command line as run (with STRUCT_FLAGS), for --attest.
+fn run_tree(flags: TreeFlags, raw_depth: Option<usize>, raw_path: Option<PathBuf>, strict: bool, args: &[String]) {
+    let started = std::time::Instant::now();
+
     // ── Resolve path and depth ────────────────────────────────────────────────
     let path = raw_path.unwrap_or_else(|| PathBuf::from("."));
+    let raw_depth = raw_depth.or_else(|| load_config_setting("defaults.depth").and_then(|d| d.parse().ok()));
 
     let depth_for_tree = match raw_depth {
         None    => usize::MAX,
@@ -309,7 +1845,11 @@ fn main() {
     let max_size_bytes = flags.max_size_mb.map(|mb| mb * 1024 * 1024);
 
     // ── Git mode (conflicting flags: highest priority wins) ───────────────────
-    let git_mode = if flags.git_changed || flags.git_changed_root {
+    let git_mode = if flags.git_conflicts {
+        Some(GitMode::Conflicts)
+    } else if flags.git_diff.is_some() {
+        Some(GitMode::Diff)
+    } else if flags.git_changed || flags.git_changed_root {
         Some(GitMode::Changed)
     } else if flags.git_staged || flags.git_staged_root {
         Some(GitMode::Staged)
@@ -323,50 +1863,108 @@ fn main() {
         None
     };
 
-    let use_git_root = flags.git_root
+    let use_git_root = flags.from_git_root
+        || flags.git_root
         || flags.git_untracked_root
         || flags.git_staged_root
         || flags.git_changed_root
         || flags.git_history_root;
 
     if git_mode.is_some() && Repository::discover(&path).is_err() {
-        eprintln!("error: not in a git repository");
+        eprintln!("{}", tr("error: not in a git repository"));
         return;
     }
 
     let start_path = if use_git_root {
         match Repository::discover(&path) {
             Ok(repo) => repo.workdir().map(|w| w.to_path_buf()).unwrap_or_else(|| path.clone()),
-            Err(_) => { eprintln!("error: not in a git repository"); return; }
+            Err(_) => { eprintln!("{}", tr("error: not in a git repository")); return; }
         }
     } else {
         path.clone()
     };
 
-    // ── Ignore config ─────────────────────────────────────────────────────────
-    let (skip_defaults, skip_config, skip_specifics) = parse_no_ignore(&flags.no_ignore);
+    // Lock down before walking the tree; git data is read first (the
+    // repository may sit above the root) and nothing is saved afterwards
+    let sandbox = flags.sandbox;
+    set_read_only(sandbox);
+    let lock_down = || {
+        if !sandbox {
+            return;
+        }
+        let root = start_path.canonicalize().unwrap_or_else(|_| start_path.clone());
+        if let Err(e) = enter_sandbox(&root) {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
 
-    // depth 0 + git flags: git filtering is ignored for summary (summary shows dir stats, not file lists)
-    if raw_depth == Some(0) {
-        display_summary(&start_path);
-        return;
-    }
+    // --annotate: all configured annotators, or the ones named
+    let annotators = flags.annotate.as_deref().map(|names| {
+        let configured = configured_annotators();
+        if configured.is_empty() {
+            eprintln!("error: --annotate: no annotators configured (add annotator.NAME = \"command\" to the config)");
+            std::process::exit(2);
+        }
+        let wanted: Vec<&str> = names.split(',').map(str::trim).filter(|n| !n.is_empty()).collect();
+        if let Some(unknown) = wanted.iter().find(|n| !configured.iter().any(|(name, _)| name == *n)) {
+            let known: Vec<&str> = configured.iter().map(|(name, _)| name.as_str()).collect();
+            eprintln!("error: --annotate: no annotator named {} (configured: {})", unknown, known.join(", "));
+            std::process::exit(2);
+        }
+        configured.into_iter().filter(|(name, _)| wanted.is_empty() || wanted.contains(&name.as_str())).collect::<Vec<_>>()
+    });
 
-    let config_patterns = if skip_config { Vec::new() } else { load_config_patterns() };
-    let mut all_patterns = config_patterns;
+    let filter = match flags.filter.as_deref().map(|expr| Filter::parse(expr, &start_path)) {
+        Some(Err(e)) => {
+            eprintln!("error: --where: {}", e);
+            std::process::exit(2);
+        }
+        parsed => parsed.and_then(Result::ok),
+    };
 
-    // Add skip_specifics as additional ignore patterns (un-ignore means remove from
-    // default list, handled in display.rs via skip_specific — we pass the first one
-    // for backward compat; multiple specifics: each gets its own skip_specific pass)
-    if let Some(inline) = flags.ignore_patterns {
-        for p in inline.split(',') {
-            let p = p.trim().to_string();
-            if !p.is_empty() { all_patterns.push(p); }
+    let only = flags.only_patterns.as_deref().map(|list| {
+        let patterns: Vec<String> =
+            list.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+        let mut errors = Vec::new();
+        let compiled = build_ignores_from_patterns(&patterns, &mut errors);
+        if let Some((pattern, reason)) = errors.first() {
+            eprintln!("error: invalid --only pattern {:?}: {}", pattern, reason);
+            std::process::exit(2);
         }
+        compiled
+    });
+
+    // ── Ignore config ─────────────────────────────────────────────────────────
+    let mut ignores = build_ignore_rules(&flags.no_ignore, flags.ignore_patterns.as_deref(), strict, std::slice::from_ref(&start_path));
+    ignores.hide_dotfiles = hide_dotfiles(flags.all);
+    if flags.trace_ignores {
+        enable_trace();
+        ignores.trace_layers();
+    }
+
+    // depth 0 + git flags: git filtering is ignored for summary (summary shows dir stats, not file lists)
+    if raw_depth == Some(0) && flags.output.as_deref() != Some("json") && !flags.interactive {
+        lock_down();
+        display_summary(&start_path, &ignores, flags.quiet, max_size_bytes);
+        return;
     }
-    let custom_ignores = build_ignores_from_patterns(all_patterns);
 
     // ── Git file sets ─────────────────────────────────────────────────────────
+    let conflicts = match git_mode {
+        Some(GitMode::Conflicts) => get_git_conflicts(&start_path),
+        _ => None,
+    };
+    let range_diff = match (&git_mode, flags.git_diff.as_deref()) {
+        (Some(GitMode::Diff), Some(range)) => match get_git_range_diff(&start_path, range) {
+            Ok(diff) => Some(diff),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
     let git_files = if let Some(ref mode) = git_mode {
         match mode {
             GitMode::Tracked   => get_git_tracked_files(&start_path),
@@ -374,26 +1972,191 @@ fn main() {
             GitMode::Staged    => get_git_staged_files(&start_path),
             GitMode::Changed   => get_git_changed_files(&start_path),
             GitMode::History   => None,
+            GitMode::Conflicts => conflicts.as_ref().map(|c| c.keys().cloned().collect()),
+            GitMode::Diff      => range_diff.as_ref().map(|(changes, _)| changes.keys().cloned().collect()),
         }
     } else {
         None
     };
+    if let (true, Some(mode), Some(files)) = (flags.trace_ignores, &git_mode, &git_files) {
+        let mode = format!("{:?}", mode).to_lowercase();
+        eprintln!("trace-ignores: layer=git mode={} files={} replaces=ignore-rules", mode, files.len());
+    }
 
-    // For multiple -n specifics, use the first one (StructConfig takes one skip_specific).
-    // display.rs would need updating to support a Vec — for now first wins.
-    let skip_specific = skip_specifics.into_iter().next();
+    let untracked_dirs = match git_mode {
+        Some(GitMode::Untracked) if !flags.expand_untracked => get_git_untracked_dirs(&start_path),
+        _ => None,
+    };
+
+    // Plain view: the index, to notice tracked code inside built-in ignored dirs
+    let tracked_files = if git_mode.is_none() { get_git_tracked_files(&start_path) } else { None };
+
+    // +added -removed per file in the changed / staged / range views
+    let (range_changes, range_stats) = range_diff.unzip();
+    let diffstat = match git_mode {
+        Some(GitMode::Changed) => get_git_diffstat(&start_path, false),
+        Some(GitMode::Staged) => get_git_diffstat(&start_path, true),
+        Some(GitMode::Diff) => range_stats,
+        _ => None,
+    };
+
+    // Last commit per path (--gh); nothing to show before the first commit
+    let last_commits = match git_mode {
+        Some(GitMode::History) => LastCommits::new(&start_path),
+        _ => None,
+    };
+    let forge = if flags.forge_links { ForgeLinks::detect(&start_path) } else { None };
+    let badges = if flags.badges {
+        Some(Badges::new(&start_path, flags.badge_size_mb.unwrap_or(DEFAULT_BADGE_SIZE_MB)))
+    } else {
+        None
+    };
+    let generated = GeneratedFiles::new(&start_path);
+    lock_down();
+
+    let percent = match flags.percent.as_deref() {
+        Some("root") => Some(PercentMode::OfRoot(get_dir_size(&start_path))),
+        Some(_) => Some(PercentMode::OfParent),
+        None => None,
+    };
+
+    // Screen readers announce color escapes and box glyphs as noise
+    if flags.a11y {
+        colored::control::set_override(false);
+    }
 
-    let config = StructConfig {
+    let mut config = StructConfig {
         depth: depth_for_tree,
-        custom_ignores,
+        root: start_path.canonicalize().unwrap_or_else(|_| start_path.clone()),
+        symlink_style: SymlinkStyle::from_config(),
+        ignores,
         max_size_bytes,
         git_files,
         git_mode,
-        show_size: flags.show_size,
-        skip_defaults,
-        skip_specific,
+        show_size: flags.show_size || flags.align_sizes || percent.is_some(),
+        time: flags.time.as_deref().map(TimeStyle::from_flag),
+        align_sizes: flags.align_sizes,
+        shallow_size: flags.shallow_size,
+        percent,
+        budget: flags.budget_ms.map(std::time::Duration::from_millis),
+        show_df: flags.df,
+        quiet: flags.quiet,
+        forge,
+        badges,
+        meta_badges: flags.meta_badges,
+        group_by_ext: flags.group_by.as_deref() == Some("ext"),
+        sort: SortKey::from_flag(flags.sort.as_deref()),
+        reverse: flags.reverse,
+        filter,
+        only,
+        filter_dirs: Default::default(),
+        dir_sizes: DirSizes::default(),
+        max_lines: flags.max_lines,
+        diffstat,
+        conflicts,
+        range_changes,
+        last_commits,
+        untracked_dirs,
+        show_git_overhead: flags.git_overhead,
+        tracked_files,
+        show_ignored: flags.show_ignored,
+        generated,
+        hide_generated: flags.hide_generated,
+        anchors: flags.anchors.then(|| std::cell::RefCell::new(Anchors::default())),
+        archives: flags.archives,
+        lines: flags.lines.then(LineCounts::default),
+        external: None,
+        delta: flags.delta.then(|| SizeDelta::new(&start_path)),
+        perms: flags.perms,
+        owner: flags.owner,
+        a11y: flags.a11y,
+        deadline: flags.timeout.map(|t| std::time::Instant::now() + t),
+        timed_out: std::cell::Cell::new(false),
     };
 
-    println!("{}", start_path.display().to_string().cyan());
-    display_tree(&start_path, &config, 0, "", true);
+    // JSON of exactly what the tree would draw: git set, --where, --only and -s applied
+    if flags.output.as_deref() == Some("json") {
+        let doc = tree_document(&start_path, &tree_nodes(&start_path, &config, 0));
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap_or_default());
+        finish_trace();
+        return;
+    }
+
+    // --interactive browses the same entries: names, and sizes with -z
+    if flags.interactive {
+        let root = TreeNode {
+            name: start_path.display().to_string(),
+            path: start_path.clone(),
+            kind: NodeKind::Dir,
+            size: None,
+            ignored_files: None,
+            children: Some(tree_nodes(&start_path, &config, 0)),
+        };
+        if let Err(e) = browse(&root, flags.show_size) {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+        finish_trace();
+        return;
+    }
+
+    // One batched call per annotator, over everything the tree is about to draw
+    if let Some(ref annotators) = annotators {
+        let listed = listed_entries(&start_path, &config, 0);
+        config.external = Some(ExternalAnnotations::collect(annotators, &start_path, &listed));
+    }
+
+    if flags.long {
+        display_long(&start_path, &start_path, &config, 0);
+    } else {
+        if !flags.quiet {
+            let columns = meta_columns(&start_path, &config).unwrap_or_default();
+            println!("{}{}", columns, start_path.display().to_string().cyan());
+        }
+        display_tree(&start_path, &config, 0, "", true);
+    }
+    finish_trace();
+
+    if flags.attest {
+        let listed = listed_entries(&start_path, &config, 0);
+        Attestation::new(&start_path, &listed, attest_params(args, depth_for_tree)).print_footer();
+    }
+
+    if let (Some(ref delta), false) = (&config.delta, sandbox) {
+        if let Err(e) = delta.save() {
+            eprintln!("warning: could not save sizes for --delta: {}", e);
+        }
+    }
+
+    if let (Some(ref anchors), false) = (&config.anchors, sandbox) {
+        if let Err(e) = anchors.borrow().save() {
+            eprintln!("warning: could not save anchors for struct goto: {}", e);
+        }
+    }
+
+    if config.timed_out.get() {
+        eprintln!(
+            "{}",
+            trf(
+                "warning: stopped after --timeout {} — output is partial (marked truncated)",
+                &[&format!("{:?}", flags.timeout.unwrap_or_default())]
+            )
+            .yellow()
+        );
+    }
+
+    if flags.timings {
+        let counts = tally_stats();
+        eprintln!(
+            "{}",
+            format!(
+                "timings: total {}ms · dir counts {}ms ({} walked, {} cache hit(s))",
+                started.elapsed().as_millis(),
+                counts.walk_time.as_millis(),
+                format_count(counts.walks),
+                format_count(counts.hits)
+            )
+            .bright_black()
+        );
+    }
 }
\ No newline at end of file
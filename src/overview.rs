@@ -0,0 +1,165 @@
+use colored::*;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::theme::paint_dir;
+use crate::utils::{format_count, format_size, skip_virtual, unreadable_note};
+
+/// Width of the longest usage bar, in terminal cells
+pub const BAR_WIDTH: usize = 30;
+
+/// One row of the overview: a top-level dir, or all loose top-level files together
+struct Usage {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    files: usize,
+    /// Entries that couldn't be read: size and files are lower bounds
+    unreadable: usize,
+}
+
+/// Walk filter for usage totals: leave out the dirs the tree would hide
+pub fn visible_dir(entry: &walkdir::DirEntry, ignores: &IgnoreRules) -> bool {
+    entry.depth() == 0
+        || !entry.file_type().is_dir()
+        || !(ignores.ignores_dir(&entry.file_name().to_string_lossy())
+            || ignores.is_project_ignored(entry.path())
+            || skip_virtual(entry.path()).is_some())
+}
+
+/// Visible bytes and files under `dir`, skipping what the tree would hide,
+/// and how many entries couldn't be read
+pub fn dir_usage(dir: &Path, ignores: &IgnoreRules) -> (u64, usize, usize) {
+    let mut size = 0;
+    let mut files = 0;
+    let mut unreadable = 0;
+    for entry in WalkDir::new(dir).follow_links(false).into_iter().filter_entry(|e| visible_dir(e, ignores)) {
+        let Ok(entry) = entry else {
+            unreadable += 1;
+            continue;
+        };
+        if entry.file_type().is_file()
+            && !ignores.ignores_file(&entry.file_name().to_string_lossy())
+            && !ignores.is_project_ignored(entry.path())
+        {
+            files += 1;
+            match entry.metadata() {
+                Ok(m) => size += m.len(),
+                Err(_) => unreadable += 1,
+            }
+        }
+    }
+    (size, files, unreadable)
+}
+
+/// Horizontal bar for `size` scaled so `largest` fills BAR_WIDTH (eighth-cell steps)
+pub fn usage_bar(size: u64, largest: u64) -> String {
+    const PARTS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    if largest == 0 {
+        return String::new();
+    }
+    let eighths = (size as u128 * (BAR_WIDTH * 8) as u128 / largest as u128) as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(PARTS[eighths % 8]);
+    } else if eighths == 0 && size > 0 {
+        bar.push('▏'); // never let a non-empty dir look empty
+    }
+    bar
+}
+
+/// `struct overview [PATH]`: each top-level directory's size and file count
+/// with a bar scaled to the largest — `du -sh * | sort -h` that honours the ignores
+pub fn display_overview(path: &Path, ignores: &IgnoreRules, json_out: bool) {
+    let entries: Vec<_> = match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
+        Err(e) => {
+            eprintln!("error: cannot read {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut rows: Vec<Usage> = Vec::new();
+    let mut loose = Usage { name: "(files)".to_string(), is_dir: false, size: 0, files: 0, unreadable: 0 };
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else { continue };
+        if ignores.is_project_ignored(&entry.path()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if ignores.ignores_dir(&name) || skip_virtual(&entry.path()).is_some() {
+                continue;
+            }
+            let (size, files, unreadable) = dir_usage(&entry.path(), ignores);
+            rows.push(Usage { name, is_dir: true, size, files, unreadable });
+        } else if file_type.is_file() && !ignores.ignores_file(&name) {
+            loose.files += 1;
+            match entry.metadata() {
+                Ok(m) => loose.size += m.len(),
+                Err(_) => loose.unreadable += 1,
+            }
+        }
+    }
+    if loose.files > 0 {
+        rows.push(loose);
+    }
+    rows.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let total: u64 = rows.iter().map(|r| r.size).sum();
+    let largest = rows.first().map(|r| r.size).unwrap_or(0);
+
+    if json_out {
+        let items: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|r| {
+                json!({
+                    "name": r.name,
+                    "type": if r.is_dir { "dir" } else { "files" },
+                    "size": r.size,
+                    "files": r.files,
+                    "unreadable": r.unreadable,
+                })
+            })
+            .collect();
+        let out = json!({ "path": path.display().to_string(), "size": total, "entries": items });
+        println!("{}", out);
+        return;
+    }
+
+    let dirs = rows.iter().filter(|r| r.is_dir).count();
+    println!(
+        "{} {}",
+        path.display().to_string().cyan().bold(),
+        format!("({} in {} dirs)", format_size(total), format_count(dirs)).bright_black()
+    );
+    if rows.is_empty() {
+        return;
+    }
+
+    let labels: Vec<String> = rows.iter().map(|r| if r.is_dir { format!("{}/", r.name) } else { r.name.clone() }).collect();
+    let name_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let sizes: Vec<String> = rows.iter().map(|r| format_size(r.size)).collect();
+    let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    for ((row, label), size) in rows.iter().zip(&labels).zip(&sizes) {
+        let pad = " ".repeat(name_width - label.chars().count());
+        let name = if row.is_dir { paint_dir(label) } else { label.bright_black() };
+        println!(
+            "  {}{}  {:>size_width$}  {:<bar_width$}  {}",
+            name,
+            pad,
+            size,
+            usage_bar(row.size, largest).green(),
+            match unreadable_note(row.unreadable) {
+                Some(missed) => format!("{} files, {}", format_count(row.files), missed).yellow(),
+                None => format!("{} files", format_count(row.files)).bright_black(),
+            },
+            size_width = size_width,
+            bar_width = BAR_WIDTH
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use colored::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::copy::{absolute, plan_copy, GitIgnores, Item, Kind};
+use crate::guard::check_destructive;
+use crate::ignores::IgnoreRules;
+use crate::utils::{format_count, format_size};
+
+/// Archive formats, picked from the output file's extension
+#[derive(Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Format::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Format::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct PackOptions {
+    /// 0 (store) to 9 (smallest); None for the format's default
+    pub level: Option<u32>,
+    /// Also leave out what the repository's .gitignore files ignore
+    pub gitignore: bool,
+    /// Replace OUT when it already exists (subject to the guardrails)
+    pub force: bool,
+}
+
+/// Unix mode bits to record for an entry (0644 / 0755 where they can't be read)
+fn mode_of(path: &Path, kind: &Kind) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(m) = fs::symlink_metadata(path) {
+            return m.permissions().mode() & 0o7777;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    if *kind == Kind::Dir { 0o755 } else { 0o644 }
+}
+
+/// Name of an entry inside the archive: `<top>/<rel>` with forward slashes
+fn archive_name(top: &str, rel: &Path) -> String {
+    let parts: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    format!("{}/{}", top, parts.join("/"))
+}
+
+/// A file's mtime in zip's DOS format (local time, 2-second steps, 1980–2107)
+fn zip_time(path: &Path) -> Option<zip::DateTime> {
+    use chrono::{DateTime, Datelike, Local, Timelike};
+    let modified: DateTime<Local> = fs::symlink_metadata(path).ok()?.modified().ok()?.into();
+    zip::DateTime::from_date_and_time(
+        u16::try_from(modified.year()).ok()?,
+        modified.month() as u8,
+        modified.day() as u8,
+        modified.hour() as u8,
+        modified.minute() as u8,
+        modified.second() as u8,
+    )
+    .ok()
+}
+
+fn write_zip(out: &Path, src: &Path, top: &str, items: &[Item], level: Option<u32>) -> zip::result::ZipResult<()> {
+    use zip::write::SimpleFileOptions;
+
+    let mut writer = zip::ZipWriter::new(fs::File::create(out)?);
+    let base = match level {
+        Some(0) => SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored),
+        Some(l) => SimpleFileOptions::default().compression_level(Some(l as i64)),
+        None => SimpleFileOptions::default(),
+    };
+    let mut top_options = base.unix_permissions(0o755);
+    if let Some(stamp) = zip_time(src) {
+        top_options = top_options.last_modified_time(stamp);
+    }
+    writer.add_directory(format!("{}/", top), top_options)?;
+    for item in items {
+        let path = src.join(&item.rel);
+        let name = archive_name(top, &item.rel);
+        let mut options = base.unix_permissions(mode_of(&path, &item.kind));
+        if let Some(stamp) = zip_time(&path) {
+            options = options.last_modified_time(stamp);
+        }
+        match item.kind {
+            Kind::Dir => writer.add_directory(format!("{}/", name), options)?,
+            Kind::Symlink => {
+                let target = fs::read_link(&path)?;
+                writer.add_symlink(name, target.to_string_lossy(), options)?;
+            }
+            Kind::File => {
+                writer.start_file(name, options)?;
+                writer.write_all(&fs::read(&path)?)?;
+            }
+        }
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_tar<W: Write>(sink: W, src: &Path, top: &str, items: &[Item]) -> std::io::Result<W> {
+    let mut builder = tar::Builder::new(sink);
+    builder.follow_symlinks(false);
+    builder.append_dir(top, src)?;
+    for item in items {
+        builder.append_path_with_name(src.join(&item.rel), archive_name(top, &item.rel))?;
+    }
+    builder.into_inner()
+}
+
+/// `struct pack OUT [PATH]`: archive what the tree shows of PATH — no built-in
+/// ignores, config or `-i` matches, nor .gitignored files — into a zip, tar or
+/// tar.gz picked from OUT's extension. Everything goes under one top-level
+/// folder named after PATH. Returns false after printing an error.
+pub fn pack_tree(out: &Path, src: &Path, ignores: &IgnoreRules, opts: &PackOptions) -> bool {
+    let Some(format) = Format::from_path(out) else {
+        eprintln!("error: can't tell the archive format of {} (use .zip, .tar, .tar.gz or .tgz)", out.display());
+        return false;
+    };
+    if !src.is_dir() {
+        eprintln!("error: {} is not a directory", src.display());
+        return false;
+    }
+    if opts.level.is_some_and(|l| l > 9) {
+        eprintln!("error: --level takes 0 (store) to 9 (smallest)");
+        return false;
+    }
+    if fs::symlink_metadata(out).is_ok() {
+        if out.is_dir() {
+            eprintln!("error: {} is a directory", out.display());
+            return false;
+        }
+        if let Err(e) = check_destructive(out, "overwrite", opts.force) {
+            eprintln!("error: {}", e);
+            return false;
+        }
+    }
+
+    let src_abs = src.canonicalize().unwrap_or_else(|_| src.to_path_buf());
+    let top = src_abs.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "root".to_string());
+    // An archive written inside PATH must not end up in itself
+    let out_abs: PathBuf = absolute(out);
+    let git = if opts.gitignore { GitIgnores::new(src) } else { None };
+    let (items, skipped) = plan_copy(src, Some(&out_abs), ignores, git.as_ref());
+
+    let result = match format {
+        Format::Zip => write_zip(out, src, &top, &items, opts.level).map_err(|e| e.to_string()),
+        Format::Tar => fs::File::create(out)
+            .and_then(|f| write_tar(f, src, &top, &items))
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Format::TarGz => fs::File::create(out)
+            .and_then(|f| {
+                let level = opts.level.map(Compression::new).unwrap_or_default();
+                write_tar(GzEncoder::new(f, level), src, &top, &items)?.finish()
+            })
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("error: cannot write {}: {}", out.display(), e);
+        let _ = fs::remove_file(out);
+        return false;
+    }
+
+    let files = items.iter().filter(|i| i.kind != Kind::Dir).count();
+    let bytes: u64 = items.iter().map(|i| i.size).sum();
+    let packed = fs::metadata(out).map(|m| m.len()).unwrap_or(0);
+    println!("{} {} → {}", "packed".green(), src.display(), out.display());
+    println!(
+        "{}",
+        format!(
+            "{} files, {} → {} · {} ignored entries left out",
+            format_count(files),
+            format_size(bytes),
+            format_size(packed),
+            format_count(skipped)
+        )
+        .bright_black()
+    );
+    true
+}
@@ -0,0 +1,372 @@
+use colored::*;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ignores::IgnoreRules;
+use crate::overview::dir_usage;
+use crate::utils::format_size;
+
+/// Which workspace format the members came from
+#[derive(Clone, Copy)]
+enum Workspace {
+    Cargo,
+    Node,
+    Go,
+}
+
+impl Workspace {
+    fn label(self) -> &'static str {
+        match self {
+            Workspace::Cargo => "cargo workspace",
+            Workspace::Node => "npm/pnpm/yarn workspace",
+            Workspace::Go => "go.work",
+        }
+    }
+}
+
+/// One workspace member and the names of the other members it depends on
+struct Package {
+    name: String,
+    dir: PathBuf,
+    deps: BTreeSet<String>,
+    size: u64,
+}
+
+// ─── Manifest parsing ─────────────────────────────────────────────────────────
+
+/// Strings in a `["a", "b/*"]` list, which may span several lines
+fn quoted_list(text: &str) -> Vec<String> {
+    let Some(start) = text.find('[') else { return Vec::new() };
+    let Some(end) = text[start..].find(']') else { return Vec::new() };
+    text[start + 1..start + end]
+        .split(',')
+        .map(|s| s.trim().trim_matches('"').trim_matches('\'').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// `key = "value"` inside the `[section]` of a TOML file
+fn toml_string(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut current = String::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            current = line.trim_matches(['[', ']']).trim().to_string();
+        } else if current == section {
+            if let Some((k, v)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Some(v.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Crate names listed under any dependencies table of a Cargo.toml
+fn cargo_deps(text: &str) -> BTreeSet<String> {
+    const TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut deps = BTreeSet::new();
+    let mut in_deps = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            let header = line.trim_matches(['[', ']']).trim();
+            // `[dependencies.foo]` names the crate in the header itself
+            let (table, dep) = header.rsplit_once('.').unwrap_or((header, ""));
+            if TABLES.iter().any(|t| table == *t || table.ends_with(&format!(".{}", t))) && !dep.is_empty() {
+                deps.insert(dep.to_string());
+                in_deps = false;
+            } else {
+                in_deps = TABLES.iter().any(|t| header == *t || header.ends_with(&format!(".{}", t)));
+            }
+        } else if in_deps {
+            if let Some((name, _)) = line.split_once('=') {
+                let name = name.trim().trim_matches('"');
+                if !name.is_empty() && !name.starts_with('#') {
+                    deps.insert(name.to_string());
+                }
+            }
+        }
+    }
+    deps
+}
+
+/// `members = [...]` from the `[workspace]` table of a Cargo.toml
+fn cargo_members(text: &str) -> Option<Vec<String>> {
+    let start = text.find("[workspace]")?;
+    let section = &text[start + "[workspace]".len()..];
+    let section = match section.find("\n[") {
+        Some(end) => &section[..end],
+        None => section,
+    };
+    let members = section.find("members")?;
+    Some(quoted_list(&section[members..]))
+}
+
+/// Workspace globs from package.json (`workspaces`) or pnpm-workspace.yaml (`packages:`)
+fn node_members(root: &Path) -> Option<Vec<String>> {
+    if let Ok(text) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        let mut members = Vec::new();
+        let mut in_packages = false;
+        for line in text.lines() {
+            if !line.starts_with([' ', '\t', '-']) {
+                in_packages = line.trim_end() == "packages:";
+                continue;
+            }
+            if let Some(item) = line.trim().strip_prefix('-').filter(|_| in_packages) {
+                members.push(item.trim().trim_matches(['"', '\'']).to_string());
+            }
+        }
+        return Some(members);
+    }
+
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(root.join("package.json")).ok()?).ok()?;
+    let workspaces = manifest.get("workspaces")?;
+    // Either a plain list or yarn's { "packages": [...] }
+    let list = workspaces.as_array().or_else(|| workspaces.get("packages")?.as_array())?;
+    Some(list.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+}
+
+/// `use ./a` lines and `use ( ... )` blocks of a go.work file
+fn go_members(text: &str) -> Vec<String> {
+    let mut members = Vec::new();
+    let mut in_block = false;
+    for line in text.lines().map(|l| l.split("//").next().unwrap_or("").trim()) {
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                members.push(line.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if !rest.is_empty() {
+                members.push(rest.to_string());
+            }
+        }
+    }
+    members
+}
+
+/// Module paths a go.mod requires (single lines and `require ( ... )` blocks)
+fn go_requires(text: &str) -> BTreeSet<String> {
+    let mut deps = BTreeSet::new();
+    let mut in_block = false;
+    for line in text.lines().map(|l| l.split("//").next().unwrap_or("").trim()) {
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if let Some(module) = line.split_whitespace().next() {
+                deps.insert(module.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("require") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if let Some(module) = rest.split_whitespace().next() {
+                deps.insert(module.to_string());
+            }
+        }
+    }
+    deps
+}
+
+/// Directories matching a member glob like `crates/*` (`**` is treated like `*`;
+/// `!negated` globs are dropped by the caller)
+fn expand_member_glob(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for segment in pattern.trim_start_matches("./").split('/').filter(|s| !s.is_empty() && *s != ".") {
+        if !segment.contains('*') {
+            dirs = dirs.into_iter().map(|d| d.join(segment)).filter(|d| d.is_dir()).collect();
+            continue;
+        }
+        let escaped = regex::escape(&segment.replace("**", "*")).replace(r"\*", ".*");
+        let Ok(re) = Regex::new(&format!("^{}$", escaped)) else { return Vec::new() };
+        dirs = dirs
+            .iter()
+            .filter_map(|d| fs::read_dir(d).ok())
+            .flat_map(|rd| rd.filter_map(|e| e.ok()))
+            .filter(|e| e.path().is_dir() && re.is_match(&e.file_name().to_string_lossy()))
+            .map(|e| e.path())
+            .collect();
+    }
+    dirs.sort();
+    dirs
+}
+
+/// Find the workspace at `root` and read every member's manifest. Dependencies
+/// are kept only when they point at another member.
+fn load_workspace(root: &Path, ignores: &IgnoreRules) -> Option<(Workspace, Vec<Package>)> {
+    let member_dirs = |globs: Vec<String>, manifest: &str| -> Vec<PathBuf> {
+        let excluded: Vec<PathBuf> = globs
+            .iter()
+            .filter_map(|g| g.strip_prefix('!'))
+            .flat_map(|g| expand_member_glob(root, g))
+            .collect();
+        let mut dirs: Vec<PathBuf> = globs
+            .iter()
+            .filter(|g| !g.starts_with('!'))
+            .flat_map(|g| expand_member_glob(root, g))
+            .filter(|d| d.join(manifest).is_file() && !excluded.contains(d))
+            .collect();
+        dirs.dedup();
+        dirs
+    };
+
+    let (kind, raw): (Workspace, Vec<(String, PathBuf, BTreeSet<String>)>) =
+        if let Some(globs) = fs::read_to_string(root.join("Cargo.toml")).ok().and_then(|t| cargo_members(&t)) {
+            let raw = member_dirs(globs, "Cargo.toml")
+                .into_iter()
+                .filter_map(|dir| {
+                    let text = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+                    Some((toml_string(&text, "package", "name")?, dir, cargo_deps(&text)))
+                })
+                .collect();
+            (Workspace::Cargo, raw)
+        } else if let Some(globs) = node_members(root) {
+            let raw = member_dirs(globs, "package.json")
+                .into_iter()
+                .filter_map(|dir| {
+                    let text = fs::read_to_string(dir.join("package.json")).ok()?;
+                    let manifest: Value = serde_json::from_str(&text).ok()?;
+                    let name = manifest.get("name")?.as_str()?.to_string();
+                    let deps = ["dependencies", "devDependencies", "peerDependencies"]
+                        .iter()
+                        .filter_map(|k| manifest.get(k)?.as_object())
+                        .flat_map(|o| o.keys().cloned())
+                        .collect();
+                    Some((name, dir, deps))
+                })
+                .collect();
+            (Workspace::Node, raw)
+        } else if let Ok(text) = fs::read_to_string(root.join("go.work")) {
+            let raw = member_dirs(go_members(&text), "go.mod")
+                .into_iter()
+                .filter_map(|dir| {
+                    let text = fs::read_to_string(dir.join("go.mod")).ok()?;
+                    let name = text.lines().find_map(|l| l.trim().strip_prefix("module "))?.trim().to_string();
+                    Some((name, dir, go_requires(&text)))
+                })
+                .collect();
+            (Workspace::Go, raw)
+        } else {
+            return None;
+        };
+
+    let names: HashSet<String> = raw.iter().map(|(name, _, _)| name.clone()).collect();
+    let packages = raw
+        .into_iter()
+        .map(|(name, dir, deps)| Package {
+            deps: deps.into_iter().filter(|d| names.contains(d) && *d != name).collect(),
+            size: dir_usage(&dir, ignores).0,
+            name,
+            dir,
+        })
+        .collect();
+    Some((kind, packages))
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+fn print_package(
+    name: &str,
+    by_name: &BTreeMap<&str, &Package>,
+    root: &Path,
+    prefix: &str,
+    connector: &str,
+    ancestors: &mut Vec<String>,
+    printed: &mut HashSet<String>,
+) {
+    let Some(pkg) = by_name.get(name) else { return };
+    let rel = pkg.dir.strip_prefix(root).unwrap_or(&pkg.dir).display().to_string();
+
+    if ancestors.iter().any(|a| a == name) {
+        println!("{}{}{} {}", prefix, connector, name.red(), "(cycle)".red());
+        return;
+    }
+    let repeat = !printed.insert(name.to_string()) && !pkg.deps.is_empty();
+    println!(
+        "{}{}{} {}{}",
+        prefix,
+        connector,
+        name.cyan().bold(),
+        format!("{}/ · {}", rel, format_size(pkg.size)).bright_black(),
+        if repeat { " (*)".bright_black().to_string() } else { String::new() }
+    );
+    if repeat {
+        return; // already expanded above
+    }
+
+    let child_prefix = match connector {
+        "" => prefix.to_string(),
+        "└── " => format!("{}    ", prefix),
+        _ => format!("{}│   ", prefix),
+    };
+    ancestors.push(name.to_string());
+    let deps: Vec<&String> = pkg.deps.iter().collect();
+    for (i, dep) in deps.iter().enumerate() {
+        let connector = if i == deps.len() - 1 { "└── " } else { "├── " };
+        print_package(dep, by_name, root, &child_prefix, connector, ancestors, printed);
+    }
+    ancestors.pop();
+}
+
+/// Graphviz DOT: one node per package labeled with path and size, one edge per dependency
+fn print_dot(packages: &[Package], root: &Path) {
+    println!("digraph packages {{");
+    println!("  rankdir=LR;");
+    println!("  node [shape=box];");
+    for pkg in packages {
+        let rel = pkg.dir.strip_prefix(root).unwrap_or(&pkg.dir).display().to_string();
+        println!("  {:?} [label=\"{}\\n{}/ · {}\"];", pkg.name, pkg.name, rel, format_size(pkg.size));
+    }
+    for pkg in packages {
+        for dep in &pkg.deps {
+            println!("  {:?} -> {:?};", pkg.name, dep);
+        }
+    }
+    println!("}}");
+}
+
+/// `struct packages [PATH]`: the workspace members under PATH and how they
+/// depend on each other, as an indented tree (packages nothing depends on at
+/// the top) or, with `dot`, a Graphviz graph
+pub fn display_packages(root: &Path, ignores: &IgnoreRules, dot: bool) -> bool {
+    let Some((kind, mut packages)) = load_workspace(root, ignores) else {
+        eprintln!(
+            "error: no workspace found in {} (looked for Cargo.toml [workspace], package.json workspaces, \
+             pnpm-workspace.yaml, go.work)",
+            root.display()
+        );
+        return false;
+    };
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if dot {
+        print_dot(&packages, root);
+        return true;
+    }
+
+    println!(
+        "{} {}",
+        root.display().to_string().cyan(),
+        format!("({}, {} packages)", kind.label(), packages.len()).bright_black()
+    );
+    let by_name: BTreeMap<&str, &Package> = packages.iter().map(|p| (p.name.as_str(), p)).collect();
+    let depended_on: HashSet<&str> = packages.iter().flat_map(|p| p.deps.iter().map(String::as_str)).collect();
+    let mut tops: Vec<&str> = by_name.keys().copied().filter(|n| !depended_on.contains(n)).collect();
+    if tops.is_empty() {
+        // Every package is in a cycle: start from all of them
+        tops = by_name.keys().copied().collect();
+    }
+
+    let mut printed = HashSet::new();
+    for name in tops {
+        print_package(name, &by_name, root, "", "", &mut Vec::new(), &mut printed);
+    }
+    true
+}
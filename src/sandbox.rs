@@ -0,0 +1,215 @@
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+use crate::config::get_config_path;
+
+/// `--sandbox`: from here on the process can read `root` (and struct's own
+/// config dir) and nothing else, can't write or execute anything, and can't
+/// open sockets or trace other processes. Landlock does the filesystem part,
+/// a seccomp filter the rest; both stay on until the process exits.
+///
+/// Fails rather than running unprotected: on other systems, on kernels
+/// without landlock (before 5.13, or not enabled), and on CPU architectures
+/// the seccomp filter doesn't know.
+#[cfg(target_os = "linux")]
+pub fn enter(root: &Path) -> Result<(), String> {
+    // Both need this, and it keeps setuid binaries from regaining anything
+    // SAFETY: prctl with integer arguments only
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(format!("cannot set no_new_privs: {}", std::io::Error::last_os_error()));
+    }
+    landlock::restrict(root)?;
+    seccomp::restrict()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enter(_root: &Path) -> Result<(), String> {
+    Err("--sandbox needs Linux (landlock and seccomp)".to_string())
+}
+
+/// Paths opened read-only besides the root: the config dir (ignore patterns,
+/// colors) and the local timezone for modification times
+#[cfg(target_os = "linux")]
+fn extra_read_paths() -> Vec<std::path::PathBuf> {
+    let mut paths = vec![std::path::PathBuf::from("/etc/localtime")];
+    if let Some(dir) = get_config_path().parent() {
+        paths.push(dir.to_path_buf());
+    }
+    paths
+}
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    // From <linux/landlock.h>
+    const CREATE_RULESET_VERSION: u32 = 1;
+    const RULE_PATH_BENEATH: u32 = 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Every filesystem right the kernel's landlock ABI knows, so all of
+    /// them are denied unless a rule allows them
+    fn handled_access(abi: i64) -> u64 {
+        let mut access = (1 << 13) - 1; // ABI 1: execute … make_sym
+        if abi >= 2 {
+            access |= 1 << 13; // refer
+        }
+        if abi >= 3 {
+            access |= 1 << 14; // truncate
+        }
+        if abi >= 5 {
+            access |= 1 << 15; // ioctl_dev
+        }
+        access
+    }
+
+    /// Allow reading under `path` (just the file, when it is one); paths
+    /// that don't exist are skipped
+    fn allow_read(ruleset: i32, path: &Path) -> Result<(), String> {
+        let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else { return Ok(()) };
+        // SAFETY: c_path is a valid NUL-terminated string
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Ok(());
+        }
+        let access = if path.is_dir() { ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR } else { ACCESS_FS_READ_FILE };
+        let rule = PathBeneathAttr { allowed_access: access, parent_fd: fd };
+        // SAFETY: rule outlives the call and matches the kernel's packed layout
+        let rc = unsafe {
+            libc::syscall(libc::SYS_landlock_add_rule, ruleset, RULE_PATH_BENEATH, &rule as *const PathBeneathAttr, 0)
+        };
+        let err = std::io::Error::last_os_error();
+        // SAFETY: fd came from open above
+        unsafe { libc::close(fd) };
+        if rc != 0 {
+            return Err(format!("cannot allow {} in the sandbox: {}", path.display(), err));
+        }
+        Ok(())
+    }
+
+    pub fn restrict(root: &Path) -> Result<(), String> {
+        // SAFETY: a null attr with the version flag only queries the ABI
+        let abi = unsafe {
+            libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<RulesetAttr>(), 0, CREATE_RULESET_VERSION)
+        };
+        if abi < 1 {
+            return Err("--sandbox needs landlock (Linux 5.13+ with landlock enabled)".to_string());
+        }
+        let attr = RulesetAttr { handled_access_fs: handled_access(abi) };
+        // SAFETY: attr is a valid ruleset description of the size passed
+        let ruleset = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if ruleset < 0 {
+            return Err(format!("cannot create the landlock ruleset: {}", std::io::Error::last_os_error()));
+        }
+        let ruleset = ruleset as i32;
+
+        let result = std::iter::once(root.to_path_buf())
+            .chain(super::extra_read_paths())
+            .try_for_each(|path| allow_read(ruleset, &path))
+            .and_then(|()| {
+                // SAFETY: ruleset is the fd created above
+                match unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0) } {
+                    0 => Ok(()),
+                    _ => Err(format!("cannot apply the landlock ruleset: {}", std::io::Error::last_os_error())),
+                }
+            });
+        // SAFETY: ruleset is the fd created above
+        unsafe { libc::close(ruleset) };
+        result
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod seccomp {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    use libc::{sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Syscalls that fail with EPERM: running programs, networking, poking
+    /// at other processes, and changing namespaces or mounts
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED: &[libc::c_long] = &[
+        libc::SYS_execve,
+        libc::SYS_execveat,
+        libc::SYS_socket,
+        libc::SYS_socketpair,
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+    ];
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn stmt(code: u32, k: u32) -> sock_filter {
+        sock_filter { code: code as u16, jt: 0, jf: 0, k }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter { code: code as u16, jt, jf, k }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn restrict() -> Result<(), String> {
+        let deny = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        // seccomp_data: nr at offset 0, arch at offset 4
+        let mut program = vec![
+            stmt(BPF_LD | BPF_W | BPF_ABS, 4),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD | BPF_W | BPF_ABS, 0),
+        ];
+        // x32 syscalls share the x86_64 arch value; refuse them all
+        #[cfg(target_arch = "x86_64")]
+        program.extend([jump(BPF_JMP | libc::BPF_JGE | BPF_K, 0x4000_0000, 0, 1), stmt(BPF_RET | BPF_K, deny)]);
+        for &nr in DENIED {
+            program.push(jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, 0, 1));
+            program.push(stmt(BPF_RET | BPF_K, deny));
+        }
+        program.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+
+        let prog = sock_fprog { len: program.len() as u16, filter: program.as_mut_ptr() };
+        // SAFETY: prog points at a valid filter that lives through the call
+        let rc = unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &prog as *const sock_fprog) };
+        if rc != 0 {
+            return Err(format!("cannot install the seccomp filter: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn restrict() -> Result<(), String> {
+        Err("--sandbox: no seccomp filter for this CPU architecture".to_string())
+    }
+}
@@ -3,10 +3,13 @@ use regex::Regex;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use walkdir::WalkDir;
 
-use crate::ignores::{should_ignore_dir, matches_custom_pattern};
-use crate::utils::{format_size, is_executable};
+use crate::display::DirSizes;
+use crate::ignores::IgnoreRules;
+use crate::theme::{paint_dir, paint_file};
+use crate::utils::{format_count, format_size, is_executable, skip_virtual};
 
 // ─── Match mode ───────────────────────────────────────────────────────────────
 
@@ -48,24 +51,201 @@ impl MatchMode {
 
 // ─── Public API ───────────────────────────────────────────────────────────────
 
-pub fn search_files(
+/// A single search hit: (path, is_dir, size)
+pub type Hit = (PathBuf, bool, u64);
+
+/// How `struct search` walks and prints
+pub struct SearchOptions {
+    pub max_depth: usize,
+    pub flat: bool,
+    pub quiet: bool,
+    /// Stop after this many matches in total (`--max-count`)
+    pub max_count: Option<usize>,
+    /// Print walk statistics after the results (`--stats`)
+    pub stats: bool,
+    /// Don't descend into directories bigger than this (`--skip-large`)
+    pub max_size_bytes: Option<u64>,
+}
+
+/// What a search walk looked at, for `--stats`
+#[derive(Default)]
+struct SearchStats {
+    /// Entries the walk reached (files, dirs and links, roots excluded),
+    /// whatever happened to them next; every count below is a part of it
+    scanned: usize,
+    /// Directories pruned by ignore rules (their contents are never read)
+    skipped_dirs: usize,
+    /// Files dropped by ignore rules
+    skipped_files: usize,
+    /// Directories not entered for --skip-large, with their sizes
+    skipped_large: Vec<(PathBuf, u64)>,
+}
+
+/// Search one root and return the raw hits, for callers that render them
+/// themselves (e.g. `struct serve`)
+pub fn find_matches(
     pattern: &str,
     start_path: &Path,
     max_depth: usize,
-    flat: bool,
-    custom_ignores: &[Regex],
-) {
+    ignores: &IgnoreRules,
+) -> Result<Vec<Hit>, String> {
+    let matcher = MatchMode::build(pattern)?;
+    let mut stats = SearchStats::default();
+    Ok(collect_matches(&matcher, start_path, max_depth, ignores, None, None, &mut stats))
+}
+
+/// Search one or more roots. With several roots (named roots from the
+/// config) results are grouped and labeled by the root they came from.
+///
+/// Returns the number of matches, or None when the pattern is invalid —
+/// `main` turns that into grep-style exit codes (0 found, 1 none, 2 error).
+pub fn search_files(
+    pattern: &str,
+    start_paths: &[PathBuf],
+    opts: &SearchOptions,
+    ignores: &IgnoreRules,
+) -> Option<usize> {
+    let (flat, quiet) = (opts.flat, opts.quiet);
     let matcher = match MatchMode::build(pattern) {
         Ok(m) => m,
         Err(e) => {
             eprintln!("error: {}", e);
-            return;
+            return None;
         }
     };
 
-    let mut found_count = 0;
-    let mut matching_paths: HashSet<PathBuf> = HashSet::new();
-    let mut flat_results: Vec<(PathBuf, bool, u64)> = Vec::new(); // (path, is_dir, size)
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut remaining = opts.max_count;
+    let mut results: Vec<(&PathBuf, Vec<Hit>)> = Vec::new();
+    for root in start_paths {
+        if remaining == Some(0) {
+            break;
+        }
+        let hits = collect_matches(&matcher, root, opts.max_depth, ignores, remaining, opts.max_size_bytes, &mut stats);
+        remaining = remaining.map(|r| r - hits.len());
+        results.push((root, hits));
+    }
+    let elapsed = start.elapsed();
+    let found_count: usize = results.iter().map(|(_, hits)| hits.len()).sum();
+
+    if found_count == 0 {
+        if !quiet {
+            println!(
+                "{}",
+                format!("no files or directories matching '{}' found", pattern).yellow()
+            );
+        }
+    } else {
+        print_hits(pattern, start_paths, results, flat, quiet);
+    }
+
+    // stderr like the tree's warnings, so flat output stays pipeable
+    for (dir, size) in &stats.skipped_large {
+        eprintln!(
+            "{}",
+            format!("note: skipped {}/ ({}, larger than --skip-large)", dir.display(), format_size(*size)).bright_black()
+        );
+    }
+
+    if opts.stats {
+        // stderr, so `-f --stats | xargs ...` still gets clean paths
+        eprintln!();
+        eprintln!("{:>9} entries scanned", format_count(stats.scanned));
+        eprintln!(
+            "{:>9} skipped by ignores ({} dirs pruned, {} files)",
+            format_count(stats.skipped_dirs + stats.skipped_files),
+            format_count(stats.skipped_dirs),
+            format_count(stats.skipped_files)
+        );
+        eprintln!("{:>9} matched", format_count(found_count));
+        eprintln!("{:>9} elapsed", format!("{:.3}s", elapsed.as_secs_f64()));
+    }
+    Some(found_count)
+}
+
+/// Print the banner and the hits, grouped per root when there are several
+fn print_hits(
+    pattern: &str,
+    start_paths: &[PathBuf],
+    results: Vec<(&PathBuf, Vec<Hit>)>,
+    flat: bool,
+    quiet: bool,
+) {
+    let found_count: usize = results.iter().map(|(_, hits)| hits.len()).sum();
+
+    if quiet {
+        // Results only — no banner
+    } else if start_paths.len() > 1 {
+        println!(
+            "{} {} {}",
+            format!("found {} item(s) matching", format_count(found_count)).green(),
+            pattern.cyan(),
+            format!("across {} roots", start_paths.len()).green()
+        );
+    } else {
+        println!(
+            "{} {}",
+            format!("found {} item(s) matching", format_count(found_count)).green(),
+            pattern.cyan()
+        );
+    }
+    if !quiet {
+        println!();
+    }
+
+    let labeled = start_paths.len() > 1;
+    for (root, mut hits) in results {
+        if hits.is_empty() {
+            continue;
+        }
+        if labeled && !flat {
+            println!("{}", format!("[{}]", root.display()).bright_black());
+        }
+
+        if flat {
+            hits.sort_by(|a, b| a.0.cmp(&b.0));
+            let label = if labeled {
+                format!("[{}] ", root.display()).bright_black().to_string()
+            } else {
+                String::new()
+            };
+            for (path, is_dir, size) in hits {
+                if is_dir {
+                    println!("{}{}", label, paint_dir(&format!("{}/", path.display())));
+                } else {
+                    let size_str = format!(" ({})", format_size(size)).bright_black();
+                    let shown = paint_file(&path.display().to_string(), &path, is_executable(&path), |p| p.cyan());
+                    println!("{}{}{}", label, shown, size_str);
+                }
+            }
+        } else {
+            let matching_paths = with_ancestors(root, hits.into_iter().map(|(path, _, _)| path));
+            display_search_tree(root, &matching_paths, "", &|_, _| {});
+            if labeled {
+                println!();
+            }
+        }
+    }
+}
+
+/// Walk one root and collect every entry whose name matches, stopping once
+/// `limit` hits are found. Directories over `max_size` bytes are not entered.
+fn collect_matches(
+    matcher: &MatchMode,
+    start_path: &Path,
+    max_depth: usize,
+    ignores: &IgnoreRules,
+    limit: Option<usize>,
+    max_size: Option<u64>,
+    stats: &mut SearchStats,
+) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut visited = 0;
+    let mut pruned = 0;
+    // Sized as the walk reaches them; a dir's walk covers everything below it
+    let sizes = DirSizes::default();
+    let mut too_large: Vec<(PathBuf, u64)> = Vec::new();
 
     for entry in WalkDir::new(start_path)
         .follow_links(false)
@@ -76,6 +256,7 @@ pub fn search_files(
             if e.depth() == 0 {
                 return true;
             }
+            visited += 1;
             let name = match e.file_name().to_str() {
                 Some(n) => n,
                 None => return true,
@@ -84,11 +265,23 @@ pub fn search_files(
             // This lets `search "__pycache__"` find those dirs even though they're
             // in the default ignore list. We won't descend inside them (filter_entry
             // prunes recursion) so we just surface them as direct hits.
-            if e.file_type().is_dir() {
-                let is_ignored = should_ignore_dir(name)
-                    || matches_custom_pattern(name, custom_ignores);
-                if is_ignored {
-                    return matcher.is_match(name);
+            // Hidden dotfile dirs stay hidden (-a shows them).
+            if e.file_type().is_dir() && ignores.ignores_dir(name) {
+                if matcher.is_match(name) && !ignores.is_dotfile(name) {
+                    return true;
+                }
+                pruned += 1;
+                return false;
+            }
+            if e.file_type().is_dir() && (skip_virtual(e.path()).is_some() || ignores.is_project_ignored(e.path())) {
+                pruned += 1;
+                return false;
+            }
+            if let (Some(max), true) = (max_size, e.file_type().is_dir()) {
+                let size = sizes.get(e.path()).0;
+                if size > max {
+                    too_large.push((e.path().to_path_buf(), size));
+                    return false;
                 }
             }
             true
@@ -104,71 +297,69 @@ pub fn search_files(
             None => continue,
         };
 
-        if matcher.is_match(filename) {
-            let file_path = entry.path().to_path_buf();
-            let is_dir = entry.file_type().is_dir();
+        let is_dir = entry.file_type().is_dir();
+        // Ignored files are skipped just like in the tree view
+        if !is_dir && (ignores.ignores_file(filename) || ignores.is_project_ignored(entry.path())) {
+            stats.skipped_files += 1;
+            continue;
+        }
 
-            if flat {
-                let size = if is_dir {
-                    0
-                } else {
-                    entry.metadata().map(|m| m.len()).unwrap_or(0)
-                };
-                flat_results.push((file_path, is_dir, size));
+        if matcher.is_match(filename) {
+            let size = if is_dir {
+                0
             } else {
-                matching_paths.insert(file_path.clone());
-                // Record all ancestor dirs so the tree renders correctly
-                let mut cur = file_path.parent();
-                while let Some(parent) = cur {
-                    if parent == start_path {
-                        break;
-                    }
-                    matching_paths.insert(parent.to_path_buf());
-                    cur = parent.parent();
-                }
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+            hits.push((entry.path().to_path_buf(), is_dir, size));
+            if limit.is_some_and(|l| hits.len() >= l) {
+                break;
             }
-
-            found_count += 1;
         }
     }
 
-    if found_count == 0 {
-        println!(
-            "{}",
-            format!("no files or directories matching '{}' found", pattern).yellow()
-        );
-        return;
+    stats.scanned += visited;
+    stats.skipped_dirs += pruned;
+    // A skipped dir can still be a match itself
+    for (dir, _) in &too_large {
+        if limit.is_some_and(|l| hits.len() >= l) {
+            break;
+        }
+        if dir.file_name().is_some_and(|n| matcher.is_match(&n.to_string_lossy())) {
+            hits.push((dir.clone(), true, 0));
+        }
     }
+    stats.skipped_large.extend(too_large);
+    hits
+}
 
-    println!(
-        "{} {}",
-        format!("found {} item(s) matching", found_count).green(),
-        pattern.cyan()
-    );
-    println!();
-
-    if flat {
-        flat_results.sort_by(|a, b| a.0.cmp(&b.0));
-        for (path, is_dir, size) in flat_results {
-            if is_dir {
-                println!("{}", format!("{}/", path.display()).blue().bold());
-            } else {
-                let size_str = format!(" ({})", format_size(size)).bright_black();
-                println!("{}{}", path.display().to_string().cyan(), size_str);
+// ─── Tree display ─────────────────────────────────────────────────────────────
+
+/// The hits plus every directory between them and `root`, so the pruned
+/// tree can be drawn down to each of them
+pub fn with_ancestors(root: &Path, hits: impl IntoIterator<Item = PathBuf>) -> HashSet<PathBuf> {
+    let mut matching_paths = HashSet::new();
+    for file_path in hits {
+        let mut cur = file_path.parent();
+        while let Some(parent) = cur {
+            if parent == root {
+                break;
             }
+            matching_paths.insert(parent.to_path_buf());
+            cur = parent.parent();
         }
-    } else {
-        display_search_tree(start_path, &matching_paths, "", true);
+        matching_paths.insert(file_path);
     }
+    matching_paths
 }
 
-// ─── Tree display ─────────────────────────────────────────────────────────────
-
-fn display_search_tree(
+/// Draw the part of the tree under `path` that leads to `matching_paths`.
+/// `under_file` runs after each file's line with the prefix for lines
+/// nested beneath it (`struct grep` prints its snippets there).
+pub fn display_search_tree(
     path: &Path,
     matching_paths: &HashSet<PathBuf>,
     prefix: &str,
-    _is_last: bool,
+    under_file: &dyn Fn(&Path, &str),
 ) {
     let mut entries: Vec<_> = match fs::read_dir(path) {
         Ok(entries) => entries
@@ -203,26 +394,24 @@ fn display_search_tree(
                 "{}{}{}",
                 prefix,
                 connector,
-                format!("{}/", name).blue().bold()
+                paint_dir(&format!("{}/", name))
             );
             let new_prefix = if is_last_entry {
                 format!("{}    ", prefix)
             } else {
                 format!("{}│   ", prefix)
             };
-            display_search_tree(&entry_path, matching_paths, &new_prefix, is_last_entry);
+            display_search_tree(&entry_path, matching_paths, &new_prefix, under_file);
         } else {
-            let file_name = if is_executable(&entry_path) {
-                name.green().bold()
-            } else {
-                name.cyan().bold()
-            };
+            let file_name = paint_file(&name, &entry_path, is_executable(&entry_path), |n| n.cyan().bold());
             if let Ok(metadata) = fs::metadata(&entry_path) {
                 let size_str = format!(" ({})", format_size(metadata.len())).bright_black();
                 println!("{}{}{}{}", prefix, connector, file_name, size_str);
             } else {
                 println!("{}{}{}", prefix, connector, file_name);
             }
+            let nested = if is_last_entry { format!("{}    ", prefix) } else { format!("{}│   ", prefix) };
+            under_file(&entry_path, &nested);
         }
     }
 }
\ No newline at end of file
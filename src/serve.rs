@@ -0,0 +1,134 @@
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ignores::IgnoreRules;
+use crate::jsontree::list_entries;
+use crate::search::find_matches;
+use crate::summary::collect_dir_stats;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Builds the ignore rules for one request from its `no_ignore` / `ignore` /
+/// `all` params (`all` shows dotfiles, like `-a`) and the path it walks
+type RulesBuilder = dyn Fn(&[String], Option<&str>, bool, &Path) -> IgnoreRules;
+
+/// `struct serve --stdio`: answer newline-delimited JSON-RPC 2.0 requests on
+/// stdin, one response per line on stdout, until EOF or `shutdown`.
+///
+/// Methods:
+///   list      {path, depth?, no_ignore?, ignore?, all?}   → nested entries
+///   search    {pattern, path?, depth?, no_ignore?, ignore?, all?} → matches
+///   summarize {path, no_ignore?, ignore?, all?}           → directory stats
+///   shutdown  {}                                    → null, then exit
+pub fn serve_stdio(build_rules: &RulesBuilder) {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (response, stop) = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => handle_request(&request, build_rules),
+            Err(e) => (error_response(Value::Null, PARSE_ERROR, &e.to_string()), false),
+        };
+
+        if writeln!(stdout, "{}", response).and_then(|_| stdout.flush()).is_err() || stop {
+            break;
+        }
+    }
+}
+
+/// Returns the response and whether the server should stop afterwards
+fn handle_request(request: &Value, build_rules: &RulesBuilder) -> (Value, bool) {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(|m| m.as_str()) else {
+        return (error_response(id, INVALID_REQUEST, "missing method"), false);
+    };
+    let empty = json!({});
+    let params = request.get("params").unwrap_or(&empty);
+
+    let no_ignore: Vec<String> = params
+        .get("no_ignore")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let inline = params.get("ignore").and_then(|v| v.as_str());
+    let all = params.get("all").and_then(|v| v.as_bool()).unwrap_or(false);
+    let path = PathBuf::from(params.get("path").and_then(|v| v.as_str()).unwrap_or("."));
+    // 0 or missing depth means unlimited for search, 1 level for list
+    let depth = params.get("depth").and_then(|v| v.as_u64()).map(|d| d as usize);
+
+    let result = match method {
+        "list" => {
+            let ignores = build_rules(&no_ignore, inline, all, &path);
+            Ok(json!({
+                "path": path.display().to_string(),
+                "entries": list_entries(&path, depth.unwrap_or(1).max(1), ignores),
+            }))
+        }
+        "search" => match params.get("pattern").and_then(|v| v.as_str()) {
+            Some(pattern) => {
+                // As on the command line, a pattern starting with a dot looks at dotfiles
+                let ignores = build_rules(&no_ignore, inline, all || pattern.starts_with('.'), &path);
+                let max_depth = match depth {
+                    None | Some(0) => usize::MAX,
+                    Some(d) => d,
+                };
+                find_matches(pattern, &path, max_depth, &ignores)
+                    .map(|hits| {
+                        let matches: Vec<Value> = hits
+                            .into_iter()
+                            .map(|(p, is_dir, size)| {
+                                json!({ "path": p.display().to_string(), "is_dir": is_dir, "size": size })
+                            })
+                            .collect();
+                        json!({ "matches": matches })
+                    })
+                    .map_err(|e| (INVALID_PARAMS, e))
+            }
+            None => Err((INVALID_PARAMS, "missing pattern".to_string())),
+        },
+        "summarize" => {
+            if path.is_dir() {
+                let ignores = build_rules(&no_ignore, inline, all, &path);
+                let stats = collect_dir_stats(&path, &ignores);
+                let ignored: Vec<Value> = stats
+                    .ignored_subdirs
+                    .iter()
+                    .map(|(name, files)| json!({ "name": name, "files": files }))
+                    .collect();
+                Ok(json!({
+                    "path": path.display().to_string(),
+                    "total": { "dirs": stats.total_dirs, "files": stats.total_files, "size": stats.total_size },
+                    "visible": { "dirs": stats.visible_dirs, "files": stats.visible_files, "size": stats.visible_size },
+                    "types": stats.extensions,
+                    "ignored": ignored,
+                }))
+            } else {
+                Err((INVALID_PARAMS, format!("'{}' is not a directory", path.display())))
+            }
+        }
+        "shutdown" => {
+            return (json!({ "jsonrpc": "2.0", "id": id, "result": Value::Null }), true);
+        }
+        other => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", other))),
+    };
+
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    };
+    (response, false)
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -0,0 +1,506 @@
+use colored::*;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::attest::sha256_file;
+use crate::config::get_config_path;
+use crate::copy::{plan_copy, GitIgnores, Kind};
+use crate::i18n::{tr, trf};
+use crate::ignores::IgnoreRules;
+use crate::theme::paint_dir;
+use crate::utils::{format_count, format_size, is_plain_name, relative_time};
+
+/// First line of every snapshot file, bumped when the format changes
+const FORMAT: &str = "struct-snapshot v1";
+
+/// Directory holding one file per snapshot, next to ignores.txt
+fn snapshots_dir() -> PathBuf {
+    get_config_path().with_file_name("snapshots")
+}
+
+fn snapshot_file(name: &str) -> PathBuf {
+    snapshots_dir().join(format!("{}.txt", name))
+}
+
+/// What a snapshot records about one path
+#[derive(PartialEq)]
+enum Node {
+    Dir,
+    /// Size, mtime (unix seconds) and, with `--hash`, the sha256
+    File { size: u64, mtime: i64, hash: Option<String> },
+    /// Symlink and where it points
+    Link(String),
+}
+
+/// A saved tree: its paths (relative, `/`-separated) and how it was read, so
+/// `snapshot diff` walks the live tree with the same rules.
+///
+/// Saved as `FORMAT`, then `key<TAB>value` header lines (`root`, `taken`,
+/// `hashed`, `ignore`, `no-ignore`, `gitignore`), then one line per path:
+/// `d<TAB>path`, `f<TAB>path<TAB>size<TAB>mtime<TAB>sha256 or -`,
+/// `l<TAB>path<TAB>target`.
+pub struct Snapshot {
+    pub name: String,
+    pub root: PathBuf,
+    /// Unix seconds
+    pub taken: i64,
+    pub hashed: bool,
+    /// The `-i` patterns it was saved with
+    pub ignore: Option<String>,
+    /// The `-n` targets it was saved with
+    pub no_ignore: Vec<String>,
+    pub gitignore: bool,
+    entries: BTreeMap<String, Node>,
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Walk `root` like `struct copy` does. With `hashed`, files hash their
+/// contents, except where `previous` already has a hash for the same size
+/// and mtime, so snapshotting a big tree again only reads what changed.
+fn scan(
+    root: &Path,
+    ignores: &IgnoreRules,
+    gitignore: bool,
+    hashed: bool,
+    previous: Option<&Snapshot>,
+) -> BTreeMap<String, Node> {
+    let git = if gitignore { GitIgnores::new(root) } else { None };
+    let (items, _) = plan_copy(root, None, ignores, git.as_ref());
+    let mut entries = BTreeMap::new();
+    for item in items {
+        let rel: Vec<String> = item.rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        let rel = rel.join("/");
+        let path = root.join(&item.rel);
+        let node = match item.kind {
+            Kind::Dir => Node::Dir,
+            Kind::Symlink => {
+                Node::Link(fs::read_link(&path).map(|t| t.display().to_string()).unwrap_or_default())
+            }
+            Kind::File => {
+                let mtime = fs::symlink_metadata(&path).and_then(|m| m.modified()).map_or(0, unix_secs);
+                let hash = hashed.then(|| match previous.and_then(|p| p.entries.get(&rel)) {
+                    Some(Node::File { size, mtime: then, hash: Some(hash) }) if *size == item.size && *then == mtime => {
+                        hash.clone()
+                    }
+                    _ => sha256_file(&path).unwrap_or_else(|| "unreadable".to_string()),
+                });
+                Node::File { size: item.size, mtime, hash }
+            }
+        };
+        entries.insert(rel, node);
+    }
+    entries
+}
+
+/// Read a snapshot saved under `name`
+pub fn load_snapshot(name: &str) -> Result<Snapshot, String> {
+    let file = snapshot_file(name);
+    let text = fs::read_to_string(&file).map_err(|_| format!("no snapshot named {} (see struct snapshot list)", name))?;
+    let corrupt = || format!("{} is not a snapshot file", file.display());
+    let mut lines = text.lines();
+    if lines.next() != Some(FORMAT) {
+        return Err(corrupt());
+    }
+
+    let mut snap = Snapshot {
+        name: name.to_string(),
+        root: PathBuf::new(),
+        taken: 0,
+        hashed: false,
+        ignore: None,
+        no_ignore: Vec::new(),
+        gitignore: true,
+        entries: BTreeMap::new(),
+    };
+    for line in lines {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields.as_slice() {
+            ["root", root] => snap.root = PathBuf::from(root),
+            ["taken", secs] => snap.taken = secs.parse().map_err(|_| corrupt())?,
+            ["hashed", on] => snap.hashed = *on == "1",
+            ["ignore", patterns] => snap.ignore = Some(patterns.to_string()),
+            ["no-ignore", target] => snap.no_ignore.push(target.to_string()),
+            ["gitignore", on] => snap.gitignore = *on == "1",
+            ["d", path] => {
+                snap.entries.insert(path.to_string(), Node::Dir);
+            }
+            ["f", path, size, mtime, hash] => {
+                let node = Node::File {
+                    size: size.parse().map_err(|_| corrupt())?,
+                    mtime: mtime.parse().map_err(|_| corrupt())?,
+                    hash: (*hash != "-").then(|| hash.to_string()),
+                };
+                snap.entries.insert(path.to_string(), node);
+            }
+            ["l", path, target] => {
+                snap.entries.insert(path.to_string(), Node::Link(target.to_string()));
+            }
+            _ => return Err(corrupt()),
+        }
+    }
+    Ok(snap)
+}
+
+/// `struct snapshot save NAME [PATH]`: record PATH's tree (what `struct
+/// copy` would keep) under NAME, replacing an older snapshot of that name
+pub fn save_snapshot(
+    name: &str,
+    path: &Path,
+    hashed: bool,
+    ignore: Option<&str>,
+    no_ignore: &[String],
+    gitignore: bool,
+    ignores: &IgnoreRules,
+) -> Result<(), String> {
+    if !is_plain_name(name) {
+        return Err(format!("invalid snapshot name '{}' (use letters, digits, '-', '_' and '.')", name));
+    }
+    let root = path.canonicalize().map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+
+    // Hashes of an earlier snapshot of the same dir are reused where files look unchanged
+    let previous = load_snapshot(name).ok().filter(|p| p.root == root);
+    let entries = scan(&root, ignores, gitignore, hashed, previous.as_ref());
+
+    let mut out = format!("{}\nroot\t{}\ntaken\t{}\n", FORMAT, root.display(), unix_secs(SystemTime::now()));
+    out.push_str(&format!("hashed\t{}\ngitignore\t{}\n", hashed as u8, gitignore as u8));
+    if let Some(patterns) = ignore {
+        out.push_str(&format!("ignore\t{}\n", patterns));
+    }
+    for target in no_ignore {
+        out.push_str(&format!("no-ignore\t{}\n", target));
+    }
+    let (mut files, mut dirs, mut bytes) = (0, 0, 0);
+    for (rel, node) in &entries {
+        match node {
+            Node::Dir => {
+                dirs += 1;
+                out.push_str(&format!("d\t{}\n", rel));
+            }
+            Node::File { size, mtime, hash } => {
+                files += 1;
+                bytes += size;
+                out.push_str(&format!("f\t{}\t{}\t{}\t{}\n", rel, size, mtime, hash.as_deref().unwrap_or("-")));
+            }
+            Node::Link(target) => {
+                files += 1;
+                out.push_str(&format!("l\t{}\t{}\n", rel, target));
+            }
+        }
+    }
+
+    fs::create_dir_all(snapshots_dir())
+        .and_then(|_| fs::write(snapshot_file(name), out))
+        .map_err(|e| format!("failed to save snapshot: {}", e))?;
+    println!(
+        "{} {} {}",
+        "snapshot".bright_black(),
+        name.green(),
+        format!(
+            "· {} · {} files, {} dirs, {}{}",
+            root.display(),
+            format_count(files),
+            format_count(dirs),
+            format_size(bytes),
+            if hashed { ", hashed" } else { "" }
+        )
+        .bright_black()
+    );
+    Ok(())
+}
+
+/// How a path differs from the snapshot
+enum Change {
+    Added,
+    Deleted,
+    Resized(u64, u64),
+    /// Same size, other contents (other hash, or a newer mtime when not hashed)
+    Modified,
+    /// Same contents under another path (hashed snapshots only)
+    Renamed(String),
+}
+
+/// The changes between `old` and `new`, by path, with renames paired up:
+/// an added file with the hash and size of a deleted one
+fn changes(old: &BTreeMap<String, Node>, new: &BTreeMap<String, Node>) -> BTreeMap<String, Change> {
+    let mut out = BTreeMap::new();
+    for (path, before) in old {
+        let change = match (before, new.get(path)) {
+            (_, None) => Some(Change::Deleted),
+            (Node::File { size: a, .. }, Some(Node::File { size: b, .. })) if a != b => Some(Change::Resized(*a, *b)),
+            (Node::File { hash: Some(a), .. }, Some(Node::File { hash: Some(b), .. })) => (a != b).then_some(Change::Modified),
+            (Node::File { mtime: a, .. }, Some(Node::File { mtime: b, .. })) => (a != b).then_some(Change::Modified),
+            (before, Some(after)) => (before != after).then_some(Change::Modified),
+        };
+        if let Some(change) = change {
+            out.insert(path.clone(), change);
+        }
+    }
+    for path in new.keys().filter(|p| !old.contains_key(*p)) {
+        out.insert(path.clone(), Change::Added);
+    }
+
+    let mut deleted: HashMap<(u64, &str), Vec<&String>> = HashMap::new();
+    for (path, change) in &out {
+        if let (Change::Deleted, Some(Node::File { size, hash: Some(hash), .. })) = (change, old.get(path)) {
+            deleted.entry((*size, hash.as_str())).or_default().push(path);
+        }
+    }
+    let mut renames = Vec::new();
+    for (path, change) in &out {
+        if let (Change::Added, Some(Node::File { size, hash: Some(hash), .. })) = (change, new.get(path)) {
+            if let Some(from) = deleted.get_mut(&(*size, hash.as_str())).and_then(|d| d.pop()) {
+                renames.push((path.clone(), from.clone()));
+            }
+        }
+    }
+    for (to, from) in renames {
+        out.remove(&from);
+        out.insert(to, Change::Renamed(from));
+    }
+    out
+}
+
+/// Parent of a `/`-separated path ("" at the top)
+fn parent_of(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+/// The outermost dir above `path` that is itself `kind` (added or deleted):
+/// it is drawn instead of `path`, with a count of the files below
+fn folded_into<'a>(path: &'a str, all: &BTreeMap<String, Change>, kind: fn(&Change) -> bool) -> Option<&'a str> {
+    let mut dir = parent_of(path);
+    let mut outermost = None;
+    while !dir.is_empty() {
+        if all.get(dir).is_some_and(kind) {
+            outermost = Some(dir);
+        }
+        dir = parent_of(dir);
+    }
+    outermost
+}
+
+/// `struct snapshot diff NAME`: what was added, deleted, resized, modified or
+/// renamed under the snapshot's root since it was saved, as a tree. Added and
+/// deleted directories are shown once, with how many files they held.
+/// Returns None on error, else whether nothing changed.
+pub fn diff_snapshot(snap: &Snapshot, ignores: &IgnoreRules, json_out: bool) -> Option<bool> {
+    if !snap.root.is_dir() {
+        eprintln!("error: {} is not a directory anymore", snap.root.display());
+        return None;
+    }
+    let live = scan(&snap.root, ignores, snap.gitignore, snap.hashed, Some(snap));
+    let all = changes(&snap.entries, &live);
+    let unchanged = all.is_empty();
+
+    if json_out {
+        let paths = |want: fn(&Change) -> bool| all.iter().filter(|(_, c)| want(c)).map(|(p, _)| p).collect::<Vec<_>>();
+        let out = json!({
+            "snapshot": snap.name,
+            "root": snap.root.display().to_string(),
+            "taken": snap.taken,
+            "changed": !unchanged,
+            "added": paths(|c| matches!(c, Change::Added)),
+            "deleted": paths(|c| matches!(c, Change::Deleted)),
+            "modified": paths(|c| matches!(c, Change::Modified)),
+            "resized": all
+                .iter()
+                .filter_map(|(p, c)| match c {
+                    Change::Resized(before, after) => Some(json!({ "path": p, "before": before, "after": after })),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            "renamed": all
+                .iter()
+                .filter_map(|(p, c)| match c {
+                    Change::Renamed(from) => Some(json!({ "from": from, "to": p })),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", out);
+        return Some(unchanged);
+    }
+
+    let taken = UNIX_EPOCH + Duration::from_secs(snap.taken.max(0) as u64);
+    println!(
+        "{} {} {}",
+        snap.root.display().to_string().cyan(),
+        "vs snapshot".bright_black(),
+        format!("{} ({})", snap.name, relative_time(taken)).cyan()
+    );
+
+    let mut hidden_files: HashMap<&str, usize> = HashMap::new();
+    let mut shown: Vec<&str> = Vec::new();
+    for (path, change) in &all {
+        let fold = match change {
+            Change::Added => folded_into(path, &all, |c| matches!(c, Change::Added)),
+            Change::Deleted => folded_into(path, &all, |c| matches!(c, Change::Deleted)),
+            _ => None,
+        };
+        match fold {
+            Some(dir) => {
+                let node = if matches!(change, Change::Added) { live.get(path) } else { snap.entries.get(path) };
+                if node != Some(&Node::Dir) {
+                    *hidden_files.entry(dir).or_default() += 1;
+                }
+            }
+            None => shown.push(path),
+        }
+    }
+
+    // Unchanged parent dirs are drawn too, to place the changes
+    let mut children: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for path in &shown {
+        let mut path: &str = path;
+        loop {
+            let parent = parent_of(path);
+            let list = children.entry(parent).or_default();
+            if list.contains(&path) {
+                break;
+            }
+            list.push(path);
+            if parent.is_empty() {
+                break;
+            }
+            path = parent;
+        }
+    }
+    let is_dir = |path: &str| live.get(path).or(snap.entries.get(path)) == Some(&Node::Dir);
+    for list in children.values_mut() {
+        list.sort_by_cached_key(|p| (!is_dir(p), p.to_lowercase()));
+    }
+
+    let label = |path: &str| -> (ColoredString, Vec<ColoredString>) {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let name = if is_dir(path) { format!("{}/", name) } else { name.to_string() };
+        let files = |n: usize| format!("({})", trf("{} files", &[&format_count(n)])).bright_black();
+        match all.get(path) {
+            None => (paint_dir(&name), Vec::new()),
+            Some(Change::Added) => {
+                let mut tags = vec![format!("[{}]", tr("added")).green()];
+                match live.get(path) {
+                    Some(Node::File { size, .. }) => tags.insert(0, format!("({})", format_size(*size)).bright_black()),
+                    Some(Node::Dir) => tags.insert(0, files(hidden_files.get(path).copied().unwrap_or(0))),
+                    _ => {}
+                }
+                (name.green(), tags)
+            }
+            Some(Change::Deleted) => {
+                let mut tags = vec![format!("[{}]", tr("deleted")).red()];
+                if snap.entries.get(path) == Some(&Node::Dir) {
+                    tags.insert(0, files(hidden_files.get(path).copied().unwrap_or(0)));
+                }
+                (name.red().strikethrough(), tags)
+            }
+            Some(Change::Resized(before, after)) => {
+                let delta = if after > before {
+                    format!("+{}", format_size(after - before)).red()
+                } else {
+                    format!("-{}", format_size(before - after)).green()
+                };
+                let sizes = format!("{} → {}", format_size(*before), format_size(*after)).bright_black();
+                (name.yellow(), vec![sizes, delta])
+            }
+            Some(Change::Modified) => (name.yellow(), vec![format!("[{}]", tr("modified")).yellow()]),
+            Some(Change::Renamed(from)) => (name.cyan(), vec![format!("[{}]", trf("renamed from {}", &[&from])).cyan()]),
+        }
+    };
+
+    fn draw(
+        dir: &str,
+        prefix: &str,
+        children: &BTreeMap<&str, Vec<&str>>,
+        label: &dyn Fn(&str) -> (ColoredString, Vec<ColoredString>),
+    ) {
+        let Some(list) = children.get(dir) else { return };
+        for (i, path) in list.iter().enumerate() {
+            let last = i == list.len() - 1;
+            let (name, tags) = label(path);
+            let tags: String = tags.iter().map(|t| format!(" {}", t)).collect();
+            println!("{}{}{}{}", prefix, if last { "└── " } else { "├── " }, name, tags);
+            draw(path, &format!("{}{}", prefix, if last { "    " } else { "│   " }), children, label);
+        }
+    }
+    draw("", "", &children, &label);
+
+    let count = |want: fn(&Change) -> bool| format_count(all.values().filter(|c| want(c)).count());
+    let summary = format!(
+        "{} added, {} deleted, {} resized, {} modified, {} renamed",
+        count(|c| matches!(c, Change::Added)),
+        count(|c| matches!(c, Change::Deleted)),
+        count(|c| matches!(c, Change::Resized(..))),
+        count(|c| matches!(c, Change::Modified)),
+        count(|c| matches!(c, Change::Renamed(_)))
+    );
+    if unchanged {
+        println!("  {} {}", "unchanged".green(), format!("{} paths compared", format_count(live.len())).bright_black());
+    } else {
+        println!("\n{}", summary.bright_black());
+    }
+    Some(unchanged)
+}
+
+/// `struct snapshot list`
+pub fn list_snapshots(json_out: bool) {
+    let mut names: Vec<String> = fs::read_dir(snapshots_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_string_lossy().strip_suffix(".txt").map(str::to_string))
+        .collect();
+    names.sort();
+    let snapshots: Vec<Snapshot> = names.iter().filter_map(|n| load_snapshot(n).ok()).collect();
+
+    if json_out {
+        let items: Vec<serde_json::Value> = snapshots
+            .iter()
+            .map(|s| {
+                json!({
+                    "name": s.name,
+                    "root": s.root.display().to_string(),
+                    "taken": s.taken,
+                    "hashed": s.hashed,
+                    "paths": s.entries.len(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&items).unwrap_or_default());
+        return;
+    }
+    if snapshots.is_empty() {
+        println!("no snapshots yet");
+        println!("take one with: struct snapshot save NAME [PATH]");
+        return;
+    }
+    let width = snapshots.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
+    for s in &snapshots {
+        let taken = UNIX_EPOCH + Duration::from_secs(s.taken.max(0) as u64);
+        let details = format!(
+            "{} · {} paths{}",
+            relative_time(taken),
+            format_count(s.entries.len()),
+            if s.hashed { ", hashed" } else { "" }
+        );
+        println!("  {:<width$}  {}  {}", s.name.green(), s.root.display().to_string().cyan(), details.bright_black(), width = width);
+    }
+    println!("\nsnapshots dir: {}", snapshots_dir().display().to_string().bright_black());
+}
+
+/// `struct snapshot rm NAME`; false (after a message) when there's no such snapshot
+pub fn remove_snapshot(name: &str) -> bool {
+    if !is_plain_name(name) || fs::remove_file(snapshot_file(name)).is_err() {
+        eprintln!("error: no snapshot named {}", name.yellow());
+        return false;
+    }
+    println!("snapshot {} removed", name.green());
+    true
+}
@@ -1,17 +1,23 @@
+use chrono::{DateTime, Local};
 use colored::*;
 use git2::Repository;
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::config::load_config_patterns;
-use crate::ignores::{should_ignore_dir, should_ignore_file, matches_custom_pattern};
-use crate::utils::{format_size, get_dir_size, is_executable};
+use crate::i18n::{tr, trf};
+use crate::ignores::IgnoreRules;
+use crate::theme::paint_dir;
+use crate::utils::{
+    count_lines, exceeds_size, format_count, format_permissions, format_size, is_executable, keep_offline, parallel_map,
+    skip_virtual, tally_dir, unreadable_note, DirTally,
+};
 
-/// Display detailed summary of current directory (struct 0 mode)
-pub fn display_summary(path: &Path) {
+/// Display detailed summary of current directory (struct 0 mode). Directories
+/// over `max_size` bytes (`-s`) get a one-line "skipped" card instead of stats.
+pub fn display_summary(path: &Path, ignores: &IgnoreRules, quiet: bool, max_size: Option<u64>) {
     // Get absolute path
     let abs_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
     
@@ -20,12 +26,14 @@ pub fn display_summary(path: &Path) {
     if let Ok(repo) = Repository::discover(path) {
         if let Ok(head) = repo.head() {
             if let Some(branch) = head.shorthand() {
-                header = format!("{} {}", abs_path.display(), format!("({})", branch).bright_black().to_string());
+                header = format!("{} {}", abs_path.display(), format!("({})", branch).bright_black());
             }
         }
     }
-    println!("{}", header.cyan().bold());
-    println!();
+    if !quiet {
+        println!("{}", header.cyan().bold());
+        println!();
+    }
 
     let entries: Vec<_> = match fs::read_dir(path) {
         Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
@@ -35,152 +43,183 @@ pub fn display_summary(path: &Path) {
         }
     };
 
-    // Load config patterns for filtering
-    let config_patterns = load_config_patterns();
-    let mut custom_ignores = Vec::new();
-    for pattern in config_patterns {
-        let pattern = pattern.replace("*", ".*");
-        if let Ok(re) = Regex::new(&format!("^{}$", pattern)) {
-            custom_ignores.push(re);
+    // Walking the top-level dirs is the slow part: do it for all of them at
+    // once, then print in directory order
+    let counts = parallel_map(&entries, |entry| {
+        let entry_path = entry.path();
+        if !entry_path.is_dir() {
+            return DirCount::None;
         }
-    }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(kind) = skip_virtual(&entry_path) {
+            DirCount::Virtual(kind)
+        } else if ignores.ignores_dir(&name) || ignores.is_project_ignored(&entry_path) {
+            DirCount::Ignored(tally_dir(&entry_path, None))
+        } else if let Some(max) = max_size.filter(|&max| exceeds_size(&entry_path, max)) {
+            DirCount::TooLarge(max)
+        } else {
+            DirCount::Visible(collect_dir_stats(&entry_path, ignores))
+        }
+    });
 
     let mut total_ignored_files = 0;
     let mut total_ignored_size = 0u64;
+    let mut total_unreadable = 0;
     let mut ignored_names = Vec::new();
 
-    for entry in entries {
+    for (entry, count) in entries.iter().zip(counts) {
         let entry_path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
-        let is_dir = entry_path.is_dir();
-
-        // Check if should be ignored
-        let should_skip = if is_dir {
-            should_ignore_dir(&name) || matches_custom_pattern(&name, &custom_ignores)
-        } else {
-            should_ignore_file(&name) || matches_custom_pattern(&name, &custom_ignores)
-        };
 
-        if should_skip {
-            // Track ignored items
-            if is_dir {
-                let file_count = WalkDir::new(&entry_path)
-                    .follow_links(false)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                    .filter(|e| e.file_type().is_file())
-                    .count();
-                let size = get_dir_size(&entry_path);
-                total_ignored_files += file_count;
-                total_ignored_size += size;
-                ignored_names.push(format!("{}({} files)", name, file_count));
-            } else {
-                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        match count {
+            DirCount::Ignored(tally) => {
+                total_ignored_files += tally.files;
+                total_ignored_size += tally.bytes;
+                total_unreadable += tally.unreadable;
+                ignored_names.push(trf("{}({} files)", &[&name, &format_count(tally.files)]));
+            }
+            DirCount::TooLarge(max) => {
+                println!("{}", paint_dir(&format!("{}/", name)));
+                println!("  {:<9} {}", tr("skipped:").bright_black(), trf("over {}", &[&format_size(max)]).yellow());
+                println!();
+            }
+            DirCount::Visible(stats) => display_directory_summary(&entry_path, &name, stats),
+            DirCount::Virtual(kind) => {
+                println!("{}", paint_dir(&format!("{}/", name)));
+                let note = trf("virtual {} filesystem, --include-virtual to list", &[&kind]);
+                println!("  {:<9} {}", tr("skipped:").bright_black(), note.bright_black());
+                println!();
+            }
+            DirCount::None if ignores.ignores_file(&name) || ignores.is_project_ignored(&entry_path) => {
                 total_ignored_files += 1;
-                total_ignored_size += size;
+                total_ignored_size += entry.metadata().map(|m| m.len()).unwrap_or(0);
                 ignored_names.push(name);
             }
-            continue;
-        }
-
-        if is_dir {
-            display_directory_summary(&entry_path, &name, &custom_ignores);
-        } else {
-            display_file_summary(&entry_path, &name);
+            DirCount::None => display_file_summary(&entry_path, &name, false),
         }
     }
 
     // Show total ignored items summary at the end
     if total_ignored_files > 0 {
-        println!("{}", "── ignored (top level) ──".bright_black());
+        println!("{}", tr("── ignored (top level) ──").bright_black());
+        let size = match unreadable_note(total_unreadable) {
+            Some(missed) => format!("{} ({})", format_size(total_ignored_size), missed).yellow(),
+            None => format_size(total_ignored_size).bright_black(),
+        };
         println!("  {} · {} · {}", 
             ignored_names.join(", ").bright_black(),
-            format!("{} files", total_ignored_files).bright_black(),
-            format_size(total_ignored_size).bright_black()
+            trf("{} files", &[&format_count(total_ignored_files)]).bright_black(),
+            size
         );
     }
 }
 
-fn display_directory_summary(entry_path: &Path, name: &str, custom_ignores: &[Regex]) {
-    let mut total_file_count = 0;
-    let mut total_dir_count = 0;
-    let mut total_size: u64 = 0;
+/// What the summary gathered for one top-level entry
+enum DirCount {
+    /// Ignored dir: only its file count and size are shown
+    Ignored(DirTally),
+    Visible(DirStats),
+    /// Over the `-s` limit: not counted past it
+    TooLarge(u64),
+    /// /proc, /sys, ...: not walked, see `skip_virtual`
+    Virtual(&'static str),
+    /// Not a directory
+    None,
+}
 
-    let mut visible_file_count = 0;
-    let mut visible_dir_count = 0;
-    let mut visible_size: u64 = 0;
-    let mut visible_extensions: HashMap<String, usize> = HashMap::new();
-    let mut ignored_subdirs: Vec<(String, usize)> = Vec::new();
+/// Recursive counts for one directory, both overall and after ignores
+pub struct DirStats {
+    pub total_files: usize,
+    pub total_dirs: usize,
+    pub total_size: u64,
+    pub visible_files: usize,
+    pub visible_dirs: usize,
+    pub visible_size: u64,
+    /// Extension → count, visible files only
+    pub extensions: HashMap<String, usize>,
+    /// Immediate ignored subdirectories with their file counts
+    pub ignored_subdirs: Vec<(String, usize)>,
+}
 
-    // First, check immediate children for ignored subdirs
-    if let Ok(immediate_entries) = fs::read_dir(entry_path) {
-        for immediate in immediate_entries.filter_map(|e| e.ok()) {
-            let subname = immediate.file_name().to_string_lossy().to_string();
-            let subpath = immediate.path();
-            let is_subdir = subpath.is_dir();
+/// Walk a directory once and gather the numbers shown in the summary view:
+/// totals, what's left after ignores, and per-ignored-subdir file counts
+pub fn collect_dir_stats(entry_path: &Path, ignores: &IgnoreRules) -> DirStats {
+    let mut stats = DirStats {
+        total_files: 0,
+        total_dirs: 0,
+        total_size: 0,
+        visible_files: 0,
+        visible_dirs: 0,
+        visible_size: 0,
+        extensions: HashMap::new(),
+        ignored_subdirs: Vec::new(),
+    };
+    // Depth of the ignored dir we're currently inside, and whether it's an
+    // immediate child (those get their own file count)
+    let mut ignored_at: Option<(usize, bool)> = None;
 
-            if is_subdir && (should_ignore_dir(&subname) || matches_custom_pattern(&subname, custom_ignores)) {
-                // Count files in ignored subdir
-                let ignored_count = WalkDir::new(&subpath)
-                    .follow_links(false)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                    .filter(|e| e.file_type().is_file())
-                    .count();
-                ignored_subdirs.push((subname, ignored_count));
-            }
+    let walker = WalkDir::new(entry_path).follow_links(false).into_iter().filter_entry(|e| {
+        e.depth() == 0 || !e.file_type().is_dir() || skip_virtual(e.path()).is_none()
+    });
+    for entry in walker.filter_map(|e| e.ok()) {
+        let depth = entry.depth();
+        if depth == 0 {
+            continue;
         }
-    }
+        if ignored_at.is_some_and(|(d, _)| depth <= d) {
+            ignored_at = None; // walked back out of the ignored dir
+        }
+        let name = entry.file_name().to_string_lossy();
 
-    // Walk recursively to count visible items (skip ignored directories)
-    for sub_entry in WalkDir::new(entry_path)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            // Skip ignored directories during traversal
-            if e.file_type().is_dir() && e.path() != entry_path {
-                if let Some(name) = e.file_name().to_str() {
-                    return !(should_ignore_dir(name) || matches_custom_pattern(name, custom_ignores));
+        if entry.file_type().is_dir() {
+            stats.total_dirs += 1;
+            if ignored_at.is_some() {
+                continue;
+            }
+            if ignores.ignores_dir(&name) || ignores.is_project_ignored(entry.path()) {
+                ignored_at = Some((depth, depth == 1));
+                if depth == 1 {
+                    stats.ignored_subdirs.push((name.to_string(), 0));
                 }
+            } else {
+                stats.visible_dirs += 1;
             }
-            true
-        })
-        .filter_map(|e| e.ok())
-    {
-        let subpath = sub_entry.path();
-        let subname = sub_entry.file_name().to_string_lossy().to_string();
-
-        if sub_entry.file_type().is_file() {
-            // Check if file itself should be ignored
-            if !should_ignore_file(&subname) && !matches_custom_pattern(&subname, custom_ignores) {
-                visible_file_count += 1;
-                if let Ok(metadata) = sub_entry.metadata() {
-                    visible_size += metadata.len();
+        } else if entry.file_type().is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            stats.total_files += 1;
+            stats.total_size += size;
+            match ignored_at {
+                Some((_, immediate)) => {
+                    if let (true, Some(last)) = (immediate, stats.ignored_subdirs.last_mut()) {
+                        last.1 += 1;
+                    }
                 }
-                if let Some(ext) = subpath.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    *visible_extensions.entry(ext_str).or_insert(0) += 1;
+                None if !ignores.ignores_file(&name) && !ignores.is_project_ignored(entry.path()) => {
+                    stats.visible_files += 1;
+                    stats.visible_size += size;
+                    if let Some(ext) = entry.path().extension() {
+                        *stats.extensions.entry(ext.to_string_lossy().to_lowercase()).or_insert(0) += 1;
+                    }
                 }
+                None => {}
             }
-        } else if sub_entry.file_type().is_dir() && subpath != entry_path {
-            visible_dir_count += 1;
         }
     }
 
-    // Get ALL stats recursively (including everything)
-    for sub_entry in WalkDir::new(entry_path)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok()) {
-        if sub_entry.file_type().is_file() {
-            total_file_count += 1;
-            if let Ok(metadata) = sub_entry.metadata() {
-                total_size += metadata.len();
-            }
-        } else if sub_entry.file_type().is_dir() && sub_entry.path() != entry_path {
-            total_dir_count += 1;
-        }
-    }
+    stats
+}
+
+fn display_directory_summary(entry_path: &Path, name: &str, stats: DirStats) {
+    let DirStats {
+        total_files: total_file_count,
+        total_dirs: total_dir_count,
+        total_size,
+        visible_files: visible_file_count,
+        visible_dirs: visible_dir_count,
+        visible_size,
+        extensions: visible_extensions,
+        ignored_subdirs,
+    } = stats;
 
     // Display directory
     println!("{}", format!("{}/", name).blue().bold());
@@ -193,58 +232,59 @@ fn display_directory_summary(entry_path: &Path, name: &str, custom_ignores: &[Re
     
     if has_ignored {
         // Show both total and visible
-        let total_parts = vec![
-            format!("{} dirs", total_dir_count),
-            format!("{} files", total_file_count),
+        let total_parts = [
+            trf("{} dirs", &[&format_count(total_dir_count)]),
+            trf("{} files", &[&format_count(total_file_count)]),
             format_size(total_size).to_string()
         ];
-        println!("  {:<9} {}", "total:".bright_black(), total_parts.join(" · ").yellow());
+        println!("  {:<9} {}", tr("total:").bright_black(), total_parts.join(" · ").yellow());
 
         let mut visible_parts = Vec::new();
         if visible_dir_count > 0 {
-            visible_parts.push(format!("{} dirs", visible_dir_count));
+            visible_parts.push(trf("{} dirs", &[&format_count(visible_dir_count)]));
         }
         if visible_file_count > 0 {
-            visible_parts.push(format!("{} files", visible_file_count));
+            visible_parts.push(trf("{} files", &[&format_count(visible_file_count)]));
         }
         visible_parts.push(format_size(visible_size).to_string());
-        println!("  {:<9} {}", "visible:".bright_black(), visible_parts.join(" · ").green());
+        println!("  {:<9} {}", tr("visible:").bright_black(), visible_parts.join(" · ").green());
     } else {
         // Just show total (since visible = total)
         let mut parts = Vec::new();
         if total_dir_count > 0 {
-            parts.push(format!("{} dirs", total_dir_count));
+            parts.push(trf("{} dirs", &[&format_count(total_dir_count)]));
         }
         if total_file_count > 0 {
-            parts.push(format!("{} files", total_file_count));
+            parts.push(trf("{} files", &[&format_count(total_file_count)]));
         }
         parts.push(format_size(total_size).to_string());
-        println!("  {:<9} {}", "total:".bright_black(), parts.join(" · ").yellow());
+        println!("  {:<9} {}", tr("total:").bright_black(), parts.join(" · ").yellow());
     }
 
     // Types line (from visible files)
     if !visible_extensions.is_empty() {
         let mut ext_vec: Vec<_> = visible_extensions.iter().collect();
-        ext_vec.sort_by(|a, b| b.1.cmp(a.1));
+        ext_vec.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
         let type_summary: Vec<String> = ext_vec.iter()
             .take(10)
-            .map(|(ext, count)| format!("{}({})", ext, count))
+            .map(|(ext, count)| format!("{}({})", ext, format_count(count)))
             .collect();
-        println!("  {:<9} {}", "types:".bright_black(), type_summary.join(" ").cyan());
+        println!("  {:<9} {}", tr("types:").bright_black(), type_summary.join(" ").cyan());
     }
 
     // Ignored subdirs
     if !ignored_subdirs.is_empty() {
         let ignored_str: Vec<String> = ignored_subdirs.iter()
-            .map(|(name, count)| format!("{}({} files)", name, count))
+            .map(|(name, count)| trf("{}({} files)", &[name, &format_count(count)]))
             .collect();
-        println!("  {:<9} {}", "ignored:".bright_black(), ignored_str.join(", ").bright_black());
+        println!("  {:<9} {}", tr("ignored:").bright_black(), ignored_str.join(", ").bright_black());
     }
 
     println!();
 }
 
-fn display_file_summary(entry_path: &Path, name: &str) {
+fn display_file_summary(entry_path: &Path, name: &str, detailed: bool) {
+    let metadata = entry_path.symlink_metadata().ok();
     let size = entry_path.metadata().map(|m| m.len()).unwrap_or(0);
     let display_name = if is_executable(entry_path) {
         name.green().bold()
@@ -254,6 +294,110 @@ fn display_file_summary(entry_path: &Path, name: &str) {
     
     println!("{}", display_name);
     println!("  {}", entry_path.canonicalize().unwrap_or(entry_path.to_path_buf()).display().to_string().bright_black());
-    println!("  {}", format_size(size).bright_black());
+    if !detailed {
+        println!("  {}", format_size(size).bright_black());
+        println!();
+        return;
+    }
+
+    println!(
+        "  {:<9} {} {}",
+        tr("size:").bright_black(),
+        format_size(size).yellow(),
+        trf("({} bytes)", &[&format_count(size)]).bright_black()
+    );
+    if let Some(modified) = metadata.as_ref().and_then(|m| m.modified().ok()) {
+        let modified: DateTime<Local> = modified.into();
+        println!("  {:<9} {}", tr("modified:").bright_black(), modified.format("%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(ref m) = metadata {
+        println!("  {:<9} {}", tr("perms:").bright_black(), format_permissions(m));
+    }
+    if let Some(status) = git_file_status(entry_path) {
+        println!("  {:<9} {}", tr("git:").bright_black(), status.cyan());
+    }
+    match count_lines(entry_path) {
+        _ if keep_offline(entry_path) => {
+            println!("  {:<9} {}", tr("lines:").bright_black(), tr("in the cloud (--hydrate to count)").bright_black())
+        }
+        Some(lines) => println!("  {:<9} {}", tr("lines:").bright_black(), format_count(lines)),
+        None => println!("  {:<9} {}", tr("lines:").bright_black(), tr("binary").bright_black()),
+    }
     println!();
-}
\ No newline at end of file
+}
+
+/// Short git status for a single file, or None outside a repository
+fn git_file_status(path: &Path) -> Option<String> {
+    let repo = Repository::discover(path).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let abs = path.canonicalize().ok()?;
+    let rel = abs.strip_prefix(&workdir).ok()?;
+    let status = repo.status_file(rel).ok()?;
+
+    let label = if status.is_ignored() {
+        "ignored"
+    } else if status.is_wt_new() {
+        "untracked"
+    } else if status.is_conflicted() {
+        "conflicted"
+    } else if status.is_index_new() || status.is_index_modified() || status.is_index_deleted() {
+        "staged"
+    } else if status.is_wt_modified() || status.is_wt_deleted() {
+        "modified"
+    } else {
+        "tracked, clean"
+    };
+    Some(tr(label).to_string())
+}
+
+/// Expand a path whose last component may contain `*` / `?` wildcards
+pub fn expand_glob(target: &str) -> Vec<PathBuf> {
+    let path = Path::new(target);
+    let file_pattern = match path.file_name().and_then(|n| n.to_str()) {
+        Some(p) if p.contains('*') || p.contains('?') => p,
+        _ => return vec![path.to_path_buf()],
+    };
+    let dir = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let escaped = regex::escape(file_pattern).replace(r"\*", ".*").replace(r"\?", ".");
+    let Ok(re) = Regex::new(&format!("^{}$", escaped)) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|rd| {
+            rd.filter_map(|e| e.ok())
+                .filter(|e| re.is_match(&e.file_name().to_string_lossy()))
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    matches.sort();
+    matches
+}
+
+/// `struct summary PATH_OR_GLOB` — a detailed card per file, the summary view per directory
+pub fn display_target_summary(target: &str, ignores: &IgnoreRules, max_size: Option<u64>) {
+    let paths = expand_glob(target);
+    if paths.is_empty() {
+        eprintln!("error: nothing matches '{}'", target);
+        return;
+    }
+
+    for path in paths {
+        if path.is_dir() {
+            display_summary(&path, ignores, false, max_size);
+        } else if path.symlink_metadata().is_ok() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            display_file_summary(&path, &name, true);
+        } else {
+            eprintln!("error: '{}' does not exist", path.display());
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use colored::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config::load_config_settings_with_prefix;
+
+/// A color plus attributes, parsed from a config value like `"orange"`,
+/// `"blue bold"`, `"bright black italic"` or `"#ff8800 underline"`
+#[derive(Clone, Copy, Default)]
+struct Style {
+    color: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    fn parse(spec: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        let words: Vec<String> = spec.split_whitespace().map(|w| w.to_lowercase()).collect();
+        let mut i = 0;
+        while i < words.len() {
+            let word = words[i].as_str();
+            match word {
+                "bold" => style.bold = true,
+                "dim" | "dimmed" => style.dimmed = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "bright" if i + 1 < words.len() => {
+                    i += 1;
+                    style.color = Some(parse_color(&format!("bright {}", words[i]))?);
+                }
+                _ => style.color = Some(parse_color(word)?),
+            }
+            i += 1;
+        }
+        Ok(style)
+    }
+
+    fn paint(&self, text: &str) -> ColoredString {
+        let mut out = match self.color {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        };
+        if self.bold {
+            out = out.bold();
+        }
+        if self.dimmed {
+            out = out.dimmed();
+        }
+        if self.italic {
+            out = out.italic();
+        }
+        if self.underline {
+            out = out.underline();
+        }
+        out
+    }
+}
+
+/// A named color (`red`, `bright blue`, plus a few common extras) or `#rrggbb`
+fn parse_color(name: &str) -> Result<Color, String> {
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::TrueColor { r, g, b }),
+            _ => Err(format!("'{}' is not a #rrggbb color", name)),
+        };
+    }
+    match name {
+        "orange" => Ok(Color::TrueColor { r: 255, g: 165, b: 0 }),
+        "pink" => Ok(Color::TrueColor { r: 255, g: 135, b: 175 }),
+        "gray" | "grey" => Ok(Color::BrightBlack),
+        _ => name.parse().map_err(|_| format!("unknown color '{}'", name)),
+    }
+}
+
+/// User color rules from `color.*` settings in the config file:
+///
+///   color.dir = "blue bold"       directories
+///   color.exec = "green bold"     executables
+///   color.symlink = "cyan"        symlinks
+///   color.symlink_external = "magenta"   symlinks pointing outside the tree
+///   color.ext.rs = "orange"       files by extension (`tar.gz` works too)
+#[derive(Default)]
+struct Theme {
+    dir: Option<Style>,
+    exec: Option<Style>,
+    symlink: Option<Style>,
+    symlink_external: Option<Style>,
+    ext: HashMap<String, Style>,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Read the `color.*` rules, warning about values that don't parse
+fn load_theme() -> Theme {
+    let mut theme = Theme::default();
+    for (key, value) in load_config_settings_with_prefix("color.") {
+        let style = match Style::parse(&value) {
+            Ok(style) => style,
+            Err(e) => {
+                eprintln!("warning: config {} = \"{}\": {}", key, value, e);
+                continue;
+            }
+        };
+        match key.as_str() {
+            "color.dir" => theme.dir = Some(style),
+            "color.exec" => theme.exec = Some(style),
+            "color.symlink" => theme.symlink = Some(style),
+            "color.symlink_external" => theme.symlink_external = Some(style),
+            _ => match key.strip_prefix("color.ext.") {
+                Some(ext) if !ext.is_empty() => {
+                    theme.ext.insert(ext.trim_start_matches('.').to_lowercase(), style);
+                }
+                _ => eprintln!("warning: unknown config key '{}'", key),
+            },
+        }
+    }
+    theme
+}
+
+/// The rules, loaded on first use so commands that draw nothing never read them
+fn theme() -> &'static Theme {
+    THEME.get_or_init(load_theme)
+}
+
+/// A directory label (`src/`), blue and bold unless `color.dir` says otherwise
+pub fn paint_dir(label: &str) -> ColoredString {
+    match theme().dir {
+        Some(style) => style.paint(label),
+        None => label.blue().bold(),
+    }
+}
+
+/// A symlink label (`link -> target`), cyan unless `color.symlink` says otherwise
+pub fn paint_symlink(label: &str) -> ColoredString {
+    match theme().symlink {
+        Some(style) => style.paint(label),
+        None => label.cyan(),
+    }
+}
+
+/// A symlink that leads out of the tree, magenta unless
+/// `color.symlink_external` says otherwise
+pub fn paint_external_symlink(label: &str) -> ColoredString {
+    match theme().symlink_external {
+        Some(style) => style.paint(label),
+        None => label.magenta(),
+    }
+}
+
+/// A file label: executables first (like `ls`), then the longest matching
+/// `color.ext.*` rule, then the caller's own default
+pub fn paint_file(label: &str, path: &Path, executable: bool, fallback: fn(&str) -> ColoredString) -> ColoredString {
+    if executable {
+        return match theme().exec {
+            Some(style) => style.paint(label),
+            None => label.green().bold(),
+        };
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let rule = name
+        .char_indices()
+        .filter(|&(i, c)| c == '.' && i > 0)
+        .find_map(|(i, _)| theme().ext.get(&name[i + 1..]));
+    match rule {
+        Some(style) => style.paint(label),
+        None => fallback(label),
+    }
+}
@@ -0,0 +1,274 @@
+use colored::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::entry::read_entries;
+use crate::theme::{paint_dir, paint_symlink};
+use crate::utils::{format_size, tally_dir};
+
+pub use crate::ignores::{compile_pattern, IgnoreRules};
+
+/// What the ignore rules do with one entry. The drawn tree and
+/// [`TreeBuilder`] both go by this, so they list the same entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    Shown,
+    /// Built-in ignored dir (target/, node_modules/, ...): one node with a
+    /// file count, not descended into
+    Collapsed,
+    /// Left out, and why (`--show-ignored` prints the reason)
+    Hidden(&'static str),
+}
+
+/// Where `name` at `path` stands under `ignores`. Checked in the tree's order:
+/// built-in dirs collapse before any pattern is looked at.
+pub fn visibility(ignores: &IgnoreRules, path: &Path, name: &str, is_dir: bool) -> Visibility {
+    if is_dir && ignores.is_default_dir(name) {
+        Visibility::Collapsed
+    } else if ignores.is_custom(name) {
+        Visibility::Hidden("ignored by pattern")
+    } else if ignores.is_project_ignored(path) {
+        Visibility::Hidden("ignored by project file")
+    } else if !is_dir && ignores.is_default_file(name) {
+        Visibility::Hidden("ignored by default")
+    } else if ignores.is_dotfile(name) {
+        Visibility::Hidden("hidden, -a to show")
+    } else {
+        Visibility::Shown
+    }
+}
+
+/// What a node stands for
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Dir,
+    File,
+    /// Never followed; `target` is what the link points to, when readable
+    Symlink { target: Option<PathBuf> },
+}
+
+/// One entry of a built tree. The root is a `Dir` named after the path
+/// the tree was built from.
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: NodeKind,
+    /// A file's length, or everything under a collapsed dir
+    pub size: Option<u64>,
+    /// Built-in ignored dir (target/, node_modules/, ...) folded into one
+    /// node: how many files it holds
+    pub ignored_files: Option<usize>,
+    /// Entries of a directory, directories first; None where the walk
+    /// stopped (depth limit, collapsed dir)
+    pub children: Option<Vec<TreeNode>>,
+}
+
+impl TreeNode {
+    pub fn is_dir(&self) -> bool {
+        self.kind == NodeKind::Dir
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.ignored_files.is_some()
+    }
+
+    /// The node as the `--output json` entry shape (`struct serve` lists and
+    /// `struct render` reads the same)
+    pub fn to_json(&self) -> Value {
+        let mut item = json!({
+            "name": self.name,
+            "path": self.path.display().to_string(),
+            "type": match self.kind {
+                NodeKind::Dir => "dir",
+                NodeKind::File => "file",
+                NodeKind::Symlink { .. } => "symlink",
+            },
+        });
+        if let Some(files) = self.ignored_files {
+            item["collapsed"] = json!(true);
+            item["ignored_files"] = json!(files);
+        }
+        if let NodeKind::Symlink { target: Some(ref target) } = self.kind {
+            item["target"] = json!(target.display().to_string());
+        }
+        if let Some(size) = self.size {
+            item["size"] = json!(size);
+        }
+        if let Some(ref children) = self.children {
+            item["children"] = Value::Array(children.iter().map(TreeNode::to_json).collect());
+        }
+        item
+    }
+
+    /// Read back an entry written by `to_json`; missing fields get defaults
+    pub fn from_json(value: &Value) -> TreeNode {
+        let text = |key: &str| value.get(key).and_then(|v| v.as_str());
+        let kind = match text("type") {
+            Some("dir") => NodeKind::Dir,
+            Some("symlink") => NodeKind::Symlink { target: text("target").map(PathBuf::from) },
+            _ => NodeKind::File,
+        };
+        let collapsed = value.get("collapsed").and_then(|c| c.as_bool()) == Some(true);
+        TreeNode {
+            name: text("name").unwrap_or("?").to_string(),
+            path: PathBuf::from(text("path").unwrap_or_default()),
+            kind,
+            size: value.get("size").and_then(|s| s.as_u64()),
+            ignored_files: collapsed
+                .then(|| value.get("ignored_files").and_then(|f| f.as_u64()).unwrap_or(0) as usize),
+            children: value
+                .get("children")
+                .and_then(|c| c.as_array())
+                .map(|children| children.iter().map(TreeNode::from_json).collect()),
+        }
+    }
+}
+
+/// Walks a directory into a [`TreeNode`] with the tree view's rules: custom
+/// patterns and built-in ignored files are left out, built-in ignored dirs
+/// become collapsed nodes with a file count, symlinks are never followed.
+///
+/// ```no_run
+/// use struct_cli::tree::TreeBuilder;
+///
+/// let root = TreeBuilder::new(".").depth(2).build();
+/// for node in root.children.unwrap_or_default() {
+///     println!("{} {:?}", node.name, node.size);
+/// }
+/// ```
+pub struct TreeBuilder {
+    root: PathBuf,
+    depth: usize,
+    ignores: IgnoreRules,
+    count_collapsed: bool,
+}
+
+impl TreeBuilder {
+    /// Everything below `root`, with the built-in ignores only
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        TreeBuilder { root: root.into(), depth: usize::MAX, ignores: IgnoreRules::default(), count_collapsed: true }
+    }
+
+    /// Levels to descend: 1 lists the root's own entries only
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn ignores(mut self, ignores: IgnoreRules) -> Self {
+        self.ignores = ignores;
+        self
+    }
+
+    /// Walk collapsed dirs for their file count and size (on by default;
+    /// off leaves both at zero, which is much faster on big build dirs)
+    pub fn count_collapsed(mut self, count: bool) -> Self {
+        self.count_collapsed = count;
+        self
+    }
+
+    pub fn build(&self) -> TreeNode {
+        TreeNode {
+            name: self.root.display().to_string(),
+            path: self.root.clone(),
+            kind: NodeKind::Dir,
+            size: None,
+            ignored_files: None,
+            children: Some(self.children(&self.root, self.depth)),
+        }
+    }
+
+    fn children(&self, dir: &Path, depth: usize) -> Vec<TreeNode> {
+        let Some(mut entries) = read_entries(dir) else { return Vec::new() };
+        entries.sort_by_cached_key(|e| (!e.is_dir(), e.name.to_lowercase()));
+
+        let mut nodes = Vec::new();
+        for entry in entries {
+            let is_dir = entry.is_dir();
+            self.ignores.trace(&entry.path, &entry.name, is_dir);
+            match visibility(&self.ignores, &entry.path, &entry.name, is_dir) {
+                Visibility::Hidden(_) => {}
+                Visibility::Collapsed => {
+                    let (files, bytes) = if self.count_collapsed {
+                        let tally = tally_dir(&entry.path, None);
+                        (tally.files, tally.bytes)
+                    } else {
+                        (0, 0)
+                    };
+                    nodes.push(TreeNode {
+                        name: entry.name,
+                        path: entry.path,
+                        kind: NodeKind::Dir,
+                        size: Some(bytes),
+                        ignored_files: Some(files),
+                        children: None,
+                    });
+                }
+                Visibility::Shown => {
+                    let (kind, size) = if entry.is_symlink() {
+                        (NodeKind::Symlink { target: fs::read_link(&entry.path).ok() }, None)
+                    } else if is_dir {
+                        (NodeKind::Dir, None)
+                    } else {
+                        (NodeKind::File, Some(entry.size()))
+                    };
+                    let children = (is_dir && depth > 1).then(|| self.children(&entry.path, depth - 1));
+                    nodes.push(TreeNode { name: entry.name, path: entry.path, kind, size, ignored_files: None, children });
+                }
+            }
+        }
+        nodes
+    }
+}
+
+/// A node's name as the tree draws it, and the note after it (` (12.0K)`,
+/// ` (40 files ignored)`), uncolored
+pub(crate) fn node_label(node: &TreeNode, show_size: bool) -> (ColoredString, String) {
+    match node.kind {
+        NodeKind::Dir if node.is_collapsed() => {
+            let files = node.ignored_files.unwrap_or(0);
+            let note = match node.size {
+                Some(bytes) if show_size => format!(" ({}, {} files ignored)", format_size(bytes), files),
+                _ => format!(" ({} files ignored)", files),
+            };
+            (paint_dir(&format!("{}/", node.name)), note)
+        }
+        NodeKind::Dir => (paint_dir(&format!("{}/", node.name)), String::new()),
+        NodeKind::Symlink { target: Some(ref target) } => {
+            (paint_symlink(&format!("{} -> {}", node.name, target.display())), String::new())
+        }
+        NodeKind::Symlink { target: None } => (paint_symlink(&node.name), String::new()),
+        NodeKind::File => {
+            let note = match node.size {
+                Some(bytes) if show_size => format!(" ({})", format_size(bytes)),
+                _ => String::new(),
+            };
+            (node.name.normal(), note)
+        }
+    }
+}
+
+/// Draw a node's children with box-drawing connectors — the plain tree
+/// `struct render` prints. Colors follow the `colored` crate's settings.
+pub fn render_tree(root: &TreeNode, show_size: bool, out: &mut impl Write) -> io::Result<()> {
+    render_children(root.children.as_deref().unwrap_or_default(), "", show_size, out)
+}
+
+fn render_children(nodes: &[TreeNode], prefix: &str, show_size: bool, out: &mut impl Write) -> io::Result<()> {
+    for (idx, node) in nodes.iter().enumerate() {
+        let is_last = idx == nodes.len() - 1;
+        let connector = if is_last { "└── " } else { "├── " };
+
+        let (display_name, note) = node_label(node, show_size);
+        writeln!(out, "{}{}{}{}", prefix, connector, display_name, note.bright_black())?;
+
+        if let Some(ref children) = node.children {
+            let new_prefix = if is_last { format!("{}    ", prefix) } else { format!("{}│   ", prefix) };
+            render_children(children, &new_prefix, show_size, out)?;
+        }
+    }
+    Ok(())
+}
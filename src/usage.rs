@@ -0,0 +1,147 @@
+use colored::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+use crate::ignores::IgnoreRules;
+use crate::overview::{usage_bar, BAR_WIDTH};
+use crate::utils::{format_count, format_size, owner_name, skip_virtual};
+
+/// `struct usage --by age` buckets: upper bound of each, oldest last
+const AGE_BUCKETS: &[(&str, u64)] = &[
+    ("< 1 day", 86_400),
+    ("1-7 days", 7 * 86_400),
+    ("1-4 weeks", 30 * 86_400),
+    ("1-6 months", 182 * 86_400),
+    ("6-12 months", 365 * 86_400),
+    ("1-2 years", 2 * 365 * 86_400),
+    ("> 2 years", u64::MAX),
+];
+
+/// One row of the table: everything sharing an owner, extension or age
+struct Group {
+    name: String,
+    size: u64,
+    files: usize,
+    /// Position in AGE_BUCKETS, to keep ages in order instead of by size
+    order: usize,
+}
+
+/// Owner key: the uid, so names are looked up once per user
+#[cfg(unix)]
+fn owner_key(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.uid()
+}
+
+#[cfg(not(unix))]
+fn owner_key(_metadata: &fs::Metadata) -> u32 {
+    0
+}
+
+fn age_bucket(metadata: &fs::Metadata) -> usize {
+    let age = metadata
+        .modified()
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    AGE_BUCKETS.iter().position(|&(_, limit)| age < limit).unwrap_or(AGE_BUCKETS.len() - 1)
+}
+
+/// `struct usage --by owner|ext|age [PATH]`: disk usage of every visible file
+/// under PATH summed per owner, extension or age bucket, biggest first (ages
+/// in order), with bars scaled to the largest group
+pub fn display_usage(path: &Path, by: &str, ignores: &IgnoreRules, json_out: bool) {
+    if !path.is_dir() {
+        eprintln!("error: {} is not a directory", path.display());
+        std::process::exit(2);
+    }
+
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    let mut owners: HashMap<u32, String> = HashMap::new();
+    let walker = WalkDir::new(path).follow_links(false).into_iter().filter_entry(|e| {
+        e.depth() == 0
+            || !e.file_type().is_dir()
+            || !(ignores.ignores_dir(&e.file_name().to_string_lossy())
+                || ignores.is_project_ignored(e.path())
+                || skip_virtual(e.path()).is_some())
+    });
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file()
+            || ignores.ignores_file(&entry.file_name().to_string_lossy())
+            || ignores.is_project_ignored(entry.path())
+        {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        let (name, order) = match by {
+            "owner" => {
+                let name = owners.entry(owner_key(&metadata)).or_insert_with(|| owner_name(&metadata)).clone();
+                (name, 0)
+            }
+            "age" => {
+                let bucket = age_bucket(&metadata);
+                (AGE_BUCKETS[bucket].0.to_string(), bucket)
+            }
+            _ => {
+                let ext = entry.path().extension().map(|e| format!(".{}", e.to_string_lossy().to_lowercase()));
+                (ext.unwrap_or_else(|| "(no extension)".to_string()), 0)
+            }
+        };
+        let group = groups.entry(name.clone()).or_insert(Group { name, size: 0, files: 0, order });
+        group.size += metadata.len();
+        group.files += 1;
+    }
+
+    let mut rows: Vec<Group> = groups.into_values().collect();
+    if by == "age" {
+        rows.sort_by_key(|g| g.order);
+    } else {
+        rows.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    }
+    let total: u64 = rows.iter().map(|g| g.size).sum();
+    let files: usize = rows.iter().map(|g| g.files).sum();
+
+    if json_out {
+        let items: Vec<serde_json::Value> =
+            rows.iter().map(|g| json!({ "name": g.name, "size": g.size, "files": g.files })).collect();
+        let out = json!({
+            "path": path.display().to_string(),
+            "by": by,
+            "size": total,
+            "files": files,
+            "groups": items,
+        });
+        println!("{}", out);
+        return;
+    }
+
+    println!(
+        "{} {}",
+        path.display().to_string().cyan().bold(),
+        format!("({} in {} files, by {})", format_size(total), format_count(files), by).bright_black()
+    );
+    let largest = rows.iter().map(|g| g.size).max().unwrap_or(0);
+    let name_width = rows.iter().map(|g| g.name.chars().count()).max().unwrap_or(0);
+    let sizes: Vec<String> = rows.iter().map(|g| format_size(g.size)).collect();
+    let size_width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    for (group, size) in rows.iter().zip(&sizes) {
+        let share = if total == 0 { 0.0 } else { group.size as f64 * 100.0 / total as f64 };
+        println!(
+            "  {:<name_width$}  {:>size_width$}  {:>5.1}%  {:<bar_width$}  {}",
+            group.name,
+            size,
+            share,
+            usage_bar(group.size, largest).green(),
+            format!("{} files", format_count(group.files)).bright_black(),
+            name_width = name_width,
+            size_width = size_width,
+            bar_width = BAR_WIDTH
+        );
+    }
+}
@@ -1,55 +1,921 @@
 use std::fs;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use walkdir::WalkDir;
 
+use crate::i18n::{tr, trf};
+
+/// How sizes are printed: decimals for K/M/G, and the smallest unit used
+#[derive(Clone, Copy)]
+pub struct SizeFormat {
+    pub precision: usize,
+    /// 0 = B, 1 = K, 2 = M, 3 = G
+    pub min_unit: u32,
+}
+
+static SIZE_FORMAT: OnceLock<SizeFormat> = OnceLock::new();
+
+/// Set the size format once at startup (`--size-precision`, `--size-unit-min`)
+pub fn set_size_format(format: SizeFormat) {
+    let _ = SIZE_FORMAT.set(format);
+}
+
+/// Unit index for `B`, `K`, `M` or `G` (case-insensitive)
+pub fn parse_size_unit(unit: &str) -> Option<u32> {
+    match unit.to_ascii_uppercase().as_str() {
+        "B" => Some(0),
+        "K" | "KB" => Some(1),
+        "M" | "MB" => Some(2),
+        "G" | "GB" => Some(3),
+        _ => None,
+    }
+}
+
 /// Format bytes into human-readable size (B, K, M, G)
 pub fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.1}G", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.1}M", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.1}K", bytes as f64 / KB as f64)
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let format = SIZE_FORMAT.get().copied().unwrap_or(SizeFormat { precision: 1, min_unit: 0 });
+
+    let mut unit = 0;
+    while unit < 3 && bytes >= 1024u64.pow(unit as u32 + 1) {
+        unit += 1;
+    }
+    let unit = unit.max(format.min_unit.min(3) as usize);
+
+    if unit == 0 {
+        format!("{}{}", bytes, tr(UNITS[0]))
     } else {
-        format!("{}B", bytes)
+        let value = bytes as f64 / 1024u64.pow(unit as u32) as f64;
+        let mut value = format!("{:.*}", format.precision, value);
+        // 1,5M where the thousands separator is a dot or a space
+        if matches!(thousands_separator(), Some('.') | Some('\u{202f}')) {
+            value = value.replace('.', ",");
+        }
+        format!("{}{}", value, tr(UNITS[unit]))
+    }
+}
+
+/// A formatted size in words for --a11y: `3.2K` → `3.2 kilobytes`,
+/// `~12M` → `about 12 megabytes`. Anything else comes back unchanged.
+pub fn spoken_size(size: &str) -> String {
+    let (about, size) = match size.strip_prefix('~') {
+        Some(rest) => (true, rest),
+        None => (false, size),
+    };
+    let split = size.find(|c: char| c.is_alphabetic()).unwrap_or(size.len());
+    let (value, unit) = size.split_at(split);
+    // The units format_size printed (maybe translated), else plain B/K/M/G
+    let index = ["B", "K", "M", "G"].iter().position(|u| tr(u) == unit).map(|i| i as u32);
+    let words = match index.or_else(|| parse_size_unit(unit)) {
+        Some(0) if value == "1" => trf("{} byte", &[&value]),
+        Some(0) => trf("{} bytes", &[&value]),
+        Some(1) => trf("{} kilobytes", &[&value]),
+        Some(2) => trf("{} megabytes", &[&value]),
+        Some(3) => trf("{} gigabytes", &[&value]),
+        _ => size.to_string(),
+    };
+    if about { trf("about {}", &[&words]) } else { words }
+}
+
+/// `incomplete, N unreadable` for a size that misses entries it couldn't
+/// read, so it reads as a lower bound; None when nothing was missed
+pub fn unreadable_note(unreadable: usize) -> Option<String> {
+    (unreadable > 0).then(|| trf("incomplete, {} unreadable", &[&format_count(unreadable)]))
+}
+
+/// How long ago `time` was, coarsely: `just now`, `5m ago`, `2h ago`,
+/// `3d ago`, `4mo ago`, `2y ago` (times in the future count as now)
+pub fn relative_time(time: SystemTime) -> String {
+    let secs = SystemTime::now().duration_since(time).map_or(0, |d| d.as_secs());
+    let ago = match secs {
+        0..=59 => return tr("just now").to_string(),
+        60..=3_599 => format!("{}m", secs / 60),
+        3_600..=86_399 => format!("{}h", secs / 3_600),
+        86_400..=2_591_999 => format!("{}d", secs / 86_400),
+        2_592_000..=31_535_999 => format!("{}mo", secs / 2_592_000),
+        _ => format!("{}y", secs / 31_536_000),
+    };
+    trf("{} ago", &[&ago])
+}
+
+/// An argument as it would be typed in a POSIX shell
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty() && arg.chars().all(|c| c.is_alphanumeric() || "_-./:=,@%+~".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Thousands separator for counts (`--number-format`); None = plain digits
+static THOUSANDS_SEP: OnceLock<Option<char>> = OnceLock::new();
+
+/// Pick the thousands separator once at startup: `locale` (the default) reads
+/// LC_ALL / LC_NUMERIC / LANG; `plain`, `comma`, `dot` and `space` force one.
+pub fn set_number_format(format: &str) {
+    let sep = match format {
+        "plain" => None,
+        "comma" => Some(','),
+        "dot" => Some('.'),
+        "space" => Some('\u{202f}'),
+        _ => locale_separator(),
+    };
+    let _ = THOUSANDS_SEP.set(sep);
+}
+
+/// Thousands separator conventional for the user's locale
+fn locale_separator() -> Option<char> {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|v| !v.is_empty())
+        .unwrap_or_default();
+    let lang = locale.split(['_', '.', '@']).next().unwrap_or("");
+    match lang {
+        _ if locale.starts_with("de_CH") => Some('\''),
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" => Some('.'),
+        "fr" | "ru" | "sv" | "fi" | "nb" | "no" | "pl" | "cs" | "sk" | "uk" | "hu" => Some('\u{202f}'),
+        _ => Some(','),
     }
 }
 
+/// The separator `format_count` groups digits with (None = plain digits)
+pub fn thousands_separator() -> Option<char> {
+    *THOUSANDS_SEP.get_or_init(locale_separator)
+}
+
+/// A count with thousands separators (`13,542`) per `--number-format`
+pub fn format_count(n: impl std::fmt::Display) -> String {
+    let digits = n.to_string();
+    let Some(sep) = thousands_separator() else {
+        return digits;
+    };
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(sep);
+        }
+        out.push(c);
+    }
+    out
+}
+
 /// Check if a file is executable
 pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| metadata_is_executable(&m, path))
+}
+
+/// Whether already-fetched metadata says executable (on Windows, by extension)
+pub fn metadata_is_executable(metadata: &fs::Metadata, path: &Path) -> bool {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        if let Ok(metadata) = fs::metadata(path) {
-            let permissions = metadata.permissions();
-            return permissions.mode() & 0o111 != 0;
-        }
+        let _ = path;
+        metadata.permissions().mode() & 0o111 != 0
     }
-    
+
     #[cfg(not(unix))]
     {
         // On Windows, check common executable extensions
-        if let Some(ext) = path.extension() {
+        let _ = metadata;
+        path.extension().is_some_and(|ext| {
             let ext = ext.to_string_lossy().to_lowercase();
-            return matches!(ext.as_str(), "exe" | "bat" | "cmd" | "sh" | "py" | "ps1");
+            matches!(ext.as_str(), "exe" | "bat" | "cmd" | "sh" | "py" | "ps1")
+        })
+    }
+}
+
+/// `ls -l` style permission string (`drwxr-xr-x`); read-only/writable elsewhere
+pub fn format_permissions(metadata: &fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        let kind = if metadata.file_type().is_symlink() {
+            'l'
+        } else if metadata.is_dir() {
+            'd'
+        } else {
+            '-'
+        };
+        let mut out = String::with_capacity(10);
+        out.push(kind);
+        for shift in [6, 3, 0] {
+            let bits = (mode >> shift) & 0o7;
+            out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            out.push(if bits & 0o1 != 0 { 'x' } else { '-' });
         }
+        out
     }
-    
-    false
+
+    #[cfg(not(unix))]
+    {
+        if metadata.permissions().readonly() {
+            "read-only".to_string()
+        } else {
+            "writable".to_string()
+        }
+    }
+}
+
+/// User name owning a file (the numeric uid when it has no passwd entry)
+pub fn owner_name(metadata: &fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+        use std::ffi::CStr;
+        use std::os::unix::fs::MetadataExt;
+
+        let uid = metadata.uid();
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buf = vec![0 as libc::c_char; 1024];
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        // SAFETY: all pointers are valid for the call; buf outlives the use of pwd.pw_name
+        let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc != 0 || result.is_null() {
+            return uid.to_string();
+        }
+        unsafe { CStr::from_ptr(pwd.pw_name) }.to_string_lossy().into_owned()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        "-".to_string()
+    }
+}
+
+/// Group name owning a file (the numeric gid when it has no group entry)
+pub fn group_name(metadata: &fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+        use std::ffi::CStr;
+        use std::os::unix::fs::MetadataExt;
+
+        let gid = metadata.gid();
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut buf = vec![0 as libc::c_char; 4096];
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        // SAFETY: all pointers are valid for the call; buf outlives the use of grp.gr_name
+        let rc = unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc != 0 || result.is_null() {
+            return gid.to_string();
+        }
+        unsafe { CStr::from_ptr(grp.gr_name) }.to_string_lossy().into_owned()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        "-".to_string()
+    }
+}
+
+/// Windows attribute letters `RHSA` (read-only, hidden, system, archive),
+/// `-` where unset; None elsewhere, where owner and group say more
+pub fn file_attributes(metadata: &fs::Metadata) -> Option<String> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        let attrs = metadata.file_attributes();
+        let flags = [(0x1, 'R'), (0x2, 'H'), (0x4, 'S'), (0x20, 'A')];
+        Some(flags.iter().map(|&(bit, c)| if attrs & bit != 0 { c } else { '-' }).collect())
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Parse a duration like `5s`, `500ms`, `2m` or a bare number of seconds
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("invalid duration '{}'", text))?;
+    let seconds = match unit {
+        "" | "s" => value,
+        "ms" => value / 1000.0,
+        "m" => value * 60.0,
+        _ => return Err(format!("invalid duration '{}' (use e.g. 500ms, 5s, 2m)", text)),
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Filesystem space for the device holding `path`: (total, used, available) bytes
+pub fn disk_space(path: &Path) -> Option<(u64, u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c_path is a valid NUL-terminated string and stat is a valid out-pointer
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        let block = stat.f_frsize as u64;
+        let total = stat.f_blocks as u64 * block;
+        let free = stat.f_bfree as u64 * block;
+        let available = stat.f_bavail as u64 * block;
+        Some((total, total.saturating_sub(free), available))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Columns of the terminal stdout is drawn on, looked up once
+static TERMINAL_WIDTH: OnceLock<Option<usize>> = OnceLock::new();
+
+/// Width to fit tree lines into: `$COLUMNS` when set, else the width of the
+/// terminal on stdout. None when output is piped — files and pagers get
+/// full-length lines.
+pub fn terminal_width() -> Option<usize> {
+    *TERMINAL_WIDTH.get_or_init(|| {
+        if let Some(cols) = std::env::var("COLUMNS").ok().and_then(|c| c.trim().parse::<usize>().ok()) {
+            return (cols > 0).then_some(cols);
+        }
+        #[cfg(unix)]
+        {
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            // SAFETY: TIOCGWINSZ only writes into the winsize struct we pass
+            let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+            (rc == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    })
+}
+
+/// `text` cut to at most `width` characters, ending in `…` when shortened
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Whether cloud placeholders may be opened and listed (`--hydrate`)
+static HYDRATE: OnceLock<bool> = OnceLock::new();
+
+/// Allow reading cloud placeholders for this run, downloading them on access
+pub fn set_hydrate(hydrate: bool) {
+    let _ = HYDRATE.set(hydrate);
+}
+
+/// A OneDrive / cloud-files placeholder whose content isn't on this disk.
+/// Reading its attributes is safe; opening or listing it starts a download.
+pub fn is_cloud_placeholder(path: &Path) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+        const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+        let cloud = FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS;
+        fs::symlink_metadata(path).map(|m| m.file_attributes() & cloud != 0).unwrap_or(false)
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// A placeholder that must not be opened or listed (no `--hydrate`)
+pub fn keep_offline(path: &Path) -> bool {
+    !HYDRATE.get().copied().unwrap_or(false) && is_cloud_placeholder(path)
+}
+
+/// Whether virtual filesystems are listed and counted (`--include-virtual`)
+static INCLUDE_VIRTUAL: OnceLock<bool> = OnceLock::new();
+
+/// Descend into /proc, /sys, /dev and the like for this run
+pub fn set_include_virtual(include: bool) {
+    let _ = INCLUDE_VIRTUAL.set(include);
 }
 
-/// Get total size of a directory recursively
+/// The pseudo-filesystem `path` is on (`proc`, `sysfs`, `devpts`, ...), or
+/// None for real storage. /dev counts as one by path, since devtmpfs can't
+/// be told apart from an ordinary tmpfs.
+pub fn virtual_fs(path: &Path) -> Option<&'static str> {
+    if path.file_name().is_some_and(|n| n == "dev") && path.canonicalize().is_ok_and(|p| p == Path::new("/dev")) {
+        return Some("dev");
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c_path is a valid NUL-terminated string and stat is a valid out-pointer
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        // Magic numbers from <linux/magic.h>
+        match stat.f_type as u32 {
+            0x0000_9fa0 => Some("proc"),
+            0x6265_6572 => Some("sysfs"),
+            0x0000_1cd1 => Some("devpts"),
+            0x0027_e0eb => Some("cgroup"),
+            0x6367_7270 => Some("cgroup2"),
+            0x6462_6720 => Some("debugfs"),
+            0x7472_6163 => Some("tracefs"),
+            0x7363_6673 => Some("securityfs"),
+            0x6165_676c => Some("pstore"),
+            0xcafe_4a11 => Some("bpf"),
+            0x6265_6570 => Some("configfs"),
+            0xde5e_81e4 => Some("efivarfs"),
+            0x9584_58f6 => Some("hugetlbfs"),
+            0x1980_0202 => Some("mqueue"),
+            0x4249_4e4d => Some("binfmt_misc"),
+            0x6573_5543 => Some("fusectl"),
+            _ => None,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Where a virtual filesystem is mounted, unless `--include-virtual`: its
+/// kind, for the note shown instead of its contents. Nothing inside one is
+/// skipped, so `struct /proc` still lists /proc.
+pub fn skip_virtual(path: &Path) -> Option<&'static str> {
+    if INCLUDE_VIRTUAL.get().copied().unwrap_or(false) {
+        return None;
+    }
+    let kind = virtual_fs(path)?;
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    virtual_fs(parent).is_none().then_some(kind)
+}
+
+/// Walk filter: don't descend into placeholder dirs that would be downloaded,
+/// or into virtual filesystems
+fn stays_local(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() == 0
+        || !entry.file_type().is_dir()
+        || !(keep_offline(entry.path()) || skip_virtual(entry.path()).is_some())
+}
+
+/// Line count for text files; None for binaries (NUL byte in the first 8K)
+pub fn count_lines(path: &Path) -> Option<usize> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        return None;
+    }
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    // A last line without a trailing newline still counts
+    let unterminated = !bytes.is_empty() && !bytes.ends_with(b"\n");
+    Some(newlines + unterminated as usize)
+}
+
+/// Get total size of a directory recursively (placeholder sizes come from
+/// their attributes, so nothing is downloaded); cached like `tally_dir`
 pub fn get_dir_size(path: &Path) -> u64 {
-    WalkDir::new(path)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter_map(|e| e.metadata().ok())
-        .filter(|m| m.is_file())
-        .map(|m| m.len())
-        .sum()
-}
\ No newline at end of file
+    tally_dir(path, None).bytes
+}
+
+/// Whether the files under `path` add up to more than `max` bytes; the walk
+/// stops as soon as they do (`-s` in the summary, which skips such dirs)
+pub fn exceeds_size(path: &Path, max: u64) -> bool {
+    let mut bytes = 0;
+    for entry in WalkDir::new(path).follow_links(false).into_iter().filter_entry(stays_local).flatten() {
+        match entry.metadata() {
+            Ok(meta) if meta.is_file() => bytes += meta.len(),
+            _ => {}
+        }
+        if bytes > max {
+            return true;
+        }
+    }
+    false
+}
+
+/// Bytes, file count and unreadable entries of `path` and of every directory
+/// below it, from a single walk (a file counts toward each of its ancestors
+/// up to `path`). Entries that couldn't be read make the bytes a lower bound.
+pub fn subtree_totals(path: &Path) -> HashMap<PathBuf, (u64, usize, usize)> {
+    let mut totals: HashMap<PathBuf, (u64, usize, usize)> = HashMap::new();
+    if skip_virtual(path).is_some() {
+        totals.insert(path.to_path_buf(), (0, 0, 0));
+        return totals;
+    }
+    // Add to `from` and each of its ancestors up to `path`
+    let mut add = |from: &Path, bytes: u64, files: usize, unreadable: usize| {
+        let mut dir = Some(from);
+        while let Some(d) = dir {
+            let total = totals.entry(d.to_path_buf()).or_default();
+            total.0 += bytes;
+            total.1 += files;
+            total.2 += unreadable;
+            if d == path {
+                break;
+            }
+            dir = d.parent();
+        }
+    };
+    for entry in WalkDir::new(path).follow_links(false).into_iter().filter_entry(stays_local) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                if let Some(failed) = e.path() {
+                    add(failed, 0, 0, 1);
+                }
+                continue;
+            }
+        };
+        if entry.file_type().is_dir() {
+            add(entry.path(), 0, 0, 0);
+            continue;
+        }
+        let Some(parent) = entry.path().parent() else { continue };
+        match entry.metadata() {
+            Ok(meta) if meta.is_file() => add(parent, meta.len(), 1, 0),
+            Ok(_) => {}
+            Err(_) => add(parent, 0, 0, 1),
+        }
+    }
+    totals
+}
+
+/// Total size of the files directly inside a directory — no recursion, so
+/// it costs one read of the directory (`--shallow-size`)
+pub fn shallow_dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .map(|rd| {
+            rd.filter_map(|e| e.ok())
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Recursive size of every directory under `root` (root included) from a
+/// single walk, for size checks on many nested dirs at once
+pub fn dir_sizes(root: &Path) -> HashMap<PathBuf, u64> {
+    subtree_totals(root).into_iter().map(|(dir, (bytes, _, _))| (dir, bytes)).collect()
+}
+
+/// File count and total size of a directory, possibly cut short by a time budget
+#[derive(Clone)]
+pub struct DirTally {
+    pub files: usize,
+    pub bytes: u64,
+    pub entries: usize,
+    pub elapsed: Duration,
+    /// false when the budget ran out and the numbers are a lower bound
+    pub complete: bool,
+    /// Entries that couldn't be read (permissions, vanished mid-walk): the
+    /// numbers are a lower bound too
+    pub unreadable: usize,
+    /// Restored from an earlier run's size cache, which only notices changes
+    /// near the top of the directory: the numbers may be out of date
+    pub saved: bool,
+}
+
+/// How many complete tallies are kept for reuse within one run
+const TALLY_CACHE_SIZE: usize = 256;
+/// Tallies of at least this many entries are also saved for later runs
+const PERSIST_MIN_ENTRIES: usize = 1000;
+
+/// Least-recently-used cache of directory tallies, keyed by canonical path.
+/// An entry is only reused while the directory's signature (see
+/// `tree_signature`) is unchanged, so a long `struct serve` session doesn't
+/// hand out counts for a rebuilt dir. Big tallies are kept across runs in
+/// the size cache file.
+#[derive(Default)]
+struct TallyCache {
+    entries: HashMap<PathBuf, (DirTally, Option<u64>, u64)>,
+    tick: u64,
+    stats: TallyStats,
+    /// A big tally was added since the size cache file was read
+    dirty: bool,
+}
+
+/// Work done counting directories so far, for --timings
+#[derive(Clone, Copy, Default)]
+pub struct TallyStats {
+    /// Tallies served from the cache
+    pub hits: usize,
+    /// Directories actually walked
+    pub walks: usize,
+    /// Time spent on directory counts, cache checks included
+    pub walk_time: Duration,
+}
+
+static TALLY_CACHE: OnceLock<Mutex<TallyCache>> = OnceLock::new();
+
+fn tally_cache() -> &'static Mutex<TallyCache> {
+    TALLY_CACHE.get_or_init(|| Mutex::new(load_size_cache()))
+}
+
+/// Whether this run may write its caches (off under `--sandbox`)
+static READ_ONLY: OnceLock<bool> = OnceLock::new();
+
+/// Keep the size and last-commit caches from being written for this run
+pub fn set_read_only(read_only: bool) {
+    let _ = READ_ONLY.set(read_only);
+}
+
+/// Whether caches are left as they are this run
+pub fn read_only() -> bool {
+    READ_ONLY.get().copied().unwrap_or(false)
+}
+
+/// struct's cache dir: `$XDG_CACHE_HOME/struct`, else `~/.cache/struct`
+pub fn cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()).map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(".cache")
+    });
+    base.join("struct")
+}
+
+/// The size cache file: `sizes.txt` in the cache dir
+pub fn size_cache_path() -> PathBuf {
+    cache_dir().join("sizes.txt")
+}
+
+/// Tallies saved by earlier runs, one per line:
+/// `signature<TAB>files<TAB>bytes<TAB>entries<TAB>path`
+fn load_size_cache() -> TallyCache {
+    let mut cache = TallyCache::default();
+    let text = fs::read_to_string(size_cache_path()).unwrap_or_default();
+    for line in text.lines() {
+        let mut fields = line.splitn(5, '\t');
+        let mut next = || fields.next().unwrap_or_default();
+        let (Ok(signature), Ok(files), Ok(bytes), Ok(entries), path) =
+            (next().parse(), next().parse(), next().parse(), next().parse(), next())
+        else {
+            continue;
+        };
+        if path.is_empty() {
+            continue;
+        }
+        let tally = DirTally { files, bytes, entries, elapsed: Duration::ZERO, complete: true, unreadable: 0, saved: true };
+        cache.entries.insert(PathBuf::from(path), (tally, Some(signature), 0));
+    }
+    cache
+}
+
+/// Write the big tallies back to the size cache file when this run added
+/// any. Failures are ignored: the cache only saves time.
+pub fn save_size_cache() {
+    let Ok(cache) = tally_cache().lock() else { return };
+    if !cache.dirty || read_only() {
+        return;
+    }
+    let mut out = String::new();
+    for (path, (tally, signature, _)) in &cache.entries {
+        // A count with unreadable parts may be complete next time (permissions fixed)
+        if let (Some(signature), true) = (signature, tally.entries >= PERSIST_MIN_ENTRIES && tally.unreadable == 0) {
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                signature,
+                tally.files,
+                tally.bytes,
+                tally.entries,
+                path.display()
+            ));
+        }
+    }
+    let file = size_cache_path();
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(file, out);
+}
+
+/// What a cached tally is checked against: the mtimes of the directory and
+/// of its direct subdirectories, FNV-1a hashed so the value in the size cache
+/// file means the same to every build. Cheap enough to run before each
+/// lookup: installing a package or a build replacing a top-level folder
+/// changes it, a file changed deeper down doesn't (hence `DirTally::saved`).
+/// None when the deadline passes first.
+fn tree_signature(path: &Path, deadline: Option<Instant>) -> Option<u64> {
+    let nanos = |m: fs::Metadata| m.modified().ok()?.duration_since(SystemTime::UNIX_EPOCH).ok().map(|d| d.as_nanos());
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(path).ok()? {
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            return None;
+        }
+        let entry = entry.ok()?;
+        if entry.file_type().ok()?.is_dir() {
+            dirs.push((entry.file_name(), nanos(entry.metadata().ok()?)?));
+        }
+    }
+    dirs.sort();
+
+    let mut hash = fnv1a(FNV_OFFSET, &nanos(fs::metadata(path).ok()?)?.to_le_bytes());
+    for (name, mtime) in dirs {
+        hash = fnv1a(hash, name.as_encoded_bytes());
+        hash = fnv1a(hash, &mtime.to_le_bytes());
+    }
+    Some(hash)
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a, continuing from `hash`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, b| (h ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Cache hits and walks for directory counts in this run
+pub fn tally_stats() -> TallyStats {
+    tally_cache().lock().map(|c| c.stats).unwrap_or_default()
+}
+
+/// Count files and bytes under `path`, reusing an earlier complete count of
+/// the same directory from this run or a saved one when there is one
+pub fn tally_dir(path: &Path, budget: Option<Duration>) -> DirTally {
+    let start = Instant::now();
+    if skip_virtual(path).is_some() {
+        return DirTally { files: 0, bytes: 0, entries: 1, elapsed: Duration::ZERO, complete: true, unreadable: 0, saved: false };
+    }
+    let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let signature = tree_signature(path, budget.map(|b| start + b));
+
+    if let Ok(mut cache) = tally_cache().lock() {
+        cache.tick += 1;
+        let tick = cache.tick;
+        if let Some((tally, seen, used)) = cache.entries.get_mut(&key) {
+            if *seen == signature && signature.is_some() {
+                *used = tick;
+                let mut tally = tally.clone();
+                tally.elapsed = start.elapsed();
+                cache.stats.hits += 1;
+                cache.stats.walk_time += tally.elapsed;
+                return tally;
+            }
+        }
+    }
+
+    // The signature check is part of the budget
+    let mut tally = walk_tally(path, budget.map(|b| b.saturating_sub(start.elapsed())));
+    tally.elapsed = start.elapsed();
+
+    if let Ok(mut cache) = tally_cache().lock() {
+        cache.stats.walks += 1;
+        cache.stats.walk_time += tally.elapsed;
+        // Budget-limited counts are lower bounds — don't hand them out again
+        if tally.complete {
+            if cache.entries.len() >= TALLY_CACHE_SIZE && !cache.entries.contains_key(&key) {
+                let oldest = cache.entries.iter().min_by_key(|(_, e)| e.2).map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    cache.entries.remove(&oldest);
+                }
+            }
+            let tick = cache.tick;
+            cache.dirty |= tally.entries >= PERSIST_MIN_ENTRIES;
+            cache.entries.insert(key, (tally.clone(), signature, tick));
+        }
+    }
+    tally
+}
+
+/// Count files and bytes under `path`, its subdirectories walked in
+/// parallel, giving up once `budget` is spent
+fn walk_tally(path: &Path, budget: Option<Duration>) -> DirTally {
+    let start = Instant::now();
+    let deadline = budget.map(|b| start + b);
+    // The directory itself counts as an entry, as in a walk
+    let mut tally = DirTally { files: 0, bytes: 0, entries: 1, elapsed: Duration::ZERO, complete: true, unreadable: 0, saved: false };
+    let mut subdirs = Vec::new();
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => Some(entries),
+        Err(_) => {
+            tally.unreadable += 1;
+            None
+        }
+    };
+    for entry in entries.into_iter().flatten() {
+        let Ok(entry) = entry else {
+            tally.unreadable += 1;
+            continue;
+        };
+        tally.entries += 1;
+        let Ok(kind) = entry.file_type() else {
+            tally.unreadable += 1;
+            continue;
+        };
+        if kind.is_dir() {
+            if !keep_offline(&entry.path()) && skip_virtual(&entry.path()).is_none() {
+                subdirs.push(entry.path());
+            }
+        } else if kind.is_file() {
+            tally.files += 1;
+            match entry.metadata() {
+                Ok(m) => tally.bytes += m.len(),
+                Err(_) => tally.unreadable += 1,
+            }
+        }
+    }
+
+    for part in parallel_map(&subdirs, |dir| walk_tally_serial(dir, deadline)) {
+        tally.files += part.files;
+        tally.bytes += part.bytes;
+        // Each part counted its own root, which was already counted above
+        tally.entries += part.entries - 1;
+        tally.complete &= part.complete;
+        tally.unreadable += part.unreadable;
+    }
+    tally.elapsed = start.elapsed();
+    tally
+}
+
+/// One subdirectory's share of `walk_tally`, on a single thread
+fn walk_tally_serial(path: &Path, deadline: Option<Instant>) -> DirTally {
+    let mut tally = DirTally { files: 0, bytes: 0, entries: 0, elapsed: Duration::ZERO, complete: true, unreadable: 0, saved: false };
+    for entry in WalkDir::new(path).follow_links(false).into_iter().filter_entry(stays_local) {
+        let Ok(entry) = entry else {
+            tally.unreadable += 1;
+            continue;
+        };
+        tally.entries += 1;
+        if entry.file_type().is_file() {
+            tally.files += 1;
+            match entry.metadata() {
+                Ok(m) => tally.bytes += m.len(),
+                Err(_) => tally.unreadable += 1,
+            }
+        }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            tally.complete = false;
+            break;
+        }
+    }
+    tally
+}
+
+thread_local! {
+    /// Set on `parallel_map` workers, so work nested inside one runs inline
+    /// instead of starting a pool per item
+    static IN_WORKER: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// `f` over every item on a small pool of threads, results in input order
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(items.len());
+    if workers <= 1 || IN_WORKER.with(|w| w.get()) {
+        return items.iter().map(f).collect();
+    }
+
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    IN_WORKER.with(|w| w.set(true));
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(item) = items.get(i) else { break };
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Names typed after `@` or used as file names (bookmarks, snapshots): only
+/// what needs no quoting
+pub fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
+
+/// Expand a leading `~` to $HOME
+pub fn expand_tilde(path: &str) -> PathBuf {
+    if path == "~" || path.starts_with("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return PathBuf::from(home).join(path.trim_start_matches('~').trim_start_matches('/'));
+        }
+    }
+    PathBuf::from(path)
+}